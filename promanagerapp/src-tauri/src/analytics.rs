@@ -0,0 +1,530 @@
+use crate::models::*;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, Utc};
+use std::collections::HashMap;
+
+// Flow metrics over task status history
+//
+// Lead time runs from creation to completion, cycle time from the first move
+// to in progress until completion. Time in status measures every interval a
+// task spent in todo or in progress; intervals still open count up to `now`.
+
+pub struct MetricsRange {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl MetricsRange {
+    fn contains(&self, at: DateTime<Utc>) -> bool {
+        self.from.map_or(true, |from| at >= from) && self.to.map_or(true, |to| at <= to)
+    }
+}
+
+pub fn flow_metrics(
+    tasks: &[Task],
+    transitions: &[StatusTransition],
+    range: &MetricsRange,
+    now: DateTime<Utc>,
+) -> FlowMetrics {
+    let mut by_task: HashMap<&str, Vec<&StatusTransition>> = HashMap::new();
+    for transition in transitions {
+        by_task.entry(transition.task_id.as_str()).or_default().push(transition);
+    }
+    for history in by_task.values_mut() {
+        history.sort_by_key(|t| t.transitioned_at);
+    }
+
+    let mut lead_times = Vec::new();
+    let mut cycle_times = Vec::new();
+    let mut in_status: HashMap<String, Vec<f64>> = HashMap::new();
+
+    for task in tasks {
+        let history = by_task.get(task.id.as_str()).map(Vec::as_slice).unwrap_or(&[]);
+
+        if let Some(done_at) = completion_time(task, history) {
+            if range.contains(done_at) {
+                lead_times.push(hours_between(task.created_at, done_at));
+
+                let started = history
+                    .iter()
+                    .find(|t| t.to_status == TaskStatus::InProgress)
+                    .map(|t| t.transitioned_at);
+                if let Some(started) = started.filter(|s| *s <= done_at) {
+                    cycle_times.push(hours_between(started, done_at));
+                }
+            }
+        }
+
+        for (i, transition) in history.iter().enumerate() {
+            if transition.to_status == TaskStatus::Done {
+                continue;
+            }
+            let end = match history.get(i + 1) {
+                Some(next) => next.transitioned_at,
+                None if task.completed => continue,
+                None => now,
+            };
+            if range.contains(end) {
+                in_status
+                    .entry(transition.to_status.to_string())
+                    .or_default()
+                    .push(hours_between(transition.transitioned_at, end));
+            }
+        }
+    }
+
+    let mut time_in_status: Vec<StatusDuration> = in_status
+        .into_iter()
+        .filter_map(|(status, hours)| {
+            duration_stats(hours).map(|stats| StatusDuration {
+                status: TaskStatus::from(status),
+                stats,
+            })
+        })
+        .collect();
+    time_in_status.sort_by_key(|s| s.status.to_string());
+
+    FlowMetrics {
+        lead_time: duration_stats(lead_times),
+        cycle_time: duration_stats(cycle_times),
+        time_in_status,
+    }
+}
+
+fn completion_time(task: &Task, history: &[&StatusTransition]) -> Option<DateTime<Utc>> {
+    task.completed_at.or_else(|| {
+        history
+            .iter()
+            .rev()
+            .find(|t| t.to_status == TaskStatus::Done)
+            .map(|t| t.transitioned_at)
+    })
+}
+
+fn hours_between(start: DateTime<Utc>, end: DateTime<Utc>) -> f64 {
+    (end - start).num_seconds().max(0) as f64 / 3600.0
+}
+
+pub fn duration_stats(mut hours: Vec<f64>) -> Option<DurationStats> {
+    if hours.is_empty() {
+        return None;
+    }
+    hours.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    Some(DurationStats {
+        count: hours.len(),
+        mean_hours: hours.iter().sum::<f64>() / hours.len() as f64,
+        median_hours: percentile(&hours, 50.0),
+        p85_hours: percentile(&hours, 85.0),
+        max_hours: hours[hours.len() - 1],
+    })
+}
+
+// Nearest-rank percentile of an ascending, non-empty slice
+pub fn percentile(sorted: &[f64], p: f64) -> f64 {
+    let rank = ((p / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+// Burndown and cumulative flow
+//
+// Both replay history rather than reading current task state: status comes
+// from the transition log, while project membership and the completed flag
+// are rebuilt from the activity log, so moving or reopening a task later does
+// not rewrite past days. Each day is sampled at its end (UTC), or at `now` for
+// today. Deleted tasks leave no row behind and are not counted.
+
+pub struct TaskHistory<'a> {
+    pub transitions: &'a [StatusTransition],
+    // Activity entries for the task's `project_id`, `completed` and `estimate_hours` fields
+    pub changes: &'a [Activity],
+}
+
+struct DaySnapshot {
+    date: NaiveDate,
+    // (status, completed, amount in the chart's unit) of each task in the project
+    statuses: Vec<(TaskStatus, bool, f64)>,
+}
+
+pub fn burndown(
+    project_id: &str,
+    tasks: &[Task],
+    history: &TaskHistory,
+    from: NaiveDate,
+    to: NaiveDate,
+    unit: ChartUnit,
+    now: DateTime<Utc>,
+) -> Vec<BurndownPoint> {
+    let snapshots = replay(project_id, tasks, history, from, to, unit, now);
+    let remaining: Vec<f64> = snapshots
+        .iter()
+        .map(|day| {
+            day.statuses
+                .iter()
+                .filter(|(status, completed, _)| !completed && *status != TaskStatus::Done)
+                .map(|(_, _, amount)| amount)
+                .sum()
+        })
+        .collect();
+
+    // Straight line from the first day's scope down to zero on the last day
+    let start = remaining.first().copied().unwrap_or(0.0);
+    let steps = (remaining.len().max(2) - 1) as f64;
+
+    snapshots
+        .iter()
+        .zip(remaining)
+        .enumerate()
+        .map(|(i, (day, remaining))| BurndownPoint {
+            date: day.date,
+            remaining,
+            ideal: start * (1.0 - i as f64 / steps),
+        })
+        .collect()
+}
+
+pub fn cumulative_flow(
+    project_id: &str,
+    tasks: &[Task],
+    history: &TaskHistory,
+    from: NaiveDate,
+    to: NaiveDate,
+    unit: ChartUnit,
+    now: DateTime<Utc>,
+) -> Vec<CumulativeFlowPoint> {
+    replay(project_id, tasks, history, from, to, unit, now)
+        .into_iter()
+        .map(|day| {
+            let total = |status: TaskStatus| {
+                day.statuses
+                    .iter()
+                    .filter(|(s, _, _)| *s == status)
+                    .map(|(_, _, amount)| amount)
+                    .sum()
+            };
+            CumulativeFlowPoint {
+                date: day.date,
+                todo: total(TaskStatus::Todo),
+                in_progress: total(TaskStatus::InProgress),
+                done: total(TaskStatus::Done),
+            }
+        })
+        .collect()
+}
+
+fn replay(
+    project_id: &str,
+    tasks: &[Task],
+    history: &TaskHistory,
+    from: NaiveDate,
+    to: NaiveDate,
+    unit: ChartUnit,
+    now: DateTime<Utc>,
+) -> Vec<DaySnapshot> {
+    let mut transitions: HashMap<&str, Vec<&StatusTransition>> = HashMap::new();
+    for transition in history.transitions {
+        transitions.entry(transition.task_id.as_str()).or_default().push(transition);
+    }
+    for list in transitions.values_mut() {
+        list.sort_by_key(|t| t.transitioned_at);
+    }
+
+    let mut changes: HashMap<(&str, &str), Vec<&Activity>> = HashMap::new();
+    for change in history.changes {
+        if let Some(field) = change.field.as_deref() {
+            changes.entry((change.entity_id.as_str(), field)).or_default().push(change);
+        }
+    }
+    for list in changes.values_mut() {
+        list.sort_by_key(|c| c.id);
+    }
+
+    from.iter_days()
+        .take_while(|date| *date <= to)
+        .map(|date| {
+            let at = date.and_hms_opt(23, 59, 59).unwrap().and_utc().min(now);
+            let statuses = tasks
+                .iter()
+                .filter(|task| task.created_at <= at)
+                .filter(|task| {
+                    let moves = changes.get(&(task.id.as_str(), "project_id")).map_or(&[][..], Vec::as_slice);
+                    field_value_at(moves, task.project_id.clone(), at).as_deref() == Some(project_id)
+                })
+                .map(|task| {
+                    let status = transitions
+                        .get(task.id.as_str())
+                        .and_then(|list| list.iter().rev().find(|t| t.transitioned_at <= at))
+                        .map_or_else(|| task.status.clone(), |t| t.to_status.clone());
+                    let toggles = changes.get(&(task.id.as_str(), "completed")).map_or(&[][..], Vec::as_slice);
+                    let amount = match unit {
+                        ChartUnit::Tasks => 1.0,
+                        ChartUnit::EstimateHours => {
+                            let estimates = changes.get(&(task.id.as_str(), "estimate_hours")).map_or(&[][..], Vec::as_slice);
+                            field_value_at(estimates, task.estimate_hours.map(|h| h.to_string()), at)
+                                .and_then(|hours| hours.parse().ok())
+                                .unwrap_or(0.0)
+                        }
+                    };
+                    (status, completed_by(task, toggles, at), amount)
+                })
+                .collect();
+            DaySnapshot { date, statuses }
+        })
+        .collect()
+}
+
+// Value of a field at `at`: the newest change made by then, else the value the
+// first later change replaced, else the current value
+fn field_value_at(changes: &[&Activity], current: Option<String>, at: DateTime<Utc>) -> Option<String> {
+    match changes.iter().rev().find(|c| c.created_at <= at) {
+        Some(change) => change.new_value.clone(),
+        None => match changes.first() {
+            Some(first) => first.old_value.clone(),
+            None => current,
+        },
+    }
+}
+
+fn completed_by(task: &Task, changes: &[&Activity], at: DateTime<Utc>) -> bool {
+    if changes.is_empty() {
+        // Never toggled since creation, e.g. imported as already done
+        return task.completed && task.completed_at.map_or(true, |done| done <= at);
+    }
+    field_value_at(changes, None, at).as_deref() == Some("true")
+}
+
+// Monte Carlo forecasting
+//
+// Each trial burns down the remaining tasks by drawing a random past day's
+// throughput (completions per day, zero days included) until nothing is left.
+// The spread of trial lengths gives the completion date percentiles.
+
+pub const FORECAST_PERCENTILES: [u8; 3] = [50, 85, 95];
+
+// A trial that has not finished after this many days is cut off
+const MAX_FORECAST_DAYS: i64 = 3650;
+
+// More trials hardly change the percentiles but keep a core busy for longer
+pub const MAX_FORECAST_TRIALS: u32 = 100_000;
+
+// Ten years of history is more than any project has, and bounds the throughput table
+pub const MAX_FORECAST_HISTORY_DAYS: u32 = 3650;
+
+pub struct ForecastOptions {
+    pub trials: u32,
+    pub history_days: u32,
+    pub seed: u64,
+}
+
+/// `completions` are the completion times of the project's finished tasks.
+/// Returns `None` when nothing was completed in the sampled history.
+pub fn forecast(
+    remaining_tasks: i64,
+    completions: &[DateTime<Utc>],
+    options: &ForecastOptions,
+    now: DateTime<Utc>,
+) -> Option<Forecast> {
+    let today = now.date_naive();
+    let history_days = options.history_days.clamp(1, MAX_FORECAST_HISTORY_DAYS) as i64;
+    let first_day = today - Duration::days(history_days - 1);
+
+    let mut throughput = vec![0i64; history_days as usize];
+    for done_at in completions {
+        let day = done_at.date_naive();
+        if day >= first_day && day <= today {
+            throughput[(day - first_day).num_days() as usize] += 1;
+        }
+    }
+    if throughput.iter().all(|n| *n == 0) {
+        return None;
+    }
+
+    let mut rng = SplitMix64(options.seed);
+    let trials = options.trials.clamp(1, MAX_FORECAST_TRIALS);
+    let mut durations: Vec<f64> = (0..trials)
+        .map(|_| {
+            let mut left = remaining_tasks;
+            let mut days = 0;
+            while left > 0 && days < MAX_FORECAST_DAYS {
+                left -= throughput[rng.below(throughput.len())];
+                days += 1;
+            }
+            days as f64
+        })
+        .collect();
+    durations.sort_by(|a, b| a.partial_cmp(b).unwrap());
+
+    Some(Forecast {
+        remaining_tasks,
+        trials,
+        history_days: history_days as u32,
+        seed: options.seed,
+        percentiles: FORECAST_PERCENTILES
+            .iter()
+            .map(|p| {
+                let days = percentile(&durations, *p as f64) as i64;
+                ForecastPercentile {
+                    percentile: *p,
+                    days,
+                    date: today + Duration::days(days),
+                }
+            })
+            .collect(),
+    })
+}
+
+// Small, seedable generator so that a given seed always replays the same forecast
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+// Milestone health
+//
+// A milestone is at risk when the project's recent daily throughput would not
+// clear its open tasks by the end of the target day.
+
+pub fn milestone_health(total: i64, completed: i64, days_left: i64, daily_throughput: f64) -> MilestoneHealth {
+    let open = total - completed;
+    if total > 0 && open == 0 {
+        return MilestoneHealth::Completed;
+    }
+    if days_left < 0 {
+        return MilestoneHealth::Overdue;
+    }
+    if open == 0 {
+        return MilestoneHealth::OnTrack;
+    }
+    if daily_throughput <= 0.0 || open as f64 / daily_throughput > (days_left + 1) as f64 {
+        MilestoneHealth::AtRisk
+    } else {
+        MilestoneHealth::OnTrack
+    }
+}
+
+// Workload per person
+//
+// Open tasks are bucketed by the week (starting Monday) they are due in.
+
+pub fn workload(people: Vec<Person>, open_tasks: &[Task]) -> Vec<Workload> {
+    people
+        .into_iter()
+        .map(|person| {
+            let tasks: Vec<&Task> = open_tasks
+                .iter()
+                .filter(|task| task.assignee_ids.contains(&person.id))
+                .collect();
+
+            let mut weeks: Vec<WeekLoad> = Vec::new();
+            for task in &tasks {
+                let week_start = task.due_date.map(|due| {
+                    let day = due.date_naive();
+                    day - Duration::days(day.weekday().num_days_from_monday() as i64)
+                });
+                let index = match weeks.iter().position(|week| week.week_start == week_start) {
+                    Some(index) => index,
+                    None => {
+                        weeks.push(WeekLoad { week_start, open_tasks: 0, estimate_hours: 0.0 });
+                        weeks.len() - 1
+                    }
+                };
+                weeks[index].open_tasks += 1;
+                weeks[index].estimate_hours += task.estimate_hours.unwrap_or(0.0);
+            }
+            weeks.sort_by_key(|week| (week.week_start.is_none(), week.week_start));
+
+            Workload {
+                open_tasks: tasks.len() as i64,
+                estimate_hours: tasks.iter().filter_map(|task| task.estimate_hours).sum(),
+                unestimated_tasks: tasks.iter().filter(|task| task.estimate_hours.is_none()).count() as i64,
+                weeks,
+                person,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 3, 15, 12, 0, 0).unwrap()
+    }
+
+    fn options(seed: u64) -> ForecastOptions {
+        ForecastOptions {
+            trials: 2_000,
+            history_days: 30,
+            seed,
+        }
+    }
+
+    // None, one or two completions on each of the last 30 days
+    fn completions() -> Vec<DateTime<Utc>> {
+        (0..30)
+            .flat_map(|day| (0..day % 3).map(move |_| now() - Duration::days(day)))
+            .collect()
+    }
+
+    fn days(forecast: &Forecast) -> Vec<(u8, i64)> {
+        forecast.percentiles.iter().map(|p| (p.percentile, p.days)).collect()
+    }
+
+    #[test]
+    fn same_seed_gives_same_forecast() {
+        let first = forecast(40, &completions(), &options(7), now()).unwrap();
+        let again = forecast(40, &completions(), &options(7), now()).unwrap();
+        assert_eq!(days(&first), days(&again));
+
+        let percentiles: Vec<u8> = first.percentiles.iter().map(|p| p.percentile).collect();
+        assert_eq!(percentiles, FORECAST_PERCENTILES);
+        // At about a task a day, 40 tasks take weeks, and later percentiles never come sooner
+        assert!(first.percentiles.windows(2).all(|pair| pair[0].days <= pair[1].days));
+        assert!(first.percentiles[0].days > 20);
+        for p in &first.percentiles {
+            assert_eq!(p.date, now().date_naive() + Duration::days(p.days));
+        }
+    }
+
+    #[test]
+    fn no_completions_in_history_gives_no_forecast() {
+        assert!(forecast(10, &[], &options(1), now()).is_none());
+        let too_old = vec![now() - Duration::days(60)];
+        assert!(forecast(10, &too_old, &options(1), now()).is_none());
+    }
+
+    #[test]
+    fn no_open_tasks_finish_today() {
+        let done = forecast(0, &completions(), &options(3), now()).unwrap();
+        assert_eq!(done.remaining_tasks, 0);
+        assert!(done.percentiles.iter().all(|p| p.days == 0 && p.date == now().date_naive()));
+    }
+
+    #[test]
+    fn trials_are_capped() {
+        let mut options = options(5);
+        options.trials = u32::MAX;
+        let capped = forecast(3, &completions(), &options, now()).unwrap();
+        assert_eq!(capped.trials, MAX_FORECAST_TRIALS);
+    }
+
+    #[test]
+    fn history_is_capped() {
+        let mut options = options(5);
+        options.history_days = u32::MAX;
+        let capped = forecast(3, &completions(), &options, now()).unwrap();
+        assert_eq!(capped.history_days, MAX_FORECAST_HISTORY_DAYS);
+    }
+}
@@ -0,0 +1,800 @@
+use crate::database::Database;
+use crate::error::{self, Error};
+use crate::models::*;
+use crate::workspaces::{self, DatabaseGuard, WorkspaceState};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::net::{Ipv4Addr, SocketAddr, TcpListener};
+use std::ops::Deref;
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+use uuid::Uuid;
+
+const DEFAULT_PER_PAGE: usize = 50;
+const MAX_PER_PAGE: usize = 200;
+
+#[derive(Clone)]
+struct ApiContext {
+    workspaces: WorkspaceState,
+    token: String,
+}
+
+pub type ApiServerState = Mutex<Option<ApiServer>>;
+
+/// A running server; dropping the shutdown sender stops it.
+pub struct ApiServer {
+    pub info: ApiServerInfo,
+    shutdown: oneshot::Sender<()>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ApiServerInfo {
+    pub url: String,
+    pub port: u16,
+    pub token: String,
+}
+
+impl ApiServer {
+    /// Binds to localhost and serves the API on the Tauri async runtime.
+    /// A `port` of 0 lets the OS pick a free port, and a random token is
+    /// generated unless one is given.
+    pub fn start(workspaces: WorkspaceState, port: u16, token: Option<String>) -> std::io::Result<ApiServer> {
+        let listener = TcpListener::bind(SocketAddr::from((Ipv4Addr::LOCALHOST, port)))?;
+        listener.set_nonblocking(true)?;
+        let port = listener.local_addr()?.port();
+
+        let token = token.unwrap_or_else(|| Uuid::new_v4().simple().to_string());
+        let app = router(ApiContext {
+            workspaces,
+            token: token.clone(),
+        });
+
+        let (shutdown, rx) = oneshot::channel::<()>();
+        tauri::async_runtime::spawn(async move {
+            // Registering the listener needs the runtime, so it happens inside the task
+            let server = match axum::Server::from_tcp(listener) {
+                Ok(builder) => builder.serve(app.into_make_service()),
+                Err(e) => {
+                    eprintln!("REST API server error: {}", e);
+                    return;
+                }
+            };
+            let graceful = server.with_graceful_shutdown(async {
+                rx.await.ok();
+            });
+            if let Err(e) = graceful.await {
+                eprintln!("REST API server error: {}", e);
+            }
+        });
+
+        Ok(ApiServer {
+            info: ApiServerInfo {
+                url: format!("http://127.0.0.1:{}/api", port),
+                port,
+                token,
+            },
+            shutdown,
+        })
+    }
+
+    pub fn stop(self) {
+        let _ = self.shutdown.send(());
+    }
+}
+
+fn router(ctx: ApiContext) -> Router {
+    let api = Router::new()
+        .route("/projects", get(list_projects).post(create_project))
+        .route(
+            "/projects/:id",
+            get(get_project).patch(update_project).delete(delete_project),
+        )
+        .route("/projects/:id/tasks", get(list_project_tasks))
+        .route("/projects/:id/archive", post(archive_project))
+        .route("/projects/:id/unarchive", post(unarchive_project))
+        .route("/tasks", get(list_tasks).post(create_task))
+        .route("/tasks/today", get(today_tasks))
+        .route("/tasks/upcoming", get(upcoming_tasks))
+        .route(
+            "/tasks/:id",
+            get(get_task).patch(update_task).delete(delete_task),
+        )
+        .route("/tasks/:id/complete", post(complete_task))
+        .route("/tasks/:id/activity", get(task_activity))
+        .route("/activity", get(activity_feed))
+        .route("/stats", get(task_stats))
+        .route("/batch", post(run_batch))
+        .route_layer(middleware::from_fn_with_state(ctx.clone(), require_token))
+        // The OpenAPI document is public so tools can discover the API before authenticating
+        .route("/openapi.json", get(openapi_document));
+
+    Router::new().nest("/api", api).with_state(ctx)
+}
+
+// Errors
+
+struct ApiError {
+    status: StatusCode,
+    message: String,
+    // The record as it is now, for edit conflicts
+    current: Option<Value>,
+}
+
+impl ApiError {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        ApiError {
+            status,
+            message: message.into(),
+            current: None,
+        }
+    }
+}
+
+impl From<Error> for ApiError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Conflict(conflict) => {
+                let mut error = ApiError::new(StatusCode::CONFLICT, conflict.to_string());
+                error.current = Some(conflict.current);
+                error
+            }
+            e if e.is_not_found() => ApiError::new(StatusCode::NOT_FOUND, "Not found"),
+            // Requests the database refused, such as a failed batch operation
+            Error::Invalid(message) => ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, message),
+            e => ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+        }
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = match self.current {
+            Some(current) => json!({ "error": self.message, "current": current }),
+            None => json!({ "error": self.message }),
+        };
+        (self.status, Json(body)).into_response()
+    }
+}
+
+type ApiResult<T> = Result<T, ApiError>;
+
+// Holds the database lock with changes attributed to the API until dropped
+struct ApiSession<'a> {
+    db: DatabaseGuard<'a>,
+    previous_source: ChangeSource,
+}
+
+impl Deref for ApiSession<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Drop for ApiSession<'_> {
+    fn drop(&mut self) {
+        self.db.set_source(self.previous_source);
+    }
+}
+
+fn lock(ctx: &ApiContext) -> ApiResult<ApiSession<'_>> {
+    let db = ctx.workspaces.database().map_err(|e| {
+        let status = if e == workspaces::LOCKED {
+            StatusCode::LOCKED
+        } else {
+            StatusCode::INTERNAL_SERVER_ERROR
+        };
+        ApiError::new(status, e)
+    })?;
+    let previous_source = db.set_source(ChangeSource::Api);
+    Ok(ApiSession { db, previous_source })
+}
+
+// Runs database work on the blocking thread pool, so a slow query or a wait
+// for the lock doesn't hold up the async runtime the server shares with the app
+async fn with_db<T, F>(ctx: ApiContext, f: F) -> ApiResult<T>
+where
+    T: Send + 'static,
+    F: FnOnce(&Database) -> error::Result<T> + Send + 'static,
+{
+    tokio::task::spawn_blocking(move || {
+        let db = lock(&ctx)?;
+        Ok(f(&db)?)
+    })
+    .await
+    .map_err(|e| ApiError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?
+}
+
+async fn require_token<B>(
+    State(ctx): State<ApiContext>,
+    request: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| constant_time_eq(token.as_bytes(), ctx.token.as_bytes()));
+
+    if !authorized {
+        return ApiError::new(StatusCode::UNAUTHORIZED, "Missing or invalid bearer token").into_response();
+    }
+    next.run(request).await
+}
+
+// Compares every byte whatever the first difference, so response times give
+// away nothing about how much of a guessed token was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// Pagination
+
+#[derive(Debug, Deserialize)]
+struct PageParams {
+    page: Option<usize>,
+    per_page: Option<usize>,
+}
+
+// Listings leave out archived projects and their tasks unless asked
+#[derive(Debug, Deserialize)]
+struct ArchiveParams {
+    #[serde(default)]
+    include_archived: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct Page<T> {
+    items: Vec<T>,
+    page: usize,
+    per_page: usize,
+    total: usize,
+}
+
+impl PageParams {
+    fn bounds(&self) -> (usize, usize) {
+        let page = self.page.unwrap_or(1).max(1);
+        let per_page = self.per_page.unwrap_or(DEFAULT_PER_PAGE).clamp(1, MAX_PER_PAGE);
+        (page, per_page)
+    }
+
+    // Page and page size for the database, rejecting pages so far out that
+    // the rows they end at would overflow
+    fn checked_bounds(&self) -> ApiResult<(i64, i64)> {
+        let (page, per_page) = self.bounds();
+        page.checked_mul(per_page)
+            .and_then(|end| i64::try_from(end).ok())
+            .map(|_| (page as i64, per_page as i64))
+            .ok_or_else(|| ApiError::new(StatusCode::BAD_REQUEST, "page is out of range"))
+    }
+
+    // LIMIT and OFFSET for the query
+    fn limit_offset(&self) -> ApiResult<(i64, i64)> {
+        let (page, per_page) = self.checked_bounds()?;
+        Ok((per_page, (page - 1) * per_page))
+    }
+
+    // Wraps a page the database cut out with `limit_offset`
+    fn page<T>(&self, (items, total): (Vec<T>, i64)) -> Page<T> {
+        let (page, per_page) = self.bounds();
+        Page {
+            items,
+            page,
+            per_page,
+            total: total as usize,
+        }
+    }
+}
+
+// Patch bodies carry everything but the id, which comes from the path
+fn with_id<T: serde::de::DeserializeOwned>(id: String, mut body: Value) -> ApiResult<T> {
+    match body.as_object_mut() {
+        Some(fields) => {
+            fields.insert("id".to_string(), Value::String(id));
+        }
+        None => return Err(ApiError::new(StatusCode::BAD_REQUEST, "Expected a JSON object")),
+    }
+    serde_json::from_value(body).map_err(|e| ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, e.to_string()))
+}
+
+// Project handlers
+
+async fn list_projects(
+    State(ctx): State<ApiContext>,
+    Query(params): Query<PageParams>,
+    Query(archive): Query<ArchiveParams>,
+) -> ApiResult<Json<Page<Project>>> {
+    let (limit, offset) = params.limit_offset()?;
+    let projects = with_db(ctx, move |db| db.get_projects_page(archive.include_archived, limit, offset)).await?;
+    Ok(Json(params.page(projects)))
+}
+
+async fn create_project(
+    State(ctx): State<ApiContext>,
+    Json(project_data): Json<CreateProject>,
+) -> ApiResult<(StatusCode, Json<Project>)> {
+    let project = with_db(ctx, move |db| db.create_project(project_data)).await?;
+    Ok((StatusCode::CREATED, Json(project)))
+}
+
+async fn get_project(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Project>> {
+    Ok(Json(with_db(ctx, move |db| db.get_project(&id)).await?))
+}
+
+async fn update_project(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+    Json(body): Json<Value>,
+) -> ApiResult<Json<Project>> {
+    let project_data: UpdateProject = with_id(id, body)?;
+    Ok(Json(with_db(ctx, move |db| db.update_project(project_data)).await?))
+}
+
+async fn archive_project(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Project>> {
+    Ok(Json(with_db(ctx, move |db| db.archive_project(&id)).await?))
+}
+
+async fn unarchive_project(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Project>> {
+    Ok(Json(with_db(ctx, move |db| db.unarchive_project(&id)).await?))
+}
+
+async fn delete_project(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    with_db(ctx, move |db| {
+        db.get_project(&id)?;
+        db.delete_project(&id)
+    })
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn list_project_tasks(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Json<Page<Task>>> {
+    let (limit, offset) = params.limit_offset()?;
+    let tasks = with_db(ctx, move |db| {
+        db.get_project(&id)?;
+        db.get_tasks_by_project_page(&id, limit, offset)
+    })
+    .await?;
+    Ok(Json(params.page(tasks)))
+}
+
+// Task handlers
+
+async fn list_tasks(
+    State(ctx): State<ApiContext>,
+    Query(params): Query<PageParams>,
+    Query(archive): Query<ArchiveParams>,
+) -> ApiResult<Json<Page<Task>>> {
+    let (limit, offset) = params.limit_offset()?;
+    let tasks = with_db(ctx, move |db| db.get_tasks_page(archive.include_archived, limit, offset)).await?;
+    Ok(Json(params.page(tasks)))
+}
+
+async fn create_task(
+    State(ctx): State<ApiContext>,
+    Json(task_data): Json<CreateTask>,
+) -> ApiResult<(StatusCode, Json<Task>)> {
+    let task = with_db(ctx, move |db| db.create_task(task_data)).await?;
+    Ok((StatusCode::CREATED, Json(task)))
+}
+
+async fn get_task(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+) -> ApiResult<Json<Task>> {
+    Ok(Json(with_db(ctx, move |db| db.get_task(&id)).await?))
+}
+
+async fn update_task(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+    Json(body): Json<Value>,
+) -> ApiResult<Json<Task>> {
+    let task_data: UpdateTask = with_id(id, body)?;
+    Ok(Json(with_db(ctx, move |db| db.update_task(task_data)).await?))
+}
+
+async fn delete_task(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+) -> ApiResult<StatusCode> {
+    with_db(ctx, move |db| {
+        db.get_task(&id)?;
+        db.delete_task(&id)
+    })
+    .await?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[derive(Debug, Deserialize)]
+struct CompleteBody {
+    completed: bool,
+}
+
+async fn complete_task(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+    Json(body): Json<CompleteBody>,
+) -> ApiResult<Json<Task>> {
+    let task_data: UpdateTask = with_id(id, json!({ "completed": body.completed }))?;
+    Ok(Json(with_db(ctx, move |db| db.update_task(task_data)).await?))
+}
+
+async fn today_tasks(State(ctx): State<ApiContext>) -> ApiResult<Json<Vec<Task>>> {
+    Ok(Json(with_db(ctx, |db| db.get_today_tasks()).await?))
+}
+
+async fn upcoming_tasks(State(ctx): State<ApiContext>) -> ApiResult<Json<Vec<Task>>> {
+    Ok(Json(with_db(ctx, |db| db.get_upcoming_tasks()).await?))
+}
+
+async fn task_stats(State(ctx): State<ApiContext>) -> ApiResult<Json<TaskStats>> {
+    Ok(Json(with_db(ctx, |db| db.get_task_stats()).await?))
+}
+
+// Batch handler
+
+async fn run_batch(
+    State(ctx): State<ApiContext>,
+    Json(operations): Json<Vec<BatchOperation>>,
+) -> ApiResult<Json<Vec<BatchResult>>> {
+    Ok(Json(with_db(ctx, move |db| db.batch(operations)).await?))
+}
+
+// Activity handlers
+
+async fn task_activity(
+    State(ctx): State<ApiContext>,
+    Path(id): Path<String>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Json<ActivityPage>> {
+    let (page, per_page) = params.checked_bounds()?;
+    Ok(Json(with_db(ctx, move |db| db.get_task_activity(&id, page, per_page)).await?))
+}
+
+async fn activity_feed(
+    State(ctx): State<ApiContext>,
+    Query(params): Query<PageParams>,
+) -> ApiResult<Json<ActivityPage>> {
+    let (page, per_page) = params.checked_bounds()?;
+    Ok(Json(with_db(ctx, move |db| db.get_activity_feed(page, per_page)).await?))
+}
+
+// OpenAPI
+
+async fn openapi_document() -> Json<Value> {
+    Json(openapi())
+}
+
+fn openapi() -> Value {
+    let id_param = json!([{ "name": "id", "in": "path", "required": true, "schema": { "type": "string" } }]);
+    let page_params = json!([
+        { "name": "page", "in": "query", "schema": { "type": "integer", "minimum": 1, "default": 1 } },
+        { "name": "per_page", "in": "query", "schema": { "type": "integer", "minimum": 1, "maximum": MAX_PER_PAGE, "default": DEFAULT_PER_PAGE } }
+    ]);
+    let listing_params = json!([
+        page_params[0],
+        page_params[1],
+        { "name": "include_archived", "in": "query", "schema": { "type": "boolean", "default": false } }
+    ]);
+    let schema = |name: &str| json!({ "$ref": format!("#/components/schemas/{}", name) });
+    let body = |name: &str| json!({ "required": true, "content": { "application/json": { "schema": schema(name) } } });
+    let ok = |name: &str| json!({ "description": "OK", "content": { "application/json": { "schema": schema(name) } } });
+    let page_of = |name: &str| json!({
+        "description": "OK",
+        "content": { "application/json": { "schema": {
+            "type": "object",
+            "properties": {
+                "items": { "type": "array", "items": schema(name) },
+                "page": { "type": "integer" },
+                "per_page": { "type": "integer" },
+                "total": { "type": "integer" }
+            }
+        } } }
+    });
+    let list_of = |name: &str| json!({
+        "description": "OK",
+        "content": { "application/json": { "schema": { "type": "array", "items": schema(name) } } }
+    });
+    let created = |name: &str| json!({ "description": "Created", "content": { "application/json": { "schema": schema(name) } } });
+    let no_content = json!({ "description": "Deleted" });
+    let not_found = json!({ "description": "Not found", "content": { "application/json": { "schema": schema("Error") } } });
+    let bad_page = json!({ "description": "The page is out of range", "content": { "application/json": { "schema": schema("Error") } } });
+    let conflict = |name: &str| json!({
+        "description": "The record has been changed since `expected_revision`; `current` holds it as it is now",
+        "content": { "application/json": { "schema": {
+            "type": "object",
+            "properties": { "error": { "type": "string" }, "current": schema(name) }
+        } } }
+    });
+
+    let timestamp = json!({ "type": "string", "format": "date-time" });
+    let status = json!({ "type": "string", "enum": ["todo", "in_progress", "done"] });
+    let priority = json!({ "type": "string", "enum": ["low", "medium", "high"] });
+    let project_status = json!({ "type": "string", "enum": ["active", "on_hold", "completed", "archived"] });
+
+    json!({
+        "openapi": "3.0.3",
+        "info": {
+            "title": "ProManager API",
+            "version": env!("CARGO_PKG_VERSION"),
+            "description": "Serves the active workspace. While it is encrypted and locked, every request fails with 423 until it is unlocked in the app."
+        },
+        "servers": [{ "url": "/api" }],
+        "security": [{ "bearerAuth": [] }],
+        "paths": {
+            "/projects": {
+                "get": { "summary": "List projects", "parameters": listing_params, "responses": { "200": page_of("Project"), "400": bad_page } },
+                "post": { "summary": "Create a project", "requestBody": body("CreateProject"), "responses": { "201": created("Project") } }
+            },
+            "/projects/{id}": {
+                "parameters": id_param,
+                "get": { "summary": "Get a project", "responses": { "200": ok("Project"), "404": not_found } },
+                "patch": { "summary": "Update a project", "requestBody": body("UpdateProject"), "responses": { "200": ok("Project"), "404": not_found, "409": conflict("Project") } },
+                "delete": { "summary": "Delete a project and its tasks", "responses": { "204": no_content, "404": not_found } }
+            },
+            "/projects/{id}/archive": {
+                "parameters": id_param,
+                "post": { "summary": "Archive a project, hiding it and its tasks from listings", "responses": { "200": ok("Project"), "404": not_found } }
+            },
+            "/projects/{id}/unarchive": {
+                "parameters": id_param,
+                "post": { "summary": "Make an archived project active again", "responses": { "200": ok("Project"), "404": not_found } }
+            },
+            "/projects/{id}/tasks": {
+                "parameters": id_param,
+                "get": { "summary": "List the tasks of a project", "parameters": page_params, "responses": { "200": page_of("Task"), "400": bad_page, "404": not_found } }
+            },
+            "/tasks": {
+                "get": { "summary": "List tasks", "parameters": listing_params, "responses": { "200": page_of("Task"), "400": bad_page } },
+                "post": { "summary": "Create a task", "requestBody": body("CreateTask"), "responses": { "201": created("Task") } }
+            },
+            "/tasks/today": {
+                "get": { "summary": "Tasks due today", "responses": { "200": list_of("Task") } }
+            },
+            "/tasks/upcoming": {
+                "get": { "summary": "Tasks due in the next week", "responses": { "200": list_of("Task") } }
+            },
+            "/tasks/{id}": {
+                "parameters": id_param,
+                "get": { "summary": "Get a task", "responses": { "200": ok("Task"), "404": not_found } },
+                "patch": { "summary": "Update a task", "requestBody": body("UpdateTask"), "responses": { "200": ok("Task"), "404": not_found, "409": conflict("Task") } },
+                "delete": { "summary": "Delete a task", "responses": { "204": no_content, "404": not_found } }
+            },
+            "/tasks/{id}/complete": {
+                "parameters": id_param,
+                "post": {
+                    "summary": "Mark a task complete or incomplete",
+                    "requestBody": { "required": true, "content": { "application/json": { "schema": {
+                        "type": "object", "required": ["completed"], "properties": { "completed": { "type": "boolean" } }
+                    } } } },
+                    "responses": { "200": ok("Task"), "404": not_found }
+                }
+            },
+            "/tasks/{id}/activity": {
+                "parameters": id_param,
+                "get": { "summary": "Change history of a task", "parameters": page_params, "responses": { "200": ok("ActivityPage"), "400": bad_page } }
+            },
+            "/activity": {
+                "get": { "summary": "Activity feed across all entities", "parameters": page_params, "responses": { "200": ok("ActivityPage"), "400": bad_page } }
+            },
+            "/stats": {
+                "get": { "summary": "Task statistics", "responses": { "200": ok("TaskStats") } }
+            },
+            "/batch": {
+                "post": {
+                    "summary": "Apply several operations in one transaction; if any fails, none are applied",
+                    "requestBody": { "required": true, "content": { "application/json": { "schema": {
+                        "type": "array", "items": schema("BatchOperation")
+                    } } } },
+                    "responses": {
+                        "200": list_of("BatchResult"),
+                        "409": {
+                            "description": "An update's `expected_revision` was out of date and the batch was rolled back; `current` holds the record as it is now",
+                            "content": { "application/json": { "schema": {
+                                "type": "object",
+                                "properties": { "error": { "type": "string" }, "current": { "oneOf": [schema("Project"), schema("Task")] } }
+                            } } }
+                        },
+                        "422": { "description": "An operation failed and the batch was rolled back", "content": { "application/json": { "schema": schema("Error") } } }
+                    }
+                }
+            }
+        },
+        "components": {
+            "securitySchemes": { "bearerAuth": { "type": "http", "scheme": "bearer" } },
+            "schemas": {
+                "Error": { "type": "object", "properties": { "error": { "type": "string" } } },
+                "Project": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "name": { "type": "string" },
+                        "description": { "type": "string", "nullable": true },
+                        "color": { "type": "string" },
+                        "created_at": timestamp,
+                        "updated_at": timestamp,
+                        "status": project_status,
+                        "auto_archive": { "type": "boolean" },
+                        "revision": { "type": "integer" }
+                    }
+                },
+                "CreateProject": {
+                    "type": "object",
+                    "required": ["name", "color"],
+                    "properties": {
+                        "name": { "type": "string" },
+                        "description": { "type": "string", "nullable": true },
+                        "color": { "type": "string" },
+                        "auto_archive": { "type": "boolean", "default": false }
+                    }
+                },
+                "UpdateProject": {
+                    "type": "object",
+                    "description": "Merge patch: missing fields are left unchanged and null clears a nullable field",
+                    "properties": {
+                        "name": { "type": "string" },
+                        "description": { "type": "string", "nullable": true },
+                        "color": { "type": "string" },
+                        "status": project_status,
+                        "auto_archive": { "type": "boolean" },
+                        "expected_revision": { "type": "integer", "description": "Fail with 409 unless the project is still at this revision" }
+                    }
+                },
+                "Task": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "string" },
+                        "title": { "type": "string" },
+                        "description": { "type": "string", "nullable": true },
+                        "project_id": { "type": "string", "nullable": true },
+                        "status": status,
+                        "priority": priority,
+                        "due_date": { "type": "string", "format": "date-time", "nullable": true },
+                        "estimate_hours": { "type": "number", "minimum": 0, "nullable": true },
+                        "completed": { "type": "boolean" },
+                        "completed_at": { "type": "string", "format": "date-time", "nullable": true },
+                        "created_at": timestamp,
+                        "updated_at": timestamp,
+                        "milestone_id": { "type": "string", "nullable": true },
+                        "assignee_ids": { "type": "array", "items": { "type": "string" } },
+                        "revision": { "type": "integer" },
+                        "comment_count": { "type": "integer" },
+                        "checklist_total": { "type": "integer" },
+                        "checklist_checked": { "type": "integer" }
+                    }
+                },
+                "CreateTask": {
+                    "type": "object",
+                    "required": ["title", "status", "priority"],
+                    "properties": {
+                        "title": { "type": "string" },
+                        "description": { "type": "string", "nullable": true },
+                        "project_id": { "type": "string", "nullable": true },
+                        "status": status,
+                        "priority": priority,
+                        "due_date": { "type": "string", "format": "date-time", "nullable": true },
+                        "estimate_hours": { "type": "number", "minimum": 0, "nullable": true },
+                        "milestone_id": { "type": "string", "nullable": true }
+                    }
+                },
+                "UpdateTask": {
+                    "type": "object",
+                    "description": "Merge patch: missing fields are left unchanged and null clears a nullable field",
+                    "properties": {
+                        "title": { "type": "string" },
+                        "description": { "type": "string", "nullable": true },
+                        "project_id": { "type": "string", "nullable": true },
+                        "status": status,
+                        "priority": priority,
+                        "due_date": { "type": "string", "format": "date-time", "nullable": true },
+                        "estimate_hours": { "type": "number", "minimum": 0, "nullable": true },
+                        "completed": { "type": "boolean" },
+                        "milestone_id": { "type": "string", "nullable": true },
+                        "assignee_ids": { "type": "array", "items": { "type": "string" }, "description": "Replaces the assignees; an empty list unassigns everyone" },
+                        "expected_revision": { "type": "integer", "description": "Fail with 409 unless the task is still at this revision" }
+                    }
+                },
+                "BatchOperation": {
+                    "type": "object",
+                    "required": ["op"],
+                    "description": "`project` goes with the project operations, `task` with create_task and update_task, and `id` with the delete and complete operations",
+                    "properties": {
+                        "op": { "type": "string", "enum": ["create_project", "update_project", "delete_project", "create_task", "update_task", "complete_task", "delete_task"] },
+                        "id": { "type": "string" },
+                        "completed": { "type": "boolean", "default": true },
+                        "project": { "type": "object", "description": "CreateProject, or UpdateProject including its id" },
+                        "task": { "type": "object", "description": "CreateTask, or UpdateTask including its id" }
+                    }
+                },
+                "BatchResult": {
+                    "type": "object",
+                    "properties": {
+                        "type": { "type": "string", "enum": ["project", "task", "deleted"] },
+                        "value": { "description": "The project or task, or the id of what was deleted" }
+                    }
+                },
+                "Activity": {
+                    "type": "object",
+                    "properties": {
+                        "id": { "type": "integer" },
+                        "entity": { "type": "string", "enum": ["project", "task", "milestone", "sprint", "template", "reminder", "comment", "attachment", "checklist_item", "person"] },
+                        "entity_id": { "type": "string" },
+                        "action": { "type": "string", "enum": ["created", "updated", "deleted"] },
+                        "field": { "type": "string", "nullable": true },
+                        "old_value": { "type": "string", "nullable": true },
+                        "new_value": { "type": "string", "nullable": true },
+                        "source": { "type": "string", "enum": ["gui", "cli", "api", "import"] },
+                        "created_at": timestamp
+                    }
+                },
+                "ActivityPage": {
+                    "type": "object",
+                    "properties": {
+                        "items": { "type": "array", "items": schema("Activity") },
+                        "total": { "type": "integer" },
+                        "page": { "type": "integer" },
+                        "per_page": { "type": "integer" }
+                    }
+                },
+                "TaskStats": {
+                    "type": "object",
+                    "properties": {
+                        "total_tasks": { "type": "integer" },
+                        "completed_tasks": { "type": "integer" },
+                        "pending_tasks": { "type": "integer" },
+                        "today_tasks": { "type": "integer" },
+                        "overdue_tasks": { "type": "integer" }
+                    }
+                }
+            }
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tokens_must_match_exactly() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"secret"));
+    }
+
+    #[test]
+    fn pages_past_the_end_of_any_table_are_rejected() {
+        let params = |page, per_page| PageParams { page: Some(page), per_page: Some(per_page) };
+        assert_eq!(params(3, 20).limit_offset().ok(), Some((20, 40)));
+        assert_eq!(params(usize::MAX, 1).limit_offset().unwrap_err().status, StatusCode::BAD_REQUEST);
+        assert_eq!(params(usize::MAX / 2, 50).checked_bounds().unwrap_err().status, StatusCode::BAD_REQUEST);
+    }
+}
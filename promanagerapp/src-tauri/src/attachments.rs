@@ -0,0 +1,306 @@
+use crate::database::Database;
+use crate::models::*;
+use crate::workspaces::DEFAULT_WORKSPACE_ID;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use uuid::Uuid;
+
+// Content-addressed attachment store
+//
+// Files are copied into the store under their SHA-256 hash, split git-style
+// into `ab/cdef...`, so attaching the same file twice keeps one copy. The
+// database only records which task refers to which hash. Deleting an
+// attachment removes its file once nothing else refers to it; files left
+// behind by deleted tasks (or by the CLI, which has no store) are removed by
+// `collect_garbage`.
+//
+// Blobs are stored as they are, even for an encrypted workspace: SQLCipher
+// only encrypts the database, so its attachments stay readable on disk.
+// Copies made to open an attachment live in the temp directory until the
+// workspace is locked, when `remove_open_copies` deletes them.
+
+// Prefix of files being copied in, which never count as blobs
+const PARTIAL_PREFIX: &str = ".partial-";
+
+pub struct AttachmentStore {
+    root: PathBuf,
+}
+
+impl AttachmentStore {
+    pub fn new(root: impl Into<PathBuf>) -> AttachmentStore {
+        AttachmentStore { root: root.into() }
+    }
+
+    pub fn blob_path(&self, hash: &str) -> PathBuf {
+        self.root.join(&hash[..2]).join(&hash[2..])
+    }
+
+    /// Copies a file into the store unless it is there already, returning
+    /// its hash and size.
+    pub fn put(&self, source: &Path) -> io::Result<(String, u64)> {
+        let mut input = File::open(source)?;
+        // Copy next to the blobs first so a half-written file never has a
+        // blob's name, and read the source only once so the hash is always
+        // that of what was stored, even if the source changes meanwhile
+        fs::create_dir_all(&self.root)?;
+        let partial = self.root.join(format!("{}{}", PARTIAL_PREFIX, Uuid::new_v4().simple()));
+        let (hash, size) = match copy_hashed(&mut input, &partial) {
+            Ok(copied) => copied,
+            Err(e) => {
+                let _ = fs::remove_file(&partial);
+                return Err(e);
+            }
+        };
+
+        let blob = self.blob_path(&hash);
+        if blob.exists() {
+            fs::remove_file(&partial)?;
+        } else {
+            if let Some(parent) = blob.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::rename(&partial, &blob)?;
+        }
+        Ok((hash, size))
+    }
+
+    fn remove(&self, hash: &str) -> io::Result<()> {
+        match fs::remove_file(self.blob_path(hash)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    // Hashes of every blob, and paths of leftover partial copies
+    fn contents(&self) -> io::Result<(Vec<String>, Vec<PathBuf>)> {
+        let mut hashes = Vec::new();
+        let mut partials = Vec::new();
+        let entries = match fs::read_dir(&self.root) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok((hashes, partials)),
+            Err(e) => return Err(e),
+        };
+        for entry in entries {
+            let entry = entry?;
+            let prefix = entry.file_name().to_string_lossy().to_string();
+            if prefix.starts_with(PARTIAL_PREFIX) {
+                partials.push(entry.path());
+            } else if entry.file_type()?.is_dir() {
+                for blob in fs::read_dir(entry.path())? {
+                    hashes.push(format!("{}{}", prefix, blob?.file_name().to_string_lossy()));
+                }
+            }
+        }
+        Ok((hashes, partials))
+    }
+}
+
+/// The attachment stores of all workspaces. The default workspace keeps
+/// using the folder it had before there were workspaces; every other one gets
+/// a folder beside it, so garbage collection in one never sees another's files.
+pub struct AttachmentStores {
+    root: PathBuf,
+}
+
+impl AttachmentStores {
+    pub fn new(root: impl Into<PathBuf>) -> AttachmentStores {
+        AttachmentStores { root: root.into() }
+    }
+
+    pub fn store(&self, workspace: &Workspace) -> AttachmentStore {
+        AttachmentStore::new(self.folder(workspace))
+    }
+
+    /// Deletes every file of a workspace that is being deleted.
+    pub fn remove(&self, workspace: &Workspace) -> io::Result<()> {
+        match fs::remove_dir_all(self.folder(workspace)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        }
+    }
+
+    fn folder(&self, workspace: &Workspace) -> PathBuf {
+        if workspace.id == DEFAULT_WORKSPACE_ID {
+            return self.root.clone();
+        }
+        let mut name = self.root.file_name().unwrap_or_default().to_os_string();
+        name.push(format!("-{}", workspace.id));
+        self.root.with_file_name(name)
+    }
+}
+
+// Writes `input` to a new file at `path`, returning the hash and size of
+// what was written
+fn copy_hashed(input: &mut impl Read, path: &Path) -> io::Result<(String, u64)> {
+    let mut output = fs::OpenOptions::new().write(true).create_new(true).open(path)?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0; 64 * 1024];
+    let mut size = 0;
+    loop {
+        let read = match input.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => read,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        output.write_all(&buffer[..read])?;
+        hasher.update(&buffer[..read]);
+        size += read as u64;
+    }
+    Ok((format!("{:x}", hasher.finalize()), size))
+}
+
+/// Copies `source` into the store and attaches it to a task.
+pub fn attach(db: &Database, store: &AttachmentStore, task_id: &str, source: &Path) -> Result<Attachment, String> {
+    let name = source
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .ok_or_else(|| format!("{} is not a file", source.display()))?;
+    let (hash, size) = store
+        .put(source)
+        .map_err(|e| format!("Failed to store {}: {}", source.display(), e))?;
+
+    db.add_attachment(CreateAttachment {
+        task_id: task_id.to_string(),
+        mime_type: mime_type(&name).to_string(),
+        name,
+        size: size as i64,
+        hash,
+    })
+    .map_err(|e| e.to_string())
+}
+
+/// Copies an attachment out of the store to `destination`.
+pub fn export(db: &Database, store: &AttachmentStore, id: &str, destination: &Path) -> Result<(), String> {
+    let attachment = db.get_attachment(id).map_err(|e| e.to_string())?;
+    fs::copy(store.blob_path(&attachment.hash), destination)
+        .map(|_| ())
+        .map_err(|e| format!("Failed to export {} to {}: {}", attachment.name, destination.display(), e))
+}
+
+/// Opens an attachment with the default app for its type. Blobs have no
+/// file extension, so a copy under the original name is opened instead.
+pub fn open(db: &Database, store: &AttachmentStore, id: &str) -> Result<PathBuf, String> {
+    let attachment = db.get_attachment(id).map_err(|e| e.to_string())?;
+    let folder = open_copies_folder().join(&attachment.id);
+    let path = folder.join(Path::new(&attachment.name).file_name().unwrap_or_default());
+    fs::create_dir_all(&folder).map_err(|e| e.to_string())?;
+    export(db, store, id, &path)?;
+    open_with_default_app(&path).map_err(|e| format!("Failed to open {}: {}", attachment.name, e))?;
+    Ok(path)
+}
+
+/// Deletes the copies `open` made, so a locked workspace leaves none of its
+/// attachments in the temp directory. Apps they were opened in may still
+/// hold them open.
+pub fn remove_open_copies() -> io::Result<()> {
+    match fs::remove_dir_all(open_copies_folder()) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+fn open_copies_folder() -> PathBuf {
+    std::env::temp_dir().join("promanager-attachments")
+}
+
+/// Deletes an attachment, and its file unless another attachment shares it.
+pub fn delete(db: &Database, store: &AttachmentStore, id: &str) -> Result<(), String> {
+    let attachment = db.delete_attachment(id).map_err(|e| e.to_string())?;
+    let hashes = db.attachment_hashes().map_err(|e| e.to_string())?;
+    if !hashes.contains(&attachment.hash) {
+        store.remove(&attachment.hash).map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Removes every file in the store no attachment refers to, returning how
+/// many there were.
+pub fn collect_garbage(db: &Database, store: &AttachmentStore) -> Result<usize, String> {
+    let referenced = db.attachment_hashes().map_err(|e| e.to_string())?;
+    let (hashes, partials) = store.contents().map_err(|e| e.to_string())?;
+
+    let mut removed = 0;
+    for hash in hashes.iter().filter(|hash| !referenced.contains(*hash)) {
+        store.remove(hash).map_err(|e| e.to_string())?;
+        removed += 1;
+    }
+    // Copies interrupted by a crash; nothing else writes while the database is locked
+    for partial in partials {
+        fs::remove_file(partial).map_err(|e| e.to_string())?;
+    }
+    Ok(removed)
+}
+
+/// MIME type guessed from a file name's extension.
+pub fn mime_type(name: &str) -> &'static str {
+    let extension = Path::new(name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    match extension.as_str() {
+        "pdf" => "application/pdf",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "zip" => "application/zip",
+        "doc" => "application/msword",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xls" => "application/vnd.ms-excel",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "ppt" => "application/vnd.ms-powerpoint",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        _ => "application/octet-stream",
+    }
+}
+
+fn open_with_default_app(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(target_os = "macos")]
+    let mut command = Command::new("open");
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let mut command = Command::new("xdg-open");
+
+    command.arg(path).spawn().map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn put_stores_each_content_once() {
+        let root = std::env::temp_dir().join(format!("promanager-attachments-{}", Uuid::new_v4()));
+        let store = AttachmentStore::new(root.join("store"));
+        let first = root.join("notes.txt");
+        let second = root.join("copy of notes.txt");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(&first, "hello").unwrap();
+        fs::write(&second, "hello").unwrap();
+
+        let (hash, size) = store.put(&first).unwrap();
+        assert_eq!(hash, "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824");
+        assert_eq!(size, 5);
+        assert_eq!(store.put(&second).unwrap(), (hash.clone(), 5));
+        assert_eq!(fs::read_to_string(store.blob_path(&hash)).unwrap(), "hello");
+        // The second copy was dropped rather than left behind as a partial file
+        assert_eq!(store.contents().unwrap(), (vec![hash], Vec::new()));
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,1293 @@
+use app::database::{self, Database};
+use app::digest;
+use app::filter::TaskFilter;
+use app::models::*;
+use app::workspaces::Workspaces;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, Utc};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process;
+
+/// Manage ProManager projects and tasks from the terminal.
+#[derive(Parser)]
+#[command(name = "promanager", version)]
+struct Cli {
+    /// Path to a SQLite database to use instead of a workspace's
+    #[arg(long, global = true, env = "PROMANAGER_DB")]
+    db: Option<String>,
+
+    /// Workspace to use instead of the active one, by name or id
+    #[arg(long, global = true, env = "PROMANAGER_WORKSPACE", conflicts_with = "db")]
+    workspace: Option<String>,
+
+    /// Print JSON instead of a table
+    #[arg(long, global = true)]
+    json: bool,
+
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Work with tasks
+    #[command(subcommand)]
+    Tasks(TaskCommand),
+    /// Work with projects
+    #[command(subcommand)]
+    Projects(ProjectCommand),
+    /// Work with the people tasks are assigned to
+    #[command(subcommand)]
+    People(PersonCommand),
+    /// Work with workspaces, each of which has a database of its own
+    #[command(subcommand)]
+    Workspaces(WorkspaceCommand),
+    /// Show task statistics
+    Stats,
+    /// Show the activity feed, or the history of one task
+    History {
+        /// Task id or unique prefix
+        task: Option<String>,
+        #[arg(long, default_value_t = 1)]
+        page: i64,
+        #[arg(long, default_value_t = 20)]
+        per_page: i64,
+    },
+    /// Print today's digest, or the weekly review
+    Digest {
+        /// Cover the last week instead of the last day
+        #[arg(long)]
+        week: bool,
+        /// Render HTML instead of Markdown
+        #[arg(long)]
+        html: bool,
+        /// Write the digest into this folder instead of printing it
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Print a shell completion script
+    Completions { shell: Shell },
+}
+
+#[derive(Subcommand)]
+enum TaskCommand {
+    /// List tasks, optionally filtered, e.g. `status:todo due:overdue project:Website`
+    List { query: Vec<String> },
+    /// Add a task
+    Add {
+        title: String,
+        #[command(flatten)]
+        fields: TaskFields,
+    },
+    /// Edit a task
+    Edit {
+        id: String,
+        #[arg(long)]
+        title: Option<String>,
+        #[command(flatten)]
+        fields: TaskFields,
+        /// Remove a field's value; may be repeated
+        #[arg(long, value_enum)]
+        clear: Vec<ClearArg>,
+        /// Fail if the task has been changed since this revision
+        #[arg(long)]
+        expect_revision: Option<i64>,
+    },
+    /// Mark a task complete
+    Complete {
+        id: String,
+        /// Mark the task incomplete instead
+        #[arg(long)]
+        undo: bool,
+    },
+    /// Copy a task as a new, not yet started task
+    Duplicate {
+        id: String,
+        /// Title of the copy; defaults to the original title with "(copy)" added
+        #[arg(long)]
+        title: Option<String>,
+        /// Leave the checklist behind
+        #[arg(long)]
+        no_checklist: bool,
+        /// Leave the copy unassigned
+        #[arg(long)]
+        no_assignees: bool,
+    },
+    /// Move tasks to another project
+    Move {
+        #[arg(required = true)]
+        ids: Vec<String>,
+        /// Project name or id
+        #[arg(long, required_unless_present = "no_project")]
+        project: Option<String>,
+        /// Take the tasks out of their project instead
+        #[arg(long, conflicts_with = "project")]
+        no_project: bool,
+    },
+    /// Set a reminder, or list the task's reminders when neither option is given
+    Remind {
+        id: String,
+        /// Remind at a fixed time, as YYYY-MM-DD HH:MM in UTC
+        #[arg(long, value_parser = parse_remind_time)]
+        at: Option<DateTime<Utc>>,
+        /// Remind this many minutes before the task is due
+        #[arg(long, conflicts_with = "at")]
+        before_due: Option<i64>,
+    },
+    /// Comment on a task, or show its comments when no text is given
+    Comment {
+        id: String,
+        /// The comment, in Markdown
+        text: Option<String>,
+        /// Who is commenting; defaults to the current user
+        #[arg(long)]
+        author: Option<String>,
+    },
+    /// Change a task's checklist, or show it when no option is given; items
+    /// are numbered from 1 as listed
+    Checklist {
+        id: String,
+        /// Add an item at the end
+        #[arg(long)]
+        add: Option<String>,
+        /// Check or uncheck an item
+        #[arg(long, conflicts_with = "add")]
+        toggle: Option<usize>,
+        /// Remove an item
+        #[arg(long, conflicts_with_all = ["add", "toggle"])]
+        remove: Option<usize>,
+        /// Turn an item into a task of its own
+        #[arg(long, conflicts_with_all = ["add", "toggle", "remove"])]
+        to_task: Option<usize>,
+    },
+    /// Assign tasks to people, replacing their current assignees
+    Assign {
+        #[arg(required = true)]
+        ids: Vec<String>,
+        /// Person name or id; may be repeated
+        #[arg(long, required_unless_present = "nobody")]
+        to: Vec<String>,
+        /// Unassign the tasks instead
+        #[arg(long, conflicts_with = "to")]
+        nobody: bool,
+    },
+    /// Delete a task
+    Delete { id: String },
+}
+
+#[derive(Args)]
+struct TaskFields {
+    #[arg(long)]
+    description: Option<String>,
+    /// Project name or id
+    #[arg(long)]
+    project: Option<String>,
+    #[arg(long, value_enum)]
+    status: Option<StatusArg>,
+    #[arg(long, value_enum)]
+    priority: Option<PriorityArg>,
+    /// Due date as YYYY-MM-DD
+    #[arg(long, value_parser = parse_due_date)]
+    due: Option<DateTime<Utc>>,
+    /// Expected effort in hours
+    #[arg(long)]
+    estimate: Option<f64>,
+}
+
+#[derive(Subcommand)]
+enum ProjectCommand {
+    /// List projects
+    List {
+        /// Include archived projects
+        #[arg(long)]
+        all: bool,
+    },
+    /// Add a project
+    Add {
+        name: String,
+        #[arg(long)]
+        description: Option<String>,
+        #[arg(long, default_value = "#3b82f6")]
+        color: String,
+        /// Archive the project once all of its tasks are completed
+        #[arg(long)]
+        auto_archive: bool,
+    },
+    /// Edit a project
+    Edit {
+        id: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        description: Option<String>,
+        /// Remove the description
+        #[arg(long, conflicts_with = "description")]
+        clear_description: bool,
+        #[arg(long)]
+        color: Option<String>,
+        #[arg(long, value_enum)]
+        status: Option<ProjectStatusArg>,
+        /// Archive the project once all of its tasks are completed
+        #[arg(long)]
+        auto_archive: Option<bool>,
+        /// Fail if the project has been changed since this revision
+        #[arg(long)]
+        expect_revision: Option<i64>,
+    },
+    /// Archive a project, hiding it and its tasks from listings
+    Archive { id: String },
+    /// Make an archived project active again
+    Unarchive { id: String },
+    /// Move all tasks and milestones of one project into another and delete it
+    Merge { source: String, target: String },
+    /// Delete a project and all of its tasks
+    Delete { id: String },
+}
+
+#[derive(Subcommand)]
+enum PersonCommand {
+    /// List everyone in the directory
+    List,
+    /// Add a person
+    Add {
+        name: String,
+        #[arg(long)]
+        email: Option<String>,
+    },
+    /// Remove a person, unassigning them from their tasks
+    Remove { name: String },
+    /// Show who you are, or set it so `assignee:me` finds your tasks
+    Me {
+        /// Person name or id
+        name: Option<String>,
+        /// Forget who you are
+        #[arg(long, conflicts_with = "name")]
+        clear: bool,
+    },
+    /// Show open tasks and estimated hours per person and week
+    Workload,
+    /// Pass all open tasks of one person to another
+    Handover {
+        from: String,
+        /// Leave the tasks without this person instead of passing them on
+        #[arg(long, required_unless_present = "drop")]
+        to: Option<String>,
+        #[arg(long, conflicts_with = "to")]
+        drop: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum WorkspaceCommand {
+    /// List workspaces, marking the active one
+    List,
+    /// Create an empty workspace
+    Add {
+        name: String,
+        /// Encrypt it with a passphrase, asked for or read from PROMANAGER_NEW_PASSPHRASE
+        #[arg(long)]
+        encrypt: bool,
+    },
+    /// Make a workspace the active one, for the app and the CLI
+    Switch { workspace: String },
+    /// Rename a workspace
+    Rename { workspace: String, name: String },
+    /// Delete a workspace and all of its projects and tasks, unless the app has it open
+    Remove { workspace: String },
+    /// Encrypt the active workspace's database with a passphrase, unless the app has it open; attachments stay unencrypted
+    Encrypt,
+    /// Store the active workspace unencrypted again, unless the app has it open
+    Decrypt,
+    /// Change the passphrase of the active workspace
+    Passphrase,
+    /// Set how long the app waits before locking the active workspace when it is not used
+    AutoLock {
+        #[arg(required_unless_present = "never")]
+        minutes: Option<u32>,
+        /// Only lock it by hand
+        #[arg(long, conflicts_with = "minutes")]
+        never: bool,
+    },
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum StatusArg {
+    Todo,
+    InProgress,
+    Done,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ProjectStatusArg {
+    Active,
+    OnHold,
+    Completed,
+    Archived,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PriorityArg {
+    Low,
+    Medium,
+    High,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ClearArg {
+    Description,
+    Project,
+    Due,
+    Milestone,
+    Estimate,
+}
+
+impl From<ProjectStatusArg> for ProjectStatus {
+    fn from(arg: ProjectStatusArg) -> Self {
+        match arg {
+            ProjectStatusArg::Active => ProjectStatus::Active,
+            ProjectStatusArg::OnHold => ProjectStatus::OnHold,
+            ProjectStatusArg::Completed => ProjectStatus::Completed,
+            ProjectStatusArg::Archived => ProjectStatus::Archived,
+        }
+    }
+}
+
+impl From<StatusArg> for TaskStatus {
+    fn from(arg: StatusArg) -> Self {
+        match arg {
+            StatusArg::Todo => TaskStatus::Todo,
+            StatusArg::InProgress => TaskStatus::InProgress,
+            StatusArg::Done => TaskStatus::Done,
+        }
+    }
+}
+
+impl From<PriorityArg> for TaskPriority {
+    fn from(arg: PriorityArg) -> Self {
+        match arg {
+            PriorityArg::Low => TaskPriority::Low,
+            PriorityArg::Medium => TaskPriority::Medium,
+            PriorityArg::High => TaskPriority::High,
+        }
+    }
+}
+
+fn parse_due_date(s: &str) -> Result<DateTime<Utc>, String> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .map_err(|_| format!("expected a date like 2024-01-31, got '{}'", s))
+}
+
+// A field to update from its new value, or from whether `--clear` named it
+fn patch<T>(value: Option<T>, clear: &[ClearArg], field: ClearArg) -> Result<Patch<T>, String> {
+    match (value, clear.contains(&field)) {
+        (Some(_), true) => Err("A field cannot be both set and cleared".to_string()),
+        (Some(value), false) => Ok(Patch::Set(value)),
+        (None, true) => Ok(Patch::Clear),
+        (None, false) => Ok(Patch::Unchanged),
+    }
+}
+
+fn parse_remind_time(s: &str) -> Result<DateTime<Utc>, String> {
+    NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M")
+        .map(|at| at.and_utc())
+        .map_err(|_| format!("expected a time like 2024-01-31 09:30, got '{}'", s))
+}
+
+fn main() {
+    let cli = Cli::parse();
+
+    if let Command::Completions { shell } = cli.command {
+        clap_complete::generate(shell, &mut Cli::command(), "promanager", &mut std::io::stdout());
+        return;
+    }
+
+    if let Command::Workspaces(command) = cli.command {
+        let workspaces = Workspaces::new().unwrap_or_else(|e| fail(e));
+        if let Err(e) = run_workspace_command(&workspaces, command, cli.json) {
+            fail(e);
+        }
+        return;
+    }
+
+    let (path, encrypted) = match cli.db {
+        Some(path) => (PathBuf::from(path), false),
+        None => {
+            let workspaces = Workspaces::new().unwrap_or_else(|e| fail(e));
+            let workspace = match cli.workspace {
+                Some(key) => resolve_workspace(&workspaces, &key),
+                None => workspaces.active(),
+            };
+            let workspace = workspace.unwrap_or_else(|e| fail(e));
+            (workspaces.path(&workspace), workspace.encrypted)
+        }
+    };
+    let db = open_database(&path, encrypted).unwrap_or_else(|e| fail(e));
+    db.set_source(ChangeSource::Cli);
+
+    if let Err(e) = run(&db, cli.command, cli.json) {
+        fail(e);
+    }
+}
+
+// Asks for the passphrase of an encrypted workspace, or of a database given
+// with --db once it turns out to be encrypted
+fn open_database(path: &Path, encrypted: bool) -> Result<Database, String> {
+    let opened = if encrypted {
+        Database::open_encrypted(path, Some(&passphrase("Passphrase: ")?))
+    } else {
+        match Database::open(path) {
+            Err(e) if database::is_wrong_passphrase(&e) => {
+                Database::open_encrypted(path, Some(&passphrase("Passphrase: ")?))
+            }
+            opened => opened,
+        }
+    };
+    opened.map_err(|e| {
+        if database::is_wrong_passphrase(&e) {
+            "Wrong passphrase".to_string()
+        } else {
+            format!("Failed to open database {}: {}", path.display(), e)
+        }
+    })
+}
+
+// PROMANAGER_PASSPHRASE, for scripts, or else asked for without echoing it
+fn passphrase(prompt: &str) -> Result<String, String> {
+    match std::env::var("PROMANAGER_PASSPHRASE") {
+        Ok(passphrase) => Ok(passphrase),
+        Err(_) => rpassword::prompt_password(prompt).map_err(|e| e.to_string()),
+    }
+}
+
+// A passphrase being set: PROMANAGER_NEW_PASSPHRASE, or else asked for twice
+fn new_passphrase() -> Result<String, String> {
+    if let Ok(passphrase) = std::env::var("PROMANAGER_NEW_PASSPHRASE") {
+        return Ok(passphrase);
+    }
+    let passphrase = rpassword::prompt_password("New passphrase: ").map_err(|e| e.to_string())?;
+    let repeated = rpassword::prompt_password("Repeat the new passphrase: ").map_err(|e| e.to_string())?;
+    if passphrase != repeated {
+        return Err("The passphrases do not match".to_string());
+    }
+    Ok(passphrase)
+}
+
+fn fail(message: String) -> ! {
+    eprintln!("error: {}", message);
+    process::exit(1);
+}
+
+fn run(db: &Database, command: Command, json: bool) -> Result<(), String> {
+    match command {
+        Command::Tasks(command) => run_task_command(db, command, json),
+        Command::Projects(command) => run_project_command(db, command, json),
+        Command::People(command) => run_person_command(db, command, json),
+        Command::Stats => {
+            let stats = db.get_task_stats().map_err(|e| e.to_string())?;
+            if json {
+                print_json(&stats);
+            } else {
+                print_table(
+                    &["METRIC", "COUNT"],
+                    vec![
+                        vec!["total".to_string(), stats.total_tasks.to_string()],
+                        vec!["completed".to_string(), stats.completed_tasks.to_string()],
+                        vec!["pending".to_string(), stats.pending_tasks.to_string()],
+                        vec!["due today".to_string(), stats.today_tasks.to_string()],
+                        vec!["overdue".to_string(), stats.overdue_tasks.to_string()],
+                    ],
+                );
+            }
+            Ok(())
+        }
+        Command::History {
+            task,
+            page,
+            per_page,
+        } => {
+            let history = match task {
+                Some(id) => {
+                    let id = resolve_task(db, &id)?.id;
+                    db.get_task_activity(&id, page, per_page)
+                }
+                None => db.get_activity_feed(page, per_page),
+            }
+            .map_err(|e| e.to_string())?;
+
+            if json {
+                print_json(&history);
+            } else {
+                print_activity(&history);
+            }
+            Ok(())
+        }
+        Command::Digest { week, html, output } => {
+            let period = if week { DigestPeriod::Week } else { DigestPeriod::Day };
+            let format = if html { DigestFormat::Html } else { DigestFormat::Markdown };
+            let digest = db.get_digest(period).map_err(|e| e.to_string())?;
+            if json {
+                print_json(&digest);
+            } else if let Some(folder) = output {
+                println!("{}", digest::write(&digest, format, &folder)?.display());
+            } else {
+                print!("{}", digest::render(&digest, format));
+            }
+            Ok(())
+        }
+        Command::Workspaces(_) | Command::Completions { .. } => unreachable!(),
+    }
+}
+
+fn run_task_command(db: &Database, command: TaskCommand, json: bool) -> Result<(), String> {
+    match command {
+        TaskCommand::List { query } => {
+            let filter = TaskFilter::parse(&query.join(" "))?;
+            let tasks = db.filter_tasks(&filter).map_err(|e| e.to_string())?;
+            print_tasks(db, &tasks, json)
+        }
+        TaskCommand::Add { title, fields } => {
+            let project_id = match fields.project {
+                Some(project) => Some(resolve_project(db, &project)?.id),
+                None => None,
+            };
+            let task = db
+                .create_task(CreateTask {
+                    title,
+                    description: fields.description,
+                    project_id,
+                    status: fields.status.map_or(TaskStatus::Todo, Into::into),
+                    priority: fields.priority.map_or(TaskPriority::Medium, Into::into),
+                    due_date: fields.due,
+                    estimate_hours: fields.estimate,
+                    milestone_id: None,
+                })
+                .map_err(|e| e.to_string())?;
+            print_tasks(db, &[task], json)
+        }
+        TaskCommand::Edit {
+            id,
+            title,
+            fields,
+            clear,
+            expect_revision,
+        } => {
+            let id = resolve_task(db, &id)?.id;
+            let project_id = match fields.project {
+                Some(project) => Some(resolve_project(db, &project)?.id),
+                None => None,
+            };
+            let task = db
+                .update_task(UpdateTask {
+                    id,
+                    title,
+                    description: patch(fields.description, &clear, ClearArg::Description)?,
+                    project_id: patch(project_id, &clear, ClearArg::Project)?,
+                    status: fields.status.map(Into::into),
+                    priority: fields.priority.map(Into::into),
+                    due_date: patch(fields.due, &clear, ClearArg::Due)?,
+                    estimate_hours: patch(fields.estimate, &clear, ClearArg::Estimate)?,
+                    completed: None,
+                    milestone_id: patch(None, &clear, ClearArg::Milestone)?,
+                    assignee_ids: None,
+                    expected_revision: expect_revision,
+                })
+                .map_err(|e| e.to_string())?;
+            print_tasks(db, &[task], json)
+        }
+        TaskCommand::Complete { id, undo } => {
+            let id = resolve_task(db, &id)?.id;
+            let task = db
+                .update_task(UpdateTask {
+                    id,
+                    completed: Some(!undo),
+                    title: None,
+                    description: Patch::Unchanged,
+                    project_id: Patch::Unchanged,
+                    status: None,
+                    priority: None,
+                    due_date: Patch::Unchanged,
+                    estimate_hours: Patch::Unchanged,
+                    milestone_id: Patch::Unchanged,
+                    assignee_ids: None,
+                    expected_revision: None,
+                })
+                .map_err(|e| e.to_string())?;
+            print_tasks(db, &[task], json)
+        }
+        TaskCommand::Remind { id, at, before_due } => {
+            let task_id = resolve_task(db, &id)?.id;
+            if at.is_some() || before_due.is_some() {
+                db.create_reminder(CreateReminder {
+                    task_id: task_id.clone(),
+                    remind_at: at,
+                    minutes_before_due: before_due,
+                })
+                .map_err(|e| e.to_string())?;
+            }
+            let reminders = db.get_reminders(Some(&task_id)).map_err(|e| e.to_string())?;
+            print_reminders(&reminders, json);
+            Ok(())
+        }
+        TaskCommand::Comment { id, text, author } => {
+            let task_id = resolve_task(db, &id)?.id;
+            if let Some(body) = text {
+                let author = author
+                    .or_else(|| std::env::var("USER").ok())
+                    .or_else(|| std::env::var("USERNAME").ok())
+                    .ok_or("Pass --author to say who is commenting")?;
+                db.add_comment(CreateComment {
+                    task_id: task_id.clone(),
+                    author,
+                    body,
+                })
+                .map_err(|e| e.to_string())?;
+            }
+            let comments = db.get_comments(Some(&task_id)).map_err(|e| e.to_string())?;
+            print_comments(&comments, json);
+            Ok(())
+        }
+        TaskCommand::Checklist {
+            id,
+            add,
+            toggle,
+            remove,
+            to_task,
+        } => {
+            let task_id = resolve_task(db, &id)?.id;
+            let checklist = db.get_checklist(&task_id).map_err(|e| e.to_string())?;
+            let item = |number: usize| {
+                checklist
+                    .get(number.wrapping_sub(1))
+                    .map(|item| item.id.clone())
+                    .ok_or_else(|| format!("The checklist has no item {}", number))
+            };
+
+            if let Some(text) = add {
+                db.add_checklist_item(CreateChecklistItem {
+                    task_id: task_id.clone(),
+                    text,
+                })
+                .map_err(|e| e.to_string())?;
+            } else if let Some(number) = toggle {
+                db.toggle_checklist_item(&item(number)?).map_err(|e| e.to_string())?;
+            } else if let Some(number) = remove {
+                db.remove_checklist_item(&item(number)?).map_err(|e| e.to_string())?;
+            } else if let Some(number) = to_task {
+                let task = db.convert_checklist_item(&item(number)?).map_err(|e| e.to_string())?;
+                return print_tasks(db, &[task], json);
+            }
+            let checklist = db.get_checklist(&task_id).map_err(|e| e.to_string())?;
+            print_checklist(&checklist, json);
+            Ok(())
+        }
+        TaskCommand::Duplicate {
+            id,
+            title,
+            no_checklist,
+            no_assignees,
+        } => {
+            let id = resolve_task(db, &id)?.id;
+            let options = DuplicateOptions {
+                checklist: !no_checklist,
+                assignees: !no_assignees,
+            };
+            let task = db.duplicate_task(&id, title, options).map_err(|e| e.to_string())?;
+            print_tasks(db, &[task], json)
+        }
+        TaskCommand::Move {
+            ids,
+            project,
+            no_project: _,
+        } => {
+            let ids = ids
+                .iter()
+                .map(|id| resolve_task(db, id).map(|task| task.id))
+                .collect::<Result<Vec<_>, _>>()?;
+            let project_id = match project {
+                Some(project) => Some(resolve_project(db, &project)?.id),
+                None => None,
+            };
+            let tasks = db
+                .move_tasks(&ids, project_id.as_deref())
+                .map_err(|e| e.to_string())?;
+            print_tasks(db, &tasks, json)
+        }
+        TaskCommand::Assign { ids, to, nobody: _ } => {
+            let ids = ids
+                .iter()
+                .map(|id| resolve_task(db, id).map(|task| task.id))
+                .collect::<Result<Vec<_>, _>>()?;
+            let people = to
+                .iter()
+                .map(|name| resolve_person(db, name).map(|person| person.id))
+                .collect::<Result<Vec<_>, _>>()?;
+            let tasks = db.assign_tasks(&ids, &people).map_err(|e| e.to_string())?;
+            print_tasks(db, &tasks, json)
+        }
+        TaskCommand::Delete { id } => {
+            let task = resolve_task(db, &id)?;
+            db.delete_task(&task.id).map_err(|e| e.to_string())?;
+            if !json {
+                println!("Deleted task {}", short_id(&task.id));
+            }
+            Ok(())
+        }
+    }
+}
+
+fn run_project_command(db: &Database, command: ProjectCommand, json: bool) -> Result<(), String> {
+    match command {
+        ProjectCommand::List { all } => {
+            let projects = db.get_projects(all).map_err(|e| e.to_string())?;
+            print_projects(&projects, json);
+            Ok(())
+        }
+        ProjectCommand::Add {
+            name,
+            description,
+            color,
+            auto_archive,
+        } => {
+            let project = db
+                .create_project(CreateProject {
+                    name,
+                    description,
+                    color,
+                    auto_archive,
+                })
+                .map_err(|e| e.to_string())?;
+            print_projects(&[project], json);
+            Ok(())
+        }
+        ProjectCommand::Edit {
+            id,
+            name,
+            description,
+            clear_description,
+            color,
+            status,
+            auto_archive,
+            expect_revision,
+        } => {
+            let id = resolve_project(db, &id)?.id;
+            let project = db
+                .update_project(UpdateProject {
+                    id,
+                    name,
+                    description: if clear_description { Patch::Clear } else { description.map_or(Patch::Unchanged, Patch::Set) },
+                    color,
+                    status: status.map(Into::into),
+                    auto_archive,
+                    expected_revision: expect_revision,
+                })
+                .map_err(|e| e.to_string())?;
+            print_projects(&[project], json);
+            Ok(())
+        }
+        ProjectCommand::Archive { id } => {
+            let id = resolve_project(db, &id)?.id;
+            let project = db.archive_project(&id).map_err(|e| e.to_string())?;
+            print_projects(&[project], json);
+            Ok(())
+        }
+        ProjectCommand::Unarchive { id } => {
+            let id = resolve_project(db, &id)?.id;
+            let project = db.unarchive_project(&id).map_err(|e| e.to_string())?;
+            print_projects(&[project], json);
+            Ok(())
+        }
+        ProjectCommand::Merge { source, target } => {
+            let source = resolve_project(db, &source)?;
+            let target = resolve_project(db, &target)?.id;
+            let project = db
+                .merge_projects(&source.id, &target)
+                .map_err(|e| e.to_string())?;
+            if !json {
+                println!("Merged {} into {}", source.name, project.name);
+            }
+            Ok(())
+        }
+        ProjectCommand::Delete { id } => {
+            let project = resolve_project(db, &id)?;
+            db.delete_project(&project.id).map_err(|e| e.to_string())?;
+            if !json {
+                println!("Deleted project {}", project.name);
+            }
+            Ok(())
+        }
+    }
+}
+
+fn run_person_command(db: &Database, command: PersonCommand, json: bool) -> Result<(), String> {
+    match command {
+        PersonCommand::List => {
+            let people = db.get_people().map_err(|e| e.to_string())?;
+            print_people(&people, json);
+            Ok(())
+        }
+        PersonCommand::Add { name, email } => {
+            let person = db
+                .create_person(CreatePerson { name, email })
+                .map_err(|e| e.to_string())?;
+            print_people(&[person], json);
+            Ok(())
+        }
+        PersonCommand::Remove { name } => {
+            let person = resolve_person(db, &name)?;
+            db.delete_person(&person.id).map_err(|e| e.to_string())?;
+            if !json {
+                println!("Removed {}", person.name);
+            }
+            Ok(())
+        }
+        PersonCommand::Me { name, clear } => {
+            let me = match (name, clear) {
+                (Some(name), _) => {
+                    let id = resolve_person(db, &name)?.id;
+                    db.set_current_user(Some(&id))
+                }
+                (None, true) => db.set_current_user(None),
+                (None, false) => db.get_current_user(),
+            }
+            .map_err(|e| e.to_string())?;
+            if json {
+                print_json(&me);
+            } else {
+                match me {
+                    Some(person) => print_people(&[person], false),
+                    None => println!("No current user is set"),
+                }
+            }
+            Ok(())
+        }
+        PersonCommand::Workload => {
+            let workload = db.get_workload().map_err(|e| e.to_string())?;
+            print_workload(&workload, json);
+            Ok(())
+        }
+        PersonCommand::Handover { from, to, drop: _ } => {
+            let from = resolve_person(db, &from)?.id;
+            let to = match to {
+                Some(to) => Some(resolve_person(db, &to)?.id),
+                None => None,
+            };
+            let tasks = db
+                .hand_over_tasks(&from, to.as_deref())
+                .map_err(|e| e.to_string())?;
+            print_tasks(db, &tasks, json)
+        }
+    }
+}
+
+// SQLCipher covers the database only
+const ATTACHMENTS_NOT_ENCRYPTED: &str =
+    "Note: attachments are not encrypted; they stay readable in the app's attachments folder";
+
+fn run_workspace_command(workspaces: &Workspaces, command: WorkspaceCommand, json: bool) -> Result<(), String> {
+    match command {
+        WorkspaceCommand::List => print_workspaces(workspaces, &workspaces.list()?, json),
+        WorkspaceCommand::Add { name, encrypt } => {
+            let passphrase = if encrypt { Some(new_passphrase()?) } else { None };
+            let workspace = workspaces.create(&name, passphrase.as_deref())?;
+            if encrypt {
+                eprintln!("{}", ATTACHMENTS_NOT_ENCRYPTED);
+            }
+            print_workspaces(workspaces, &[workspace], json)
+        }
+        WorkspaceCommand::Switch { workspace } => {
+            let id = resolve_workspace(workspaces, &workspace)?.id;
+            let workspace = workspaces.switch(&id)?;
+            print_workspaces(workspaces, &[workspace], json)
+        }
+        WorkspaceCommand::Rename { workspace, name } => {
+            let id = resolve_workspace(workspaces, &workspace)?.id;
+            let workspace = workspaces.rename(&id, &name)?;
+            print_workspaces(workspaces, &[workspace], json)
+        }
+        WorkspaceCommand::Remove { workspace } => {
+            let id = resolve_workspace(workspaces, &workspace)?.id;
+            let workspace = workspaces.delete(&id)?;
+            if !json {
+                println!("Removed {}", workspace.name);
+            }
+            Ok(())
+        }
+        WorkspaceCommand::Encrypt => {
+            let workspace = workspaces.encrypt(&new_passphrase()?)?;
+            eprintln!("{}", ATTACHMENTS_NOT_ENCRYPTED);
+            print_workspaces(workspaces, &[workspace], json)
+        }
+        WorkspaceCommand::Decrypt => {
+            let workspace = workspaces.decrypt(&passphrase("Passphrase: ")?)?;
+            print_workspaces(workspaces, &[workspace], json)
+        }
+        WorkspaceCommand::Passphrase => {
+            let current = passphrase("Current passphrase: ")?;
+            workspaces.change_passphrase(&current, &new_passphrase()?)?;
+            if !json {
+                println!("Changed the passphrase of {}", workspaces.active()?.name);
+            }
+            Ok(())
+        }
+        WorkspaceCommand::AutoLock { minutes, never: _ } => {
+            let workspace = workspaces.set_auto_lock(minutes)?;
+            print_workspaces(workspaces, &[workspace], json)
+        }
+    }
+}
+
+// Ids can be abbreviated to any unique prefix, as shown in table output
+fn resolve_task(db: &Database, id: &str) -> Result<Task, String> {
+    let tasks = db.get_tasks(true).map_err(|e| e.to_string())?;
+    let mut matches: Vec<Task> = tasks.into_iter().filter(|t| t.id.starts_with(id)).collect();
+    match matches.len() {
+        0 => Err(format!("No task matches '{}'", id)),
+        1 => Ok(matches.remove(0)),
+        _ => Err(format!("'{}' matches several tasks, use a longer id", id)),
+    }
+}
+
+// Projects can also be referred to by name
+fn resolve_project(db: &Database, key: &str) -> Result<Project, String> {
+    let projects = db.get_projects(true).map_err(|e| e.to_string())?;
+    if let Some(project) = projects.iter().find(|p| p.name.eq_ignore_ascii_case(key)) {
+        return Ok(project.clone());
+    }
+
+    let mut matches: Vec<Project> = projects.into_iter().filter(|p| p.id.starts_with(key)).collect();
+    match matches.len() {
+        0 => Err(format!("No project matches '{}'", key)),
+        1 => Ok(matches.remove(0)),
+        _ => Err(format!("'{}' matches several projects, use a longer id", key)),
+    }
+}
+
+// People can also be referred to by name
+fn resolve_person(db: &Database, key: &str) -> Result<Person, String> {
+    let people = db.get_people().map_err(|e| e.to_string())?;
+    if let Some(person) = people.iter().find(|p| p.name.eq_ignore_ascii_case(key)) {
+        return Ok(person.clone());
+    }
+
+    let mut matches: Vec<Person> = people.into_iter().filter(|p| p.id.starts_with(key)).collect();
+    match matches.len() {
+        0 => Err(format!("No one matches '{}'", key)),
+        1 => Ok(matches.remove(0)),
+        _ => Err(format!("'{}' matches several people, use a longer id", key)),
+    }
+}
+
+// Workspaces can also be referred to by name
+fn resolve_workspace(workspaces: &Workspaces, key: &str) -> Result<Workspace, String> {
+    let list = workspaces.list()?;
+    if let Some(workspace) = list.iter().find(|w| w.name.eq_ignore_ascii_case(key)) {
+        return Ok(workspace.clone());
+    }
+
+    let mut matches: Vec<Workspace> = list.into_iter().filter(|w| w.id.starts_with(key)).collect();
+    match matches.len() {
+        0 => Err(format!("No workspace matches '{}'", key)),
+        1 => Ok(matches.remove(0)),
+        _ => Err(format!("'{}' matches several workspaces, use a longer id", key)),
+    }
+}
+
+fn print_tasks(db: &Database, tasks: &[Task], json: bool) -> Result<(), String> {
+    if json {
+        print_json(&tasks);
+        return Ok(());
+    }
+
+    let project_names: HashMap<String, String> = db
+        .get_projects(true)
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|p| (p.id, p.name))
+        .collect();
+    let people: HashMap<String, String> = db
+        .get_people()
+        .map_err(|e| e.to_string())?
+        .into_iter()
+        .map(|p| (p.id, p.name))
+        .collect();
+
+    let rows = tasks
+        .iter()
+        .map(|task| {
+            vec![
+                short_id(&task.id).to_string(),
+                if task.completed { "x" } else { " " }.to_string(),
+                task.title.clone(),
+                task.status.to_string(),
+                task.priority.to_string(),
+                task.project_id
+                    .as_ref()
+                    .and_then(|id| project_names.get(id))
+                    .cloned()
+                    .unwrap_or_default(),
+                task.due_date
+                    .map(|d| d.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default(),
+                task.assignee_ids
+                    .iter()
+                    .filter_map(|id| people.get(id))
+                    .cloned()
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                if task.checklist_total > 0 {
+                    format!("{}/{}", task.checklist_checked, task.checklist_total)
+                } else {
+                    String::new()
+                },
+                if task.comment_count > 0 { task.comment_count.to_string() } else { String::new() },
+            ]
+        })
+        .collect();
+    print_table(&["ID", "", "TITLE", "STATUS", "PRIORITY", "PROJECT", "DUE", "ASSIGNEES", "CHECKLIST", "COMMENTS"], rows);
+    Ok(())
+}
+
+fn print_projects(projects: &[Project], json: bool) {
+    if json {
+        print_json(&projects);
+        return;
+    }
+
+    let rows = projects
+        .iter()
+        .map(|project| {
+            vec![
+                short_id(&project.id).to_string(),
+                project.name.clone(),
+                project.status.as_str().to_string(),
+                project.color.clone(),
+                project.description.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "NAME", "STATUS", "COLOR", "DESCRIPTION"], rows);
+}
+
+fn print_reminders(reminders: &[Reminder], json: bool) {
+    if json {
+        print_json(&reminders);
+        return;
+    }
+
+    let format = |at: Option<DateTime<Utc>>| at.map(|at| at.format("%Y-%m-%d %H:%M").to_string()).unwrap_or_default();
+    let rows = reminders
+        .iter()
+        .map(|reminder| {
+            let when = match reminder.minutes_before_due {
+                Some(minutes) => format!("{} min before due", minutes),
+                None => format(reminder.remind_at),
+            };
+            vec![
+                short_id(&reminder.id).to_string(),
+                when,
+                format(reminder.next_at),
+                format(reminder.fired_at),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "WHEN", "NEXT", "FIRED"], rows);
+}
+
+fn print_people(people: &[Person], json: bool) {
+    if json {
+        print_json(&people);
+        return;
+    }
+
+    let rows = people
+        .iter()
+        .map(|person| {
+            vec![
+                short_id(&person.id).to_string(),
+                person.name.clone(),
+                person.email.clone().unwrap_or_default(),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "NAME", "EMAIL"], rows);
+}
+
+fn print_workspaces(workspaces: &Workspaces, list: &[Workspace], json: bool) -> Result<(), String> {
+    if json {
+        print_json(&list);
+        return Ok(());
+    }
+
+    let active = workspaces.active()?.id;
+    let rows = list
+        .iter()
+        .map(|workspace| {
+            vec![
+                short_id(&workspace.id).to_string(),
+                if workspace.id == active { "*" } else { "" }.to_string(),
+                workspace.name.clone(),
+                match (workspace.encrypted, workspace.auto_lock_minutes) {
+                    (true, Some(minutes)) => format!("yes, locks after {}m", minutes),
+                    (true, None) => "yes".to_string(),
+                    (false, _) => String::new(),
+                },
+                workspaces.path(workspace).display().to_string(),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "", "NAME", "ENCRYPTED", "DATABASE"], rows);
+    Ok(())
+}
+
+fn print_workload(workload: &[Workload], json: bool) {
+    if json {
+        print_json(&workload);
+        return;
+    }
+
+    let mut rows = Vec::new();
+    for load in workload {
+        rows.push(vec![
+            load.person.name.clone(),
+            "all".to_string(),
+            load.open_tasks.to_string(),
+            format!("{:.1}", load.estimate_hours),
+            load.unestimated_tasks.to_string(),
+        ]);
+        for week in &load.weeks {
+            rows.push(vec![
+                String::new(),
+                week.week_start
+                    .map(|start| format!("week of {}", start.format("%Y-%m-%d")))
+                    .unwrap_or_else(|| "no due date".to_string()),
+                week.open_tasks.to_string(),
+                format!("{:.1}", week.estimate_hours),
+                String::new(),
+            ]);
+        }
+    }
+    print_table(&["PERSON", "WHEN", "OPEN", "HOURS", "UNESTIMATED"], rows);
+}
+
+fn print_checklist(checklist: &[ChecklistItem], json: bool) {
+    if json {
+        print_json(&checklist);
+        return;
+    }
+
+    let rows = checklist
+        .iter()
+        .enumerate()
+        .map(|(i, item)| {
+            vec![
+                (i + 1).to_string(),
+                if item.checked { "x" } else { " " }.to_string(),
+                item.text.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["#", "", "ITEM"], rows);
+}
+
+fn print_comments(comments: &[Comment], json: bool) {
+    if json {
+        print_json(&comments);
+        return;
+    }
+
+    let rows = comments
+        .iter()
+        .map(|comment| {
+            let mut when = comment.created_at.format("%Y-%m-%d %H:%M").to_string();
+            if comment.edited_at.is_some() {
+                when.push_str(" (edited)");
+            }
+            vec![
+                short_id(&comment.id).to_string(),
+                comment.author.clone(),
+                when,
+                comment.body.split_whitespace().collect::<Vec<_>>().join(" "),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "AUTHOR", "WHEN", "COMMENT"], rows);
+}
+
+fn print_activity(history: &ActivityPage) {
+    let rows = history
+        .items
+        .iter()
+        .map(|activity| {
+            let change = match (&activity.field, activity.action.as_str()) {
+                (Some(field), _) => format!(
+                    "{}: {} -> {}",
+                    field,
+                    activity.old_value.as_deref().unwrap_or("(none)"),
+                    activity.new_value.as_deref().unwrap_or("(none)")
+                ),
+                (None, action) => format!(
+                    "{} {}",
+                    action,
+                    activity
+                        .new_value
+                        .as_deref()
+                        .or(activity.old_value.as_deref())
+                        .unwrap_or("")
+                ),
+            };
+            vec![
+                activity.created_at.format("%Y-%m-%d %H:%M").to_string(),
+                activity.source.as_str().to_string(),
+                format!("{} {}", activity.entity.as_str(), short_id(&activity.entity_id)),
+                change,
+            ]
+        })
+        .collect();
+    print_table(&["WHEN", "SOURCE", "ENTITY", "CHANGE"], rows);
+    println!(
+        "page {} of {} ({} entries)",
+        history.page,
+        (history.total + history.per_page - 1) / history.per_page.max(1),
+        history.total
+    );
+}
+
+fn print_json<T: Serialize>(value: &T) {
+    match serde_json::to_string_pretty(value) {
+        Ok(json) => println!("{}", json),
+        Err(e) => fail(e.to_string()),
+    }
+}
+
+fn print_table(headers: &[&str], rows: Vec<Vec<String>>) {
+    let mut widths: Vec<usize> = headers.iter().map(|h| h.chars().count()).collect();
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    println!("{}", format_row(headers.to_vec()));
+    for row in &rows {
+        println!("{}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
+
+fn short_id(id: &str) -> &str {
+    &id[..id.len().min(8)]
+}
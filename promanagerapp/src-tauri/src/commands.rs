@@ -1,107 +1,992 @@
-use crate::database::Database;
+use crate::api_server::{ApiServer, ApiServerInfo, ApiServerState};
+use crate::analytics::{self, ForecastOptions, MetricsRange};
+use crate::attachments::{self, AttachmentStores};
+use crate::digest;
+use crate::error::{Conflict, Error};
+use crate::events::{LOCK_EVENT, WORKSPACE_EVENT};
+use crate::filter::TaskFilter;
+use crate::interop;
 use crate::models::*;
-use std::sync::Mutex;
-use tauri::State;
+use crate::workspaces::WorkspaceState;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use std::path::PathBuf;
+use tauri::{AppHandle, Manager, State};
+
+/// Error of the update commands. A conflict carries the current copy of the
+/// record; any other failure is a message, as with the other commands.
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind")]
+pub enum UpdateError {
+    #[serde(rename = "conflict")]
+    Conflict(Conflict),
+    #[serde(rename = "error")]
+    Failed { message: String },
+}
+
+impl From<Error> for UpdateError {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Conflict(conflict) => UpdateError::Conflict(conflict),
+            e => UpdateError::Failed { message: e.to_string() },
+        }
+    }
+}
+
+impl From<&str> for UpdateError {
+    fn from(message: &str) -> Self {
+        UpdateError::Failed { message: message.to_string() }
+    }
+}
+
+impl From<String> for UpdateError {
+    fn from(message: String) -> Self {
+        UpdateError::Failed { message }
+    }
+}
+
+// Workspace commands
+#[tauri::command]
+pub fn get_workspaces(workspaces: State<WorkspaceState>) -> Result<Vec<Workspace>, String> {
+    workspaces.list()
+}
+
+#[tauri::command]
+pub fn get_active_workspace(workspaces: State<WorkspaceState>) -> Result<Workspace, String> {
+    workspaces.active()
+}
+
+/// Creates a workspace, encrypted with `passphrase` if there is one.
+#[tauri::command]
+pub fn create_workspace(
+    workspaces: State<WorkspaceState>,
+    name: String,
+    passphrase: Option<String>,
+) -> Result<Workspace, String> {
+    workspaces.ensure_unlocked()?;
+    workspaces.create(&name, passphrase.as_deref())
+}
+
+/// Swaps in the workspace's database and tells every window to reload. An
+/// encrypted workspace is switched to locked.
+#[tauri::command]
+pub fn switch_workspace(
+    app_handle: AppHandle,
+    workspaces: State<WorkspaceState>,
+    stores: State<AttachmentStores>,
+    id: String,
+) -> Result<Workspace, String> {
+    workspaces.ensure_unlocked()?;
+    let workspace = workspaces.switch(&id)?;
+    collect_attachment_leftovers(&workspaces, &stores);
+    let _ = app_handle.emit_all(WORKSPACE_EVENT, workspace.clone());
+    let status = workspaces.lock_status()?;
+    if status.locked {
+        let _ = app_handle.emit_all(LOCK_EVENT, status);
+    }
+    Ok(workspace)
+}
+
+#[tauri::command]
+pub fn rename_workspace(workspaces: State<WorkspaceState>, id: String, name: String) -> Result<Workspace, String> {
+    workspaces.ensure_unlocked()?;
+    workspaces.rename(&id, &name)
+}
+
+/// Deletes a workspace other than the active one, with all its data.
+#[tauri::command]
+pub fn delete_workspace(
+    workspaces: State<WorkspaceState>,
+    stores: State<AttachmentStores>,
+    id: String,
+) -> Result<(), String> {
+    workspaces.ensure_unlocked()?;
+    let workspace = workspaces.delete(&id)?;
+    stores
+        .remove(&workspace)
+        .map_err(|e| format!("Failed to delete the attachments of {}: {}", workspace.name, e))
+}
+
+// The CLI may have deleted tasks while the workspace was not open here
+fn collect_attachment_leftovers(workspaces: &WorkspaceState, stores: &AttachmentStores) {
+    if let Ok((workspace, db)) = workspaces.active_database() {
+        if let Err(e) = attachments::collect_garbage(&db, &stores.store(&workspace)) {
+            eprintln!("Failed to clean up attachments: {}", e);
+        }
+    }
+}
+
+// Encryption commands
+/// Whether the app has to show its unlock screen, or on first run offer to
+/// encrypt the workspace. Works while locked, like `unlock_workspace`.
+#[tauri::command]
+pub fn get_lock_status(workspaces: State<WorkspaceState>) -> Result<LockStatus, String> {
+    workspaces.lock_status()
+}
+
+#[tauri::command]
+pub fn unlock_workspace(
+    app_handle: AppHandle,
+    workspaces: State<WorkspaceState>,
+    stores: State<AttachmentStores>,
+    passphrase: String,
+) -> Result<LockStatus, String> {
+    workspaces.unlock(&passphrase)?;
+    collect_attachment_leftovers(&workspaces, &stores);
+    let status = workspaces.lock_status()?;
+    let _ = app_handle.emit_all(LOCK_EVENT, status.clone());
+    Ok(status)
+}
+
+#[tauri::command]
+pub fn lock_workspace(app_handle: AppHandle, workspaces: State<WorkspaceState>) -> Result<LockStatus, String> {
+    workspaces.lock()?;
+    if let Err(e) = attachments::remove_open_copies() {
+        eprintln!("Failed to remove opened attachments: {}", e);
+    }
+    let status = workspaces.lock_status()?;
+    let _ = app_handle.emit_all(LOCK_EVENT, status.clone());
+    Ok(status)
+}
+
+/// Encrypts the database only; the workspace's attachments stay readable on
+/// disk, which the app should say when offering encryption.
+#[tauri::command]
+pub fn encrypt_workspace(workspaces: State<WorkspaceState>, passphrase: String) -> Result<Workspace, String> {
+    workspaces.ensure_unlocked()?;
+    workspaces.encrypt(&passphrase)
+}
+
+#[tauri::command]
+pub fn decrypt_workspace(workspaces: State<WorkspaceState>, passphrase: String) -> Result<Workspace, String> {
+    workspaces.ensure_unlocked()?;
+    workspaces.decrypt(&passphrase)
+}
+
+#[tauri::command]
+pub fn change_passphrase(
+    workspaces: State<WorkspaceState>,
+    current_passphrase: String,
+    new_passphrase: String,
+) -> Result<(), String> {
+    workspaces.ensure_unlocked()?;
+    workspaces.change_passphrase(&current_passphrase, &new_passphrase)
+}
+
+/// `minutes: None` keeps the workspace unlocked until it is locked by hand.
+#[tauri::command]
+pub fn set_auto_lock(workspaces: State<WorkspaceState>, minutes: Option<u32>) -> Result<Workspace, String> {
+    workspaces.ensure_unlocked()?;
+    workspaces.set_auto_lock(minutes)
+}
 
 // Project commands
 #[tauri::command]
 pub fn create_project(
-    db: State<Mutex<Database>>,
+    workspaces: State<WorkspaceState>,
     project_data: CreateProject,
 ) -> Result<Project, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
+    let db = workspaces.database()?;
     db.create_project(project_data).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn get_projects(db: State<Mutex<Database>>) -> Result<Vec<Project>, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
-    db.get_projects().map_err(|e| e.to_string())
+pub fn get_projects(
+    workspaces: State<WorkspaceState>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Project>, String> {
+    let db = workspaces.database()?;
+    db.get_projects(include_archived.unwrap_or(false))
+        .map_err(|e| e.to_string())
 }
 
+/// With `base`, the copy the edit was made from, changes made elsewhere in
+/// the meantime are merged instead of causing a conflict
 #[tauri::command]
 pub fn update_project(
-    db: State<Mutex<Database>>,
+    workspaces: State<WorkspaceState>,
     project_data: UpdateProject,
+    base: Option<Project>,
+) -> Result<Project, UpdateError> {
+    let db = workspaces.database()?;
+    let project = match base {
+        Some(base) => db.merge_project_update(&base, project_data)?,
+        None => db.update_project(project_data)?,
+    };
+    Ok(project)
+}
+
+#[tauri::command]
+pub fn archive_project(workspaces: State<WorkspaceState>, id: String) -> Result<Project, String> {
+    let db = workspaces.database()?;
+    db.archive_project(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn unarchive_project(workspaces: State<WorkspaceState>, id: String) -> Result<Project, String> {
+    let db = workspaces.database()?;
+    db.unarchive_project(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn merge_projects(
+    workspaces: State<WorkspaceState>,
+    source_id: String,
+    target_id: String,
 ) -> Result<Project, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
-    db.update_project(project_data).map_err(|e| e.to_string())
+    let db = workspaces.database()?;
+    db.merge_projects(&source_id, &target_id)
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn delete_project(db: State<Mutex<Database>>, id: String) -> Result<(), String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
+pub fn delete_project(workspaces: State<WorkspaceState>, id: String) -> Result<(), String> {
+    let db = workspaces.database()?;
     db.delete_project(&id).map_err(|e| e.to_string())
 }
 
 // Task commands
 #[tauri::command]
-pub fn create_task(db: State<Mutex<Database>>, task_data: CreateTask) -> Result<Task, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
+pub fn create_task(workspaces: State<WorkspaceState>, task_data: CreateTask) -> Result<Task, String> {
+    let db = workspaces.database()?;
     db.create_task(task_data).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn get_tasks(db: State<Mutex<Database>>) -> Result<Vec<Task>, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
-    db.get_tasks().map_err(|e| e.to_string())
+pub fn get_tasks(
+    workspaces: State<WorkspaceState>,
+    include_archived: Option<bool>,
+) -> Result<Vec<Task>, String> {
+    let db = workspaces.database()?;
+    db.get_tasks(include_archived.unwrap_or(false))
+        .map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn get_tasks_by_project(
-    db: State<Mutex<Database>>,
+    workspaces: State<WorkspaceState>,
     project_id: String,
 ) -> Result<Vec<Task>, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
+    let db = workspaces.database()?;
     db.get_tasks_by_project(&project_id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn update_task(db: State<Mutex<Database>>, task_data: UpdateTask) -> Result<Task, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
-    db.update_task(task_data).map_err(|e| e.to_string())
+pub fn filter_tasks(workspaces: State<WorkspaceState>, query: String) -> Result<Vec<Task>, String> {
+    let filter = TaskFilter::parse(&query)?;
+    let db = workspaces.database()?;
+    db.filter_tasks(&filter).map_err(|e| e.to_string())
+}
+
+/// With `base`, the copy the edit was made from, changes made elsewhere in
+/// the meantime are merged instead of causing a conflict
+#[tauri::command]
+pub fn update_task(
+    workspaces: State<WorkspaceState>,
+    task_data: UpdateTask,
+    base: Option<Task>,
+) -> Result<Task, UpdateError> {
+    let db = workspaces.database()?;
+    let task = match base {
+        Some(base) => db.merge_task_update(&base, task_data)?,
+        None => db.update_task(task_data)?,
+    };
+    Ok(task)
 }
 
 #[tauri::command]
-pub fn delete_task(db: State<Mutex<Database>>, id: String) -> Result<(), String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
+pub fn duplicate_task(
+    workspaces: State<WorkspaceState>,
+    id: String,
+    title: Option<String>,
+    checklist: Option<bool>,
+    assignees: Option<bool>,
+) -> Result<Task, String> {
+    let options = DuplicateOptions {
+        checklist: checklist.unwrap_or(true),
+        assignees: assignees.unwrap_or(true),
+    };
+    let db = workspaces.database()?;
+    db.duplicate_task(&id, title, options).map_err(|e| e.to_string())
+}
+
+// `project_id: None` takes the tasks out of their projects
+#[tauri::command]
+pub fn move_tasks(
+    workspaces: State<WorkspaceState>,
+    task_ids: Vec<String>,
+    project_id: Option<String>,
+) -> Result<Vec<Task>, String> {
+    let db = workspaces.database()?;
+    db.move_tasks(&task_ids, project_id.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+/// Runs the operations all-or-nothing, returning one result per operation
+#[tauri::command]
+pub fn batch(
+    workspaces: State<WorkspaceState>,
+    operations: Vec<BatchOperation>,
+) -> Result<Vec<BatchResult>, String> {
+    let db = workspaces.database()?;
+    db.batch(operations).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_task(workspaces: State<WorkspaceState>, id: String) -> Result<(), String> {
+    let db = workspaces.database()?;
     db.delete_task(&id).map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn get_today_tasks(db: State<Mutex<Database>>) -> Result<Vec<Task>, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
+pub fn get_today_tasks(workspaces: State<WorkspaceState>) -> Result<Vec<Task>, String> {
+    let db = workspaces.database()?;
     db.get_today_tasks().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
-pub fn get_upcoming_tasks(db: State<Mutex<Database>>) -> Result<Vec<Task>, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
+pub fn get_upcoming_tasks(workspaces: State<WorkspaceState>) -> Result<Vec<Task>, String> {
+    let db = workspaces.database()?;
     db.get_upcoming_tasks().map_err(|e| e.to_string())
 }
 
 #[tauri::command]
 pub fn mark_task_complete(
-    db: State<Mutex<Database>>,
+    workspaces: State<WorkspaceState>,
     id: String,
     completed: bool,
 ) -> Result<Task, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
+    let db = workspaces.database()?;
     let update_data = UpdateTask {
         id,
         completed: Some(completed),
         title: None,
-        description: None,
-        project_id: None,
+        description: Patch::Unchanged,
+        project_id: Patch::Unchanged,
         status: None,
         priority: None,
-        due_date: None,
+        due_date: Patch::Unchanged,
+        estimate_hours: Patch::Unchanged,
+        milestone_id: Patch::Unchanged,
+        assignee_ids: None,
+        expected_revision: None,
     };
     db.update_task(update_data).map_err(|e| e.to_string())
 }
 
+// Milestone commands
+#[tauri::command]
+pub fn create_milestone(
+    workspaces: State<WorkspaceState>,
+    milestone_data: CreateMilestone,
+) -> Result<Milestone, String> {
+    let db = workspaces.database()?;
+    db.create_milestone(milestone_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_milestones(
+    workspaces: State<WorkspaceState>,
+    project_id: Option<String>,
+) -> Result<Vec<MilestoneProgress>, String> {
+    let db = workspaces.database()?;
+    db.get_milestones(project_id.as_deref())
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_milestone(workspaces: State<WorkspaceState>, id: String) -> Result<MilestoneProgress, String> {
+    let db = workspaces.database()?;
+    db.get_milestone(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_milestone(
+    workspaces: State<WorkspaceState>,
+    milestone_data: UpdateMilestone,
+) -> Result<Milestone, String> {
+    let db = workspaces.database()?;
+    db.update_milestone(milestone_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_milestone(workspaces: State<WorkspaceState>, id: String) -> Result<(), String> {
+    let db = workspaces.database()?;
+    db.delete_milestone(&id).map_err(|e| e.to_string())
+}
+
+// Template commands
+#[tauri::command]
+pub fn save_project_as_template(
+    workspaces: State<WorkspaceState>,
+    template_data: SaveProjectTemplate,
+) -> Result<ProjectTemplate, String> {
+    let db = workspaces.database()?;
+    db.save_project_as_template(template_data)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_templates(workspaces: State<WorkspaceState>) -> Result<Vec<ProjectTemplate>, String> {
+    let db = workspaces.database()?;
+    db.get_templates().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_template(
+    workspaces: State<WorkspaceState>,
+    template_data: UpdateProjectTemplate,
+) -> Result<ProjectTemplate, String> {
+    let db = workspaces.database()?;
+    db.update_template(template_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_template(workspaces: State<WorkspaceState>, id: String) -> Result<(), String> {
+    let db = workspaces.database()?;
+    db.delete_template(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn instantiate_template(
+    workspaces: State<WorkspaceState>,
+    request: InstantiateTemplate,
+) -> Result<Project, String> {
+    let db = workspaces.database()?;
+    db.instantiate_template(request).map_err(|e| e.to_string())
+}
+
+// Sprint commands
+#[tauri::command]
+pub fn create_sprint(workspaces: State<WorkspaceState>, sprint_data: CreateSprint) -> Result<Sprint, String> {
+    let db = workspaces.database()?;
+    db.create_sprint(sprint_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_sprints(workspaces: State<WorkspaceState>) -> Result<Vec<Sprint>, String> {
+    let db = workspaces.database()?;
+    db.get_sprints().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_sprint(workspaces: State<WorkspaceState>, sprint_data: UpdateSprint) -> Result<Sprint, String> {
+    let db = workspaces.database()?;
+    db.update_sprint(sprint_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_sprint(workspaces: State<WorkspaceState>, id: String) -> Result<(), String> {
+    let db = workspaces.database()?;
+    db.delete_sprint(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn add_tasks_to_sprint(
+    workspaces: State<WorkspaceState>,
+    sprint_id: String,
+    task_ids: Vec<String>,
+) -> Result<SprintReport, String> {
+    let db = workspaces.database()?;
+    db.add_tasks_to_sprint(&sprint_id, &task_ids)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_task_from_sprint(
+    workspaces: State<WorkspaceState>,
+    sprint_id: String,
+    task_id: String,
+) -> Result<(), String> {
+    let db = workspaces.database()?;
+    db.remove_task_from_sprint(&sprint_id, &task_id)
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn close_sprint(
+    workspaces: State<WorkspaceState>,
+    id: String,
+    next_sprint_id: Option<String>,
+) -> Result<SprintReport, String> {
+    let db = workspaces.database()?;
+    db.close_sprint(&id, next_sprint_id.as_deref())
+        .map_err(|e| e.to_string())
+}
+
 #[tauri::command]
-pub fn get_task_stats(db: State<Mutex<Database>>) -> Result<TaskStats, String> {
-    let db = db.lock().map_err(|_| "Failed to lock database")?;
+pub fn get_sprint_report(workspaces: State<WorkspaceState>, id: String) -> Result<SprintReport, String> {
+    let db = workspaces.database()?;
+    db.get_sprint_report(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_task_stats(workspaces: State<WorkspaceState>) -> Result<TaskStats, String> {
+    let db = workspaces.database()?;
     db.get_task_stats().map_err(|e| e.to_string())
 }
+
+#[tauri::command]
+pub fn get_dashboard_stats(
+    workspaces: State<WorkspaceState>,
+    days: Option<u32>,
+) -> Result<DashboardStats, String> {
+    let db = workspaces.database()?;
+    db.get_dashboard_stats(days.unwrap_or(14).min(365))
+        .map_err(|e| e.to_string())
+}
+
+// Reminder commands
+#[tauri::command]
+pub fn create_reminder(
+    workspaces: State<WorkspaceState>,
+    reminder_data: CreateReminder,
+) -> Result<Reminder, String> {
+    let db = workspaces.database()?;
+    db.create_reminder(reminder_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_reminders(
+    workspaces: State<WorkspaceState>,
+    task_id: Option<String>,
+) -> Result<Vec<Reminder>, String> {
+    let db = workspaces.database()?;
+    db.get_reminders(task_id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn snooze_reminder(
+    workspaces: State<WorkspaceState>,
+    id: String,
+    minutes: i64,
+) -> Result<Reminder, String> {
+    if minutes <= 0 {
+        return Err("Snooze for at least a minute".to_string());
+    }
+    let db = workspaces.database()?;
+    db.snooze_reminder(&id, Utc::now() + chrono::Duration::minutes(minutes))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_reminder(workspaces: State<WorkspaceState>, id: String) -> Result<(), String> {
+    let db = workspaces.database()?;
+    db.delete_reminder(&id).map_err(|e| e.to_string())
+}
+
+// Comment commands
+#[tauri::command]
+pub fn add_comment(
+    workspaces: State<WorkspaceState>,
+    comment_data: CreateComment,
+) -> Result<Comment, String> {
+    let db = workspaces.database()?;
+    db.add_comment(comment_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_comments(workspaces: State<WorkspaceState>, task_id: String) -> Result<Vec<Comment>, String> {
+    let db = workspaces.database()?;
+    db.get_comments(Some(&task_id)).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn edit_comment(
+    workspaces: State<WorkspaceState>,
+    comment_data: UpdateComment,
+) -> Result<Comment, String> {
+    let db = workspaces.database()?;
+    db.edit_comment(comment_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_comment(workspaces: State<WorkspaceState>, id: String) -> Result<(), String> {
+    let db = workspaces.database()?;
+    db.delete_comment(&id).map_err(|e| e.to_string())
+}
+
+// People commands
+#[tauri::command]
+pub fn create_person(workspaces: State<WorkspaceState>, person_data: CreatePerson) -> Result<Person, String> {
+    let db = workspaces.database()?;
+    db.create_person(person_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_people(workspaces: State<WorkspaceState>) -> Result<Vec<Person>, String> {
+    let db = workspaces.database()?;
+    db.get_people().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn update_person(workspaces: State<WorkspaceState>, person_data: UpdatePerson) -> Result<Person, String> {
+    let db = workspaces.database()?;
+    db.update_person(person_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn delete_person(workspaces: State<WorkspaceState>, id: String) -> Result<(), String> {
+    let db = workspaces.database()?;
+    db.delete_person(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_current_user(workspaces: State<WorkspaceState>) -> Result<Option<Person>, String> {
+    let db = workspaces.database()?;
+    db.get_current_user().map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn set_current_user(
+    workspaces: State<WorkspaceState>,
+    person_id: Option<String>,
+) -> Result<Option<Person>, String> {
+    let db = workspaces.database()?;
+    db.set_current_user(person_id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn assign_tasks(
+    workspaces: State<WorkspaceState>,
+    task_ids: Vec<String>,
+    person_ids: Vec<String>,
+) -> Result<Vec<Task>, String> {
+    let db = workspaces.database()?;
+    db.assign_tasks(&task_ids, &person_ids).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn hand_over_tasks(
+    workspaces: State<WorkspaceState>,
+    from_id: String,
+    to_id: Option<String>,
+) -> Result<Vec<Task>, String> {
+    let db = workspaces.database()?;
+    db.hand_over_tasks(&from_id, to_id.as_deref()).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_workload(workspaces: State<WorkspaceState>) -> Result<Vec<Workload>, String> {
+    let db = workspaces.database()?;
+    db.get_workload().map_err(|e| e.to_string())
+}
+
+// Checklist commands
+#[tauri::command]
+pub fn add_checklist_item(
+    workspaces: State<WorkspaceState>,
+    item_data: CreateChecklistItem,
+) -> Result<ChecklistItem, String> {
+    let db = workspaces.database()?;
+    db.add_checklist_item(item_data).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_checklist(
+    workspaces: State<WorkspaceState>,
+    task_id: String,
+) -> Result<Vec<ChecklistItem>, String> {
+    let db = workspaces.database()?;
+    db.get_checklist(&task_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn toggle_checklist_item(workspaces: State<WorkspaceState>, id: String) -> Result<ChecklistItem, String> {
+    let db = workspaces.database()?;
+    db.toggle_checklist_item(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn reorder_checklist(
+    workspaces: State<WorkspaceState>,
+    task_id: String,
+    item_ids: Vec<String>,
+) -> Result<Vec<ChecklistItem>, String> {
+    let db = workspaces.database()?;
+    db.reorder_checklist(&task_id, &item_ids).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn remove_checklist_item(workspaces: State<WorkspaceState>, id: String) -> Result<(), String> {
+    let db = workspaces.database()?;
+    db.remove_checklist_item(&id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn convert_checklist_item(workspaces: State<WorkspaceState>, id: String) -> Result<Task, String> {
+    let db = workspaces.database()?;
+    db.convert_checklist_item(&id).map_err(|e| e.to_string())
+}
+
+// Attachment commands
+#[tauri::command]
+pub fn attach_file(
+    workspaces: State<WorkspaceState>,
+    stores: State<AttachmentStores>,
+    task_id: String,
+    path: PathBuf,
+) -> Result<Attachment, String> {
+    let (workspace, db) = workspaces.active_database()?;
+    attachments::attach(&db, &stores.store(&workspace), &task_id, &path)
+}
+
+#[tauri::command]
+pub fn get_attachments(
+    workspaces: State<WorkspaceState>,
+    task_id: String,
+) -> Result<Vec<Attachment>, String> {
+    let db = workspaces.database()?;
+    db.get_attachments(&task_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn open_attachment(
+    workspaces: State<WorkspaceState>,
+    stores: State<AttachmentStores>,
+    id: String,
+) -> Result<(), String> {
+    let (workspace, db) = workspaces.active_database()?;
+    attachments::open(&db, &stores.store(&workspace), &id).map(|_| ())
+}
+
+#[tauri::command]
+pub fn export_attachment(
+    workspaces: State<WorkspaceState>,
+    stores: State<AttachmentStores>,
+    id: String,
+    destination: PathBuf,
+) -> Result<(), String> {
+    let (workspace, db) = workspaces.active_database()?;
+    attachments::export(&db, &stores.store(&workspace), &id, &destination)
+}
+
+#[tauri::command]
+pub fn delete_attachment(
+    workspaces: State<WorkspaceState>,
+    stores: State<AttachmentStores>,
+    id: String,
+) -> Result<(), String> {
+    let (workspace, db) = workspaces.active_database()?;
+    attachments::delete(&db, &stores.store(&workspace), &id)
+}
+
+#[tauri::command]
+pub fn collect_attachment_garbage(
+    workspaces: State<WorkspaceState>,
+    stores: State<AttachmentStores>,
+) -> Result<usize, String> {
+    let (workspace, db) = workspaces.active_database()?;
+    attachments::collect_garbage(&db, &stores.store(&workspace))
+}
+
+// Digest commands
+#[tauri::command]
+pub fn generate_digest(
+    workspaces: State<WorkspaceState>,
+    period: DigestPeriod,
+    format: DigestFormat,
+) -> Result<String, String> {
+    let db = workspaces.database()?;
+    let digest = db.get_digest(period).map_err(|e| e.to_string())?;
+    Ok(digest::render(&digest, format))
+}
+
+#[tauri::command]
+pub fn get_digest_schedule(workspaces: State<WorkspaceState>) -> Result<Option<DigestSchedule>, String> {
+    let db = workspaces.database()?;
+    db.get_setting(digest::SCHEDULE_SETTING).map_err(|e| e.to_string())
+}
+
+/// `None` stops writing digests automatically
+#[tauri::command]
+pub fn set_digest_schedule(
+    workspaces: State<WorkspaceState>,
+    schedule: Option<DigestSchedule>,
+) -> Result<(), String> {
+    if let Some(schedule) = &schedule {
+        if schedule.hour > 23 {
+            return Err("The hour must be between 0 and 23".to_string());
+        }
+        if schedule.folder.trim().is_empty() {
+            return Err("Choose a folder to write digests to".to_string());
+        }
+    }
+    let db = workspaces.database()?;
+    db.set_setting(digest::SCHEDULE_SETTING, schedule.as_ref())
+        .map_err(|e| e.to_string())
+}
+
+// Import / export commands
+#[tauri::command]
+pub fn import_todotxt(workspaces: State<WorkspaceState>, content: String) -> Result<Vec<Task>, String> {
+    let db = workspaces.database()?;
+    db.import_tasks(interop::parse_todotxt(&content))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn export_todotxt(workspaces: State<WorkspaceState>) -> Result<String, String> {
+    let db = workspaces.database()?;
+    let tasks = db.get_tasks(true).map_err(|e| e.to_string())?;
+    let projects = db.get_projects(true).map_err(|e| e.to_string())?;
+    Ok(interop::export_todotxt(&tasks, &projects))
+}
+
+#[tauri::command]
+pub fn import_markdown(workspaces: State<WorkspaceState>, content: String) -> Result<Vec<Task>, String> {
+    let db = workspaces.database()?;
+    db.import_tasks(interop::parse_markdown(&content))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn export_markdown(workspaces: State<WorkspaceState>) -> Result<String, String> {
+    let db = workspaces.database()?;
+    let tasks = db.get_tasks(true).map_err(|e| e.to_string())?;
+    let projects = db.get_projects(true).map_err(|e| e.to_string())?;
+    let comments = db.get_comments(None).map_err(|e| e.to_string())?;
+    Ok(interop::export_markdown(&tasks, &projects, &comments))
+}
+
+// REST API server commands
+#[tauri::command]
+pub fn start_api_server(
+    workspaces: State<WorkspaceState>,
+    server: State<ApiServerState>,
+    port: Option<u16>,
+) -> Result<ApiServerInfo, String> {
+    workspaces.ensure_unlocked()?;
+    let mut server = server.lock().map_err(|_| "Failed to lock API server state")?;
+    if let Some(running) = server.as_ref() {
+        return Ok(running.info.clone());
+    }
+
+    let started = ApiServer::start(workspaces.inner().clone(), port.unwrap_or(0), None)
+        .map_err(|e| e.to_string())?;
+    let info = started.info.clone();
+    *server = Some(started);
+    Ok(info)
+}
+
+#[tauri::command]
+pub fn stop_api_server(server: State<ApiServerState>) -> Result<(), String> {
+    let mut server = server.lock().map_err(|_| "Failed to lock API server state")?;
+    if let Some(running) = server.take() {
+        running.stop();
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub fn get_api_server_info(server: State<ApiServerState>) -> Result<Option<ApiServerInfo>, String> {
+    let server = server.lock().map_err(|_| "Failed to lock API server state")?;
+    Ok(server.as_ref().map(|running| running.info.clone()))
+}
+
+// Activity commands
+#[tauri::command]
+pub fn get_task_activity(
+    workspaces: State<WorkspaceState>,
+    task_id: String,
+    page: Option<i64>,
+    per_page: Option<i64>,
+) -> Result<ActivityPage, String> {
+    let db = workspaces.database()?;
+    db.get_task_activity(&task_id, page.unwrap_or(1), per_page.unwrap_or(50))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_activity_feed(
+    workspaces: State<WorkspaceState>,
+    page: Option<i64>,
+    per_page: Option<i64>,
+) -> Result<ActivityPage, String> {
+    let db = workspaces.database()?;
+    db.get_activity_feed(page.unwrap_or(1), per_page.unwrap_or(50))
+        .map_err(|e| e.to_string())
+}
+
+// Analytics commands
+#[tauri::command]
+pub fn get_task_transitions(
+    workspaces: State<WorkspaceState>,
+    task_id: String,
+) -> Result<Vec<StatusTransition>, String> {
+    let db = workspaces.database()?;
+    db.get_task_transitions(&task_id).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_flow_metrics(
+    workspaces: State<WorkspaceState>,
+    project_id: Option<String>,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> Result<FlowMetrics, String> {
+    let db = workspaces.database()?;
+    db.get_flow_metrics(project_id.as_deref(), &MetricsRange { from, to })
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_burndown(
+    workspaces: State<WorkspaceState>,
+    project_id: String,
+    from: NaiveDate,
+    to: NaiveDate,
+    unit: Option<ChartUnit>,
+) -> Result<Vec<BurndownPoint>, String> {
+    check_chart_range(from, to)?;
+    let db = workspaces.database()?;
+    db.get_burndown(&project_id, from, to, unit.unwrap_or(ChartUnit::Tasks))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_cumulative_flow(
+    workspaces: State<WorkspaceState>,
+    project_id: String,
+    from: NaiveDate,
+    to: NaiveDate,
+    unit: Option<ChartUnit>,
+) -> Result<Vec<CumulativeFlowPoint>, String> {
+    check_chart_range(from, to)?;
+    let db = workspaces.database()?;
+    db.get_cumulative_flow(&project_id, from, to, unit.unwrap_or(ChartUnit::Tasks))
+        .map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn forecast_project(
+    workspaces: State<WorkspaceState>,
+    project_id: String,
+    trials: Option<u32>,
+    history_days: Option<u32>,
+    seed: Option<u64>,
+) -> Result<Forecast, String> {
+    let options = ForecastOptions {
+        trials: trials.unwrap_or(10_000),
+        history_days: history_days.unwrap_or(90),
+        seed: seed.unwrap_or_else(|| Utc::now().timestamp_nanos_opt().unwrap_or_default() as u64),
+    };
+    let (remaining, completions) = workspaces
+        .database()?
+        .get_forecast_inputs(&project_id)
+        .map_err(|e| e.to_string())?;
+    // The database is released by now, so a long simulation blocks no one
+    analytics::forecast(remaining, &completions, &options, Utc::now())
+        .ok_or_else(|| "No tasks in this project were completed recently enough to forecast".to_string())
+}
+
+// Charts are sampled once per day, so keep the series to a sensible length
+fn check_chart_range(from: NaiveDate, to: NaiveDate) -> Result<(), String> {
+    if to < from {
+        return Err("The end date must not be before the start date".to_string());
+    }
+    if (to - from).num_days() > 366 {
+        return Err("Charts can cover at most one year".to_string());
+    }
+    Ok(())
+}
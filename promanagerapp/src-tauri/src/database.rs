@@ -1,20 +1,250 @@
-use rusqlite::{Connection, Result};
+use rusqlite::{Connection, OptionalExtension, Row, ToSql};
+use crate::analytics::{self, MetricsRange, TaskHistory};
+use crate::error::{Conflict, Error, Result};
+use crate::events::{ChangeEntity, ChangeEvent, ChangeKind};
+use crate::filter::{AssigneeFilter, MatchContext, ProjectFilter, TaskFilter};
+use crate::merge;
+use crate::reminders;
+use crate::templates;
 use crate::models::*;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
+use std::cell::{Cell, RefCell};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
 use uuid::Uuid;
 
+const PROJECT_COLUMNS: &str = "id, name, description, color, created_at, updated_at, status, auto_archive, revision";
+
+// Tasks shown in default listings and stats: those outside archived projects
+const VISIBLE_TASKS: &str = "(project_id IS NULL OR project_id NOT IN (SELECT id FROM projects WHERE status = 'archived'))";
+
+// Comment and checklist counts and assignees are worked out from their own
+// tables as the task is read. `qualified` relies on these expressions not
+// containing ", " and naming the task as `tasks`.
+const TASK_COLUMNS: &str = concat!(
+    "id, title, description, project_id, status, priority, due_date, completed, completed_at, created_at, updated_at, milestone_id, revision, ",
+    "(SELECT COUNT(*) FROM comments WHERE task_id = tasks.id), ",
+    "(SELECT COUNT(*) FROM checklist_items WHERE task_id = tasks.id), ",
+    "(SELECT COUNT(*) FROM checklist_items WHERE task_id = tasks.id AND checked), ",
+    "estimate_hours, ",
+    "(SELECT json_group_array(person_id) FROM (SELECT person_id FROM task_assignees WHERE task_id = tasks.id ORDER BY rowid))"
+);
+
+const TEMPLATE_COLUMNS: &str = "id, name, description, body, created_at, updated_at";
+
+const SPRINT_COLUMNS: &str = "id, name, goal, start_date, end_date, capacity, closed_at, created_at, updated_at";
+
+// Followed by the task's due_date and completed, which decide when it fires
+const REMINDER_COLUMNS: &str = "r.id, r.task_id, r.remind_at, r.minutes_before_due, r.snoozed_until, r.fired_at, r.created_at, t.due_date, t.completed";
+
+const COMMENT_COLUMNS: &str = "id, task_id, author, body, created_at, edited_at";
+
+const PERSON_COLUMNS: &str = "id, name, email, created_at";
+
+// Setting holding the id of the person using this copy of the app
+const CURRENT_USER_SETTING: &str = "current_user";
+
+const CHECKLIST_COLUMNS: &str = "id, task_id, text, checked, position, created_at";
+
+const ATTACHMENT_COLUMNS: &str = "id, task_id, name, mime_type, size, hash, created_at";
+
+const MILESTONE_COLUMNS: &str = "id, project_id, name, description, target_date, created_at, updated_at";
+
+// Window of recent completions used to judge whether a milestone is at risk
+const MILESTONE_THROUGHPUT_DAYS: i64 = 28;
+
+// In-progress tasks left untouched this long show up as stalled in digests
+const STALLED_DAYS: i64 = 3;
+
+// Color given to projects that are created implicitly, e.g. during an import
+const DEFAULT_PROJECT_COLOR: &str = "#3b82f6";
+
+pub const DEFAULT_DATABASE_PATH: &str = "promanager.db";
+
+// Called with every change the database makes, after it has been written
+pub type ChangeListener = Box<dyn Fn(&ChangeEvent) + Send>;
+
 pub struct Database {
     conn: Connection,
+    listeners: Vec<ChangeListener>,
+    source: Cell<ChangeSource>,
+    // Events held back while a transaction is open, so listeners never see
+    // changes that end up rolled back
+    pending_events: RefCell<Option<Vec<ChangeEvent>>>,
 }
 
 impl Database {
     pub fn new() -> Result<Self> {
-        let conn = Connection::open("promanager.db")?;
-        let db = Database { conn };
+        Self::open(DEFAULT_DATABASE_PATH)
+    }
+
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::open_encrypted(path, None)
+    }
+
+    /// Opens a database encrypted with `passphrase`, or a plain one without.
+    /// SQLCipher derives the key from the passphrase with PBKDF2-HMAC-SHA512
+    /// over 256,000 iterations and a random salt kept in the file, and
+    /// encrypts every page with AES-256.
+    pub fn open_encrypted<P: AsRef<Path>>(path: P, passphrase: Option<&str>) -> Result<Self> {
+        let conn = Connection::open(path)?;
+        if let Some(passphrase) = passphrase {
+            conn.pragma_update(None, "key", passphrase)?;
+        }
+        // SQLCipher only finds out whether the key is right on the first read
+        conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |_| Ok(()))?;
+        let db = Database {
+            conn,
+            listeners: Vec::new(),
+            source: Cell::new(ChangeSource::Gui),
+            pending_events: RefCell::new(None),
+        };
         db.create_tables()?;
         Ok(db)
     }
 
+    /// Closes the database, handing back its listeners so they can be
+    /// subscribed to the next one opened.
+    pub fn close(self) -> Vec<ChangeListener> {
+        self.listeners
+    }
+
+    pub fn subscribe<F: Fn(&ChangeEvent) + Send + 'static>(&mut self, listener: F) {
+        self.listeners.push(Box::new(listener));
+    }
+
+    pub fn resubscribe(&mut self, listeners: Vec<ChangeListener>) {
+        self.listeners.extend(listeners);
+    }
+
+    /// Writes a copy of the whole database to a new file, encrypted with
+    /// `passphrase` or in plain SQLite format without one.
+    pub fn export_copy<P: AsRef<Path>>(&self, path: P, passphrase: Option<&str>) -> Result<()> {
+        let path = path.as_ref().to_string_lossy().to_string();
+        self.conn.execute(
+            "ATTACH DATABASE ?1 AS export KEY ?2",
+            rusqlite::params![path, passphrase.unwrap_or("")],
+        )?;
+        let exported = self.conn.query_row("SELECT sqlcipher_export('export')", [], |_| Ok(()));
+        self.conn.execute("DETACH DATABASE export", [])?;
+        Ok(exported?)
+    }
+
+    /// Re-encrypts an encrypted database with a new passphrase.
+    pub fn rekey(&self, passphrase: &str) -> Result<()> {
+        Ok(self.conn.pragma_update(None, "rekey", passphrase)?)
+    }
+
+    fn publish(&self, event: ChangeEvent) {
+        if let Some(pending) = self.pending_events.borrow_mut().as_mut() {
+            pending.push(event);
+            return;
+        }
+        for listener in &self.listeners {
+            listener(&event);
+        }
+    }
+
+    /// Runs `f` so that everything it changes is committed together or not at
+    /// all, publishing change events only after the commit. Calls made while a
+    /// transaction is already open simply join it.
+    pub fn transaction<T>(&self, f: impl FnOnce(&Self) -> Result<T>) -> Result<T> {
+        if self.pending_events.borrow().is_some() {
+            return f(self);
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        *self.pending_events.borrow_mut() = Some(Vec::new());
+        let result = f(self).and_then(|value| Ok(tx.commit().map(|_| value)?));
+        let events = self.pending_events.borrow_mut().take().unwrap_or_default();
+
+        if result.is_ok() {
+            for event in events {
+                self.publish(event);
+            }
+        }
+        result
+    }
+
+    /// Applies every operation in a single transaction. If one fails nothing is
+    /// applied, and the error says which operation it was. Conflicts stay
+    /// conflicts, so callers still get the record as it is now.
+    pub fn batch(&self, operations: Vec<BatchOperation>) -> Result<Vec<BatchResult>> {
+        self.transaction(|db| {
+            let mut results = Vec::new();
+            for (i, operation) in operations.into_iter().enumerate() {
+                let name = operation.name();
+                let result = db.apply(operation).map_err(|e| {
+                    let reason = match e {
+                        Error::Conflict(mut conflict) => {
+                            conflict.operation = Some(i + 1);
+                            return Error::Conflict(conflict);
+                        }
+                        e if e.is_not_found() => "not found".to_string(),
+                        e => e.to_string(),
+                    };
+                    rejected(&format!("Operation {} ({}) failed: {}", i + 1, name, reason))
+                })?;
+                results.push(result);
+            }
+            Ok(results)
+        })
+    }
+
+    fn apply(&self, operation: BatchOperation) -> Result<BatchResult> {
+        Ok(match operation {
+            BatchOperation::CreateProject { project } => BatchResult::Project(self.create_project(project)?),
+            BatchOperation::UpdateProject { project } => BatchResult::Project(self.update_project(project)?),
+            BatchOperation::DeleteProject { id } => {
+                self.get_project(&id)?;
+                self.delete_project(&id)?;
+                BatchResult::Deleted(id)
+            }
+            BatchOperation::CreateTask { task } => BatchResult::Task(self.create_task(task)?),
+            BatchOperation::UpdateTask { task } => BatchResult::Task(self.update_task(task)?),
+            BatchOperation::CompleteTask { id, completed } => BatchResult::Task(self.update_task(UpdateTask {
+                id,
+                title: None,
+                description: Patch::Unchanged,
+                project_id: Patch::Unchanged,
+                status: None,
+                priority: None,
+                due_date: Patch::Unchanged,
+                estimate_hours: Patch::Unchanged,
+                completed: Some(completed),
+                milestone_id: Patch::Unchanged,
+                assignee_ids: None,
+                expected_revision: None,
+            })?),
+            BatchOperation::DeleteTask { id } => {
+                self.get_task(&id)?;
+                self.delete_task(&id)?;
+                BatchResult::Deleted(id)
+            }
+        })
+    }
+
+    /// Sets where subsequent changes are attributed to in the activity log,
+    /// returning the previous source.
+    pub fn set_source(&self, source: ChangeSource) -> ChangeSource {
+        self.source.replace(source)
+    }
+
+    /// Runs `f` with changes attributed to `source`, then restores the previous source.
+    pub fn with_source<T>(&self, source: ChangeSource, f: impl FnOnce(&Self) -> T) -> T {
+        let previous = self.source.replace(source);
+        let result = f(self);
+        self.source.set(previous);
+        result
+    }
+
+    /// Changes whenever another connection, such as the CLI, commits a write.
+    pub fn data_version(&self) -> Result<i64> {
+        Ok(self.conn.query_row("PRAGMA data_version", [], |row| row.get(0))?)
+    }
+
     fn create_tables(&self) -> Result<()> {
         self.conn.execute(
             "CREATE TABLE IF NOT EXISTS projects (
@@ -46,58 +276,258 @@ impl Database {
             [],
         )?;
 
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS milestones (
+                id TEXT PRIMARY KEY,
+                project_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                description TEXT,
+                target_date TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                FOREIGN KEY(project_id) REFERENCES projects(id)
+            )",
+            [],
+        )?;
+
+        self.add_column_if_missing("tasks", "milestone_id", "TEXT REFERENCES milestones(id)")?;
+        self.add_column_if_missing("projects", "status", "TEXT NOT NULL DEFAULT 'active'")?;
+        self.add_column_if_missing("projects", "auto_archive", "BOOLEAN NOT NULL DEFAULT 0")?;
+        // Bumped on every write so stale updates can be detected
+        self.add_column_if_missing("tasks", "revision", "INTEGER NOT NULL DEFAULT 1")?;
+        self.add_column_if_missing("projects", "revision", "INTEGER NOT NULL DEFAULT 1")?;
+        self.add_column_if_missing("tasks", "estimate_hours", "REAL")?;
+
+        // A task can pass through several sprints; `outcome` is filled in when
+        // the sprint closes and `carried_over_from` marks carried-over work
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS sprints (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                goal TEXT,
+                start_date TEXT NOT NULL,
+                end_date TEXT NOT NULL,
+                capacity INTEGER,
+                closed_at TEXT,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS sprint_tasks (
+                sprint_id TEXT NOT NULL,
+                task_id TEXT NOT NULL,
+                added_at TEXT NOT NULL,
+                carried_over_from TEXT,
+                outcome TEXT,
+                PRIMARY KEY(sprint_id, task_id),
+                FOREIGN KEY(sprint_id) REFERENCES sprints(id),
+                FOREIGN KEY(task_id) REFERENCES tasks(id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_sprint_tasks_task ON sprint_tasks(task_id);",
+        )?;
+
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS templates (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                description TEXT,
+                body TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                updated_at TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS reminders (
+                id TEXT PRIMARY KEY,
+                task_id TEXT NOT NULL,
+                remind_at TEXT,
+                minutes_before_due INTEGER,
+                snoozed_until TEXT,
+                fired_at TEXT,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY(task_id) REFERENCES tasks(id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_reminders_task ON reminders(task_id);",
+        )?;
+
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS comments (
+                id TEXT PRIMARY KEY,
+                task_id TEXT NOT NULL,
+                author TEXT NOT NULL,
+                body TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                edited_at TEXT,
+                FOREIGN KEY(task_id) REFERENCES tasks(id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_comments_task ON comments(task_id);",
+        )?;
+
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS people (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                email TEXT,
+                created_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS task_assignees (
+                task_id TEXT NOT NULL,
+                person_id TEXT NOT NULL,
+                assigned_at TEXT NOT NULL,
+                PRIMARY KEY(task_id, person_id),
+                FOREIGN KEY(task_id) REFERENCES tasks(id),
+                FOREIGN KEY(person_id) REFERENCES people(id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_task_assignees_person ON task_assignees(person_id);",
+        )?;
+
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS checklist_items (
+                id TEXT PRIMARY KEY,
+                task_id TEXT NOT NULL,
+                text TEXT NOT NULL,
+                checked BOOLEAN NOT NULL DEFAULT 0,
+                position INTEGER NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY(task_id) REFERENCES tasks(id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_checklist_items_task ON checklist_items(task_id);",
+        )?;
+
+        // File contents live in the attachment store, keyed by `hash`
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS attachments (
+                id TEXT PRIMARY KEY,
+                task_id TEXT NOT NULL,
+                name TEXT NOT NULL,
+                mime_type TEXT NOT NULL,
+                size INTEGER NOT NULL,
+                hash TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                FOREIGN KEY(task_id) REFERENCES tasks(id)
+            );
+            CREATE INDEX IF NOT EXISTS idx_attachments_task ON attachments(task_id);
+            CREATE INDEX IF NOT EXISTS idx_attachments_hash ON attachments(hash);",
+        )?;
+
+        // Preferences as JSON values, e.g. the digest schedule
+        self.conn.execute(
+            "CREATE TABLE IF NOT EXISTS settings (
+                key TEXT PRIMARY KEY,
+                value TEXT NOT NULL
+            )",
+            [],
+        )?;
+
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS activity (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                entity TEXT NOT NULL,
+                entity_id TEXT NOT NULL,
+                action TEXT NOT NULL,
+                field TEXT,
+                old_value TEXT,
+                new_value TEXT,
+                source TEXT NOT NULL,
+                created_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_activity_entity ON activity(entity, entity_id);
+            CREATE TRIGGER IF NOT EXISTS activity_no_update BEFORE UPDATE ON activity
+            BEGIN
+                SELECT RAISE(ABORT, 'activity is append-only');
+            END;
+            CREATE TRIGGER IF NOT EXISTS activity_no_delete BEFORE DELETE ON activity
+            BEGIN
+                SELECT RAISE(ABORT, 'activity is append-only');
+            END;",
+        )?;
+
+        self.conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS task_transitions (
+                id INTEGER PRIMARY KEY AUTOINCREMENT,
+                task_id TEXT NOT NULL,
+                from_status TEXT,
+                to_status TEXT NOT NULL,
+                transitioned_at TEXT NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_task_transitions_task ON task_transitions(task_id);",
+        )?;
+
+        // Tasks created before transitions were tracked start with their current status
+        self.conn.execute(
+            "INSERT INTO task_transitions (task_id, from_status, to_status, transitioned_at)
+             SELECT id, NULL, status, created_at FROM tasks
+             WHERE id NOT IN (SELECT task_id FROM task_transitions)",
+            [],
+        )?;
+
+        Ok(())
+    }
+
+    // For columns added after a table was first released
+    fn add_column_if_missing(&self, table: &str, column: &str, definition: &str) -> Result<()> {
+        let exists: bool = self.conn.query_row(
+            &format!("SELECT COUNT(*) > 0 FROM pragma_table_info('{}') WHERE name = ?1", table),
+            [column],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            self.conn.execute(
+                &format!("ALTER TABLE {} ADD COLUMN {} {}", table, column, definition),
+                [],
+            )?;
+        }
         Ok(())
     }
 
     // Project operations
     pub fn create_project(&self, project_data: CreateProject) -> Result<Project> {
-        let id = Uuid::new_v4().to_string();
-        let now = Utc::now();
+        self.transaction(|db| {
+            let id = Uuid::new_v4().to_string();
+            let now = Utc::now();
         
-        let project = Project {
-            id: id.clone(),
-            name: project_data.name,
-            description: project_data.description,
-            color: project_data.color,
-            created_at: now,
-            updated_at: now,
-        };
+            let project = Project {
+                id: id.clone(),
+                name: project_data.name,
+                description: project_data.description,
+                color: project_data.color,
+                created_at: now,
+                updated_at: now,
+                status: ProjectStatus::Active,
+                auto_archive: project_data.auto_archive,
+                revision: 1,
+            };
 
-        self.conn.execute(
-            "INSERT INTO projects (id, name, description, color, created_at, updated_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
-            (
-                &project.id,
-                &project.name,
-                &project.description,
-                &project.color,
-                project.created_at.to_rfc3339(),
-                project.updated_at.to_rfc3339(),
-            ),
-        )?;
+            db.conn.execute(
+                "INSERT INTO projects (id, name, description, color, created_at, updated_at, status, auto_archive) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                (
+                    &project.id,
+                    &project.name,
+                    &project.description,
+                    &project.color,
+                    project.created_at.to_rfc3339(),
+                    project.updated_at.to_rfc3339(),
+                    project.status.as_str(),
+                    project.auto_archive,
+                ),
+            )?;
 
-        Ok(project)
+            db.record_activity(ChangeEntity::Project, &project.id, ChangeKind::Created, None, None, Some(project.name.clone()))?;
+            db.publish(ChangeEvent::created(ChangeEntity::Project, &project.id, &project));
+            Ok(project)
+        })
     }
 
-    pub fn get_projects(&self) -> Result<Vec<Project>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, name, description, color, created_at, updated_at FROM projects ORDER BY created_at DESC"
-        )?;
-
-        let project_iter = stmt.query_map([], |row| {
-            Ok(Project {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                color: row.get(3)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-            })
-        })?;
+    /// Archived projects are left out unless `include_archived` is set.
+    pub fn get_projects(&self, include_archived: bool) -> Result<Vec<Project>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM projects WHERE ?1 OR status != 'archived' ORDER BY created_at DESC",
+            PROJECT_COLUMNS
+        ))?;
+
+        let project_iter = stmt.query_map([include_archived], project_from_row)?;
 
         let mut projects = Vec::new();
         for project in project_iter {
@@ -107,133 +537,266 @@ impl Database {
         Ok(projects)
     }
 
-    pub fn update_project(&self, project_data: UpdateProject) -> Result<Project> {
-        let now = Utc::now();
+    /// `limit` projects of `get_projects` after skipping `offset`, and how
+    /// many there are in all.
+    pub fn get_projects_page(&self, include_archived: bool, limit: i64, offset: i64) -> Result<(Vec<Project>, i64)> {
+        let total = self.conn.query_row(
+            "SELECT COUNT(*) FROM projects WHERE ?1 OR status != 'archived'",
+            [include_archived],
+            |row| row.get(0),
+        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM projects WHERE ?1 OR status != 'archived' ORDER BY created_at DESC LIMIT ?2 OFFSET ?3",
+            PROJECT_COLUMNS
+        ))?;
+        let projects = stmt
+            .query_map((include_archived, limit, offset), project_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok((projects, total))
+    }
 
-        // First get the current project
+    pub fn get_project(&self, id: &str) -> Result<Project> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, name, description, color, created_at, updated_at FROM projects WHERE id = ?1"
-        )?;
-
-        let mut project = stmt.query_row([&project_data.id], |row| {
-            Ok(Project {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                description: row.get(2)?,
-                color: row.get(3)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(4)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-            })
-        })?;
+            &format!("SELECT {} FROM projects WHERE id = ?1", PROJECT_COLUMNS),
+        )?;
 
-        // Update fields if provided
-        if let Some(name) = project_data.name {
-            project.name = name;
-        }
-        if let Some(description) = project_data.description {
-            project.description = Some(description);
-        }
-        if let Some(color) = project_data.color {
-            project.color = color;
+        Ok(stmt.query_row([id], project_from_row)?)
+    }
+
+    pub fn update_project(&self, project_data: UpdateProject) -> Result<Project> {
+        self.transaction(|db| {
+            let now = Utc::now();
+
+            // First get the current project
+            let mut stmt = db.conn.prepare(
+                &format!("SELECT {} FROM projects WHERE id = ?1", PROJECT_COLUMNS),
+            )?;
+
+            let mut project = stmt.query_row([&project_data.id], project_from_row)?;
+            if let Some(expected) = project_data.expected_revision {
+                if expected != project.revision {
+                    return Err(conflict(ChangeEntity::Project, expected, &project, Vec::new()));
+                }
+            }
+            let before = project.clone();
+
+            // Update fields if provided
+            if let Some(name) = project_data.name {
+                project.name = name;
+            }
+            project_data.description.apply(&mut project.description);
+            if let Some(color) = project_data.color {
+                project.color = color;
+            }
+            if let Some(status) = project_data.status {
+                project.status = status;
+            }
+            if let Some(auto_archive) = project_data.auto_archive {
+                project.auto_archive = auto_archive;
+            }
+            project.updated_at = now;
+            project.revision += 1;
+
+            // Update in database, unless someone else got there first
+            let updated = db.conn.execute(
+                "UPDATE projects SET name = ?1, description = ?2, color = ?3, updated_at = ?4, status = ?5, auto_archive = ?6, revision = ?7 
+                 WHERE id = ?8 AND revision = ?9",
+                (
+                    &project.name,
+                    &project.description,
+                    &project.color,
+                    project.updated_at.to_rfc3339(),
+                    project.status.as_str(),
+                    project.auto_archive,
+                    project.revision,
+                    &project.id,
+                    before.revision,
+                ),
+            )?;
+            if updated == 0 {
+                return Err(conflict(ChangeEntity::Project, before.revision, &db.get_project(&project.id)?, Vec::new()));
+            }
+
+            for (field, old_value, new_value) in project_changes(&before, &project) {
+                db.record_activity(ChangeEntity::Project, &project.id, ChangeKind::Updated, Some(field), old_value, new_value)?;
+            }
+            db.publish(ChangeEvent::updated(ChangeEntity::Project, &project.id, &project));
+            Ok(project)
+        })
+    }
+
+    /// Applies an update written against `base`, an earlier copy of the project.
+    /// Changes made since then are kept as long as they touch other fields.
+    pub fn merge_project_update(&self, base: &Project, mut project_data: UpdateProject) -> Result<Project> {
+        let current = self.get_project(&project_data.id)?;
+        let fields = merge::overlapping(base, &current, &project_data);
+        if !fields.is_empty() {
+            return Err(conflict(ChangeEntity::Project, base.revision, &current, fields));
         }
-        project.updated_at = now;
+        project_data.expected_revision = Some(current.revision);
+        self.update_project(project_data)
+    }
 
-        // Update in database
-        self.conn.execute(
-            "UPDATE projects SET name = ?1, description = ?2, color = ?3, updated_at = ?4 WHERE id = ?5",
-            (
-                &project.name,
-                &project.description,
-                &project.color,
-                project.updated_at.to_rfc3339(),
-                &project.id,
-            ),
-        )?;
+    /// Hides the project and its tasks from default listings and stats.
+    pub fn archive_project(&self, id: &str) -> Result<Project> {
+        self.set_project_status(id, ProjectStatus::Archived)
+    }
 
-        Ok(project)
+    pub fn unarchive_project(&self, id: &str) -> Result<Project> {
+        self.set_project_status(id, ProjectStatus::Active)
     }
 
-    pub fn delete_project(&self, id: &str) -> Result<()> {
-        // First delete all tasks associated with this project
-        self.conn.execute("DELETE FROM tasks WHERE project_id = ?1", [id])?;
-        
-        // Then delete the project
-        self.conn.execute("DELETE FROM projects WHERE id = ?1", [id])?;
-        
+    fn set_project_status(&self, id: &str, status: ProjectStatus) -> Result<Project> {
+        self.update_project(UpdateProject {
+            id: id.to_string(),
+            name: None,
+            description: Patch::Unchanged,
+            color: None,
+            status: Some(status),
+            auto_archive: None,
+            expected_revision: None,
+        })
+    }
+
+    // Archives a project that asked for it once its last open task is completed
+    fn auto_archive_if_done(&self, project_id: &str) -> Result<()> {
+        let (auto_archive, status, open): (bool, String, i64) = self.conn.query_row(
+            "SELECT p.auto_archive, p.status, 
+                    (SELECT COUNT(*) FROM tasks t WHERE t.project_id = p.id AND t.completed = 0) 
+             FROM projects p WHERE p.id = ?1",
+            [project_id],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+        if auto_archive && open == 0 && ProjectStatus::from(status) != ProjectStatus::Archived {
+            self.archive_project(project_id)?;
+        }
         Ok(())
     }
 
+    pub fn delete_project(&self, id: &str) -> Result<()> {
+        self.transaction(|db| {
+            let project = db.get_project(id).ok();
+            let tasks = db.get_tasks_by_project(id)?;
+
+            let milestones = db.get_milestones(Some(id))?;
+
+            // First delete all tasks and milestones associated with this project
+            db.conn.execute(
+                "DELETE FROM sprint_tasks WHERE task_id IN (SELECT id FROM tasks WHERE project_id = ?1)",
+                [id],
+            )?;
+            db.conn.execute(
+                "DELETE FROM reminders WHERE task_id IN (SELECT id FROM tasks WHERE project_id = ?1)",
+                [id],
+            )?;
+            db.conn.execute(
+                "DELETE FROM comments WHERE task_id IN (SELECT id FROM tasks WHERE project_id = ?1)",
+                [id],
+            )?;
+            db.conn.execute(
+                "DELETE FROM checklist_items WHERE task_id IN (SELECT id FROM tasks WHERE project_id = ?1)",
+                [id],
+            )?;
+            db.conn.execute(
+                "DELETE FROM task_assignees WHERE task_id IN (SELECT id FROM tasks WHERE project_id = ?1)",
+                [id],
+            )?;
+            // Their files stay in the store until the next garbage collection
+            db.conn.execute(
+                "DELETE FROM attachments WHERE task_id IN (SELECT id FROM tasks WHERE project_id = ?1)",
+                [id],
+            )?;
+            db.conn.execute("DELETE FROM tasks WHERE project_id = ?1", [id])?;
+            db.conn.execute("DELETE FROM milestones WHERE project_id = ?1", [id])?;
+
+            // Then delete the project
+            let deleted = db.conn.execute("DELETE FROM projects WHERE id = ?1", [id])?;
+
+            for task in &tasks {
+                db.record_activity(ChangeEntity::Task, &task.id, ChangeKind::Deleted, None, Some(task.title.clone()), None)?;
+                db.publish(ChangeEvent::deleted(ChangeEntity::Task, &task.id));
+            }
+            for milestone in &milestones {
+                let id = &milestone.milestone.id;
+                db.record_activity(ChangeEntity::Milestone, id, ChangeKind::Deleted, None, Some(milestone.milestone.name.clone()), None)?;
+                db.publish(ChangeEvent::deleted(ChangeEntity::Milestone, id));
+            }
+            if let (true, Some(project)) = (deleted > 0, project) {
+                db.record_activity(ChangeEntity::Project, id, ChangeKind::Deleted, None, Some(project.name), None)?;
+                db.publish(ChangeEvent::deleted(ChangeEntity::Project, id));
+            }
+            Ok(())
+        })
+    }
+
     // Task operations
     pub fn create_task(&self, task_data: CreateTask) -> Result<Task> {
-        let id = Uuid::new_v4().to_string();
-        let now = Utc::now();
+        self.transaction(|db| {
+            if task_data.estimate_hours.is_some_and(|hours| hours < 0.0) {
+                return Err(rejected("An estimate cannot be negative"));
+            }
+            let id = Uuid::new_v4().to_string();
+            let now = Utc::now();
         
-        let task = Task {
-            id: id.clone(),
-            title: task_data.title,
-            description: task_data.description,
-            project_id: task_data.project_id,
-            status: task_data.status,
-            priority: task_data.priority,
-            due_date: task_data.due_date,
-            completed: false,
-            completed_at: None,
-            created_at: now,
-            updated_at: now,
-        };
+            let mut task = Task {
+                id: id.clone(),
+                title: task_data.title,
+                description: task_data.description,
+                project_id: task_data.project_id,
+                status: task_data.status,
+                priority: task_data.priority,
+                due_date: task_data.due_date,
+                estimate_hours: task_data.estimate_hours,
+                completed: false,
+                completed_at: None,
+                created_at: now,
+                updated_at: now,
+                milestone_id: None,
+                assignee_ids: Vec::new(),
+                revision: 1,
+                comment_count: 0,
+                checklist_total: 0,
+                checklist_checked: 0,
+            };
+            if let Some(milestone_id) = task_data.milestone_id {
+                db.assign_milestone(&mut task, milestone_id)?;
+            }
 
-        self.conn.execute(
-            "INSERT INTO tasks (id, title, description, project_id, status, priority, due_date, completed, completed_at, created_at, updated_at) 
-             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
-            (
-                &task.id,
-                &task.title,
-                &task.description,
-                &task.project_id,
-                task.status.to_string(),
-                task.priority.to_string(),
-                task.due_date.map(|d| d.to_rfc3339()),
-                task.completed,
-                task.completed_at.map(|d| d.to_rfc3339()),
-                task.created_at.to_rfc3339(),
-                task.updated_at.to_rfc3339(),
-            ),
-        )?;
+            db.conn.execute(
+                "INSERT INTO tasks (id, title, description, project_id, status, priority, due_date, completed, completed_at, created_at, updated_at, milestone_id, estimate_hours) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                (
+                    &task.id,
+                    &task.title,
+                    &task.description,
+                    &task.project_id,
+                    task.status.to_string(),
+                    task.priority.to_string(),
+                    task.due_date.map(|d| d.to_rfc3339()),
+                    task.completed,
+                    task.completed_at.map(|d| d.to_rfc3339()),
+                    task.created_at.to_rfc3339(),
+                    task.updated_at.to_rfc3339(),
+                    &task.milestone_id,
+                    task.estimate_hours,
+                ),
+            )?;
 
-        Ok(task)
+            db.record_activity(ChangeEntity::Task, &task.id, ChangeKind::Created, None, None, Some(task.title.clone()))?;
+            db.record_transition(&task.id, None, &task.status, task.created_at)?;
+            db.publish(ChangeEvent::created(ChangeEntity::Task, &task.id, &task));
+            Ok(task)
+        })
     }
 
-    pub fn get_tasks(&self) -> Result<Vec<Task>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, description, project_id, status, priority, due_date, completed, completed_at, created_at, updated_at 
-             FROM tasks ORDER BY created_at DESC"
-        )?;
-
-        let task_iter = stmt.query_map([], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                description: row.get(2)?,
-                project_id: row.get(3)?,
-                status: TaskStatus::from(row.get::<_, String>(4)?),
-                priority: TaskPriority::from(row.get::<_, String>(5)?),
-                due_date: row.get::<_, Option<String>>(6)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                completed: row.get(7)?,
-                completed_at: row.get::<_, Option<String>>(8)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-            })
-        })?;
+    /// Tasks in archived projects are left out unless `include_archived` is set.
+    pub fn get_tasks(&self, include_archived: bool) -> Result<Vec<Task>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tasks WHERE ?1 OR {} ORDER BY created_at DESC",
+            TASK_COLUMNS, VISIBLE_TASKS
+        ))?;
+
+        let task_iter = stmt.query_map([include_archived], task_from_row)?;
 
         let mut tasks = Vec::new();
         for task in task_iter {
@@ -243,33 +806,20 @@ impl Database {
         Ok(tasks)
     }
 
+    pub fn get_task(&self, id: &str) -> Result<Task> {
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT {} FROM tasks WHERE id = ?1", TASK_COLUMNS),
+        )?;
+
+        Ok(stmt.query_row([id], task_from_row)?)
+    }
+
     pub fn get_tasks_by_project(&self, project_id: &str) -> Result<Vec<Task>> {
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, description, project_id, status, priority, due_date, completed, completed_at, created_at, updated_at 
-             FROM tasks WHERE project_id = ?1 ORDER BY created_at DESC"
-        )?;
-
-        let task_iter = stmt.query_map([project_id], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                description: row.get(2)?,
-                project_id: row.get(3)?,
-                status: TaskStatus::from(row.get::<_, String>(4)?),
-                priority: TaskPriority::from(row.get::<_, String>(5)?),
-                due_date: row.get::<_, Option<String>>(6)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                completed: row.get(7)?,
-                completed_at: row.get::<_, Option<String>>(8)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-            })
-        })?;
+            &format!("SELECT {} FROM tasks WHERE project_id = ?1 ORDER BY created_at DESC", TASK_COLUMNS),
+        )?;
+
+        let task_iter = stmt.query_map([project_id], task_from_row)?;
 
         let mut tasks = Vec::new();
         for task in task_iter {
@@ -279,46 +829,115 @@ impl Database {
         Ok(tasks)
     }
 
+    /// A page of `get_tasks`, like `get_projects_page`.
+    pub fn get_tasks_page(&self, include_archived: bool, limit: i64, offset: i64) -> Result<(Vec<Task>, i64)> {
+        self.task_page(&format!("(?1 OR {})", VISIBLE_TASKS), &[&include_archived], limit, offset)
+    }
+
+    /// A page of `get_tasks_by_project`, like `get_projects_page`.
+    pub fn get_tasks_by_project_page(&self, project_id: &str, limit: i64, offset: i64) -> Result<(Vec<Task>, i64)> {
+        self.task_page("project_id = ?1", &[&project_id], limit, offset)
+    }
+
+    // Newest first, as in the full listings
+    fn task_page(&self, filter: &str, params: &[&dyn ToSql], limit: i64, offset: i64) -> Result<(Vec<Task>, i64)> {
+        let total = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM tasks WHERE {}", filter),
+            params,
+            |row| row.get(0),
+        )?;
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tasks WHERE {} ORDER BY created_at DESC LIMIT {} OFFSET {}",
+            TASK_COLUMNS, filter, limit, offset
+        ))?;
+        let tasks = stmt.query_map(params, task_from_row)?.collect::<rusqlite::Result<_>>()?;
+        Ok((tasks, total))
+    }
+
+    pub fn filter_tasks(&self, filter: &TaskFilter) -> Result<Vec<Task>> {
+        let project_names: HashMap<String, String> = self
+            .get_projects(true)?
+            .into_iter()
+            .map(|p| (p.id, p.name))
+            .collect();
+
+        let people: HashMap<String, String> = self
+            .get_people()?
+            .into_iter()
+            .map(|p| (p.id, p.name))
+            .collect();
+        let current_user = match filter.assignee {
+            Some(AssigneeFilter::Me) => match self.get_current_user()? {
+                Some(person) => Some(person.id),
+                None => return Err(rejected("Set the current user to see the tasks assigned to you")),
+            },
+            _ => None,
+        };
+
+        // Words are searched for in the discussion too
+        let mut comments: HashMap<String, String> = HashMap::new();
+        if !filter.text.is_empty() {
+            for comment in self.get_comments(None)? {
+                let text = comments.entry(comment.task_id).or_default();
+                text.push_str(&comment.body);
+                text.push('\n');
+            }
+        }
+
+        // Naming a project reaches into it even when it is archived
+        let include_archived = matches!(filter.project, Some(ProjectFilter::Named(_)));
+        Ok(self
+            .get_tasks(include_archived)?
+            .into_iter()
+            .filter(|task| {
+                let context = MatchContext {
+                    project_name: task
+                        .project_id
+                        .as_ref()
+                        .and_then(|id| project_names.get(id))
+                        .map(String::as_str),
+                    comments: comments.get(&task.id).map_or("", String::as_str),
+                    assignee_names: task
+                        .assignee_ids
+                        .iter()
+                        .filter_map(|id| people.get(id))
+                        .map(String::as_str)
+                        .collect(),
+                    current_user: current_user.as_deref(),
+                };
+                filter.matches(task, &context)
+            })
+            .collect())
+    }
+
     pub fn update_task(&self, task_data: UpdateTask) -> Result<Task> {
         let now = Utc::now();
 
         // First get the current task
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, description, project_id, status, priority, due_date, completed, completed_at, created_at, updated_at 
-             FROM tasks WHERE id = ?1"
-        )?;
-
-        let mut task = stmt.query_row([&task_data.id], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                description: row.get(2)?,
-                project_id: row.get(3)?,
-                status: TaskStatus::from(row.get::<_, String>(4)?),
-                priority: TaskPriority::from(row.get::<_, String>(5)?),
-                due_date: row.get::<_, Option<String>>(6)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                completed: row.get(7)?,
-                completed_at: row.get::<_, Option<String>>(8)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-            })
-        })?;
+            &format!("SELECT {} FROM tasks WHERE id = ?1", TASK_COLUMNS),
+        )?;
+
+        let mut task = stmt.query_row([&task_data.id], task_from_row)?;
+        if let Some(expected) = task_data.expected_revision {
+            if expected != task.revision {
+                return Err(conflict(ChangeEntity::Task, expected, &task, Vec::new()));
+            }
+        }
+        let before = task.clone();
 
         // Update fields if provided
         if let Some(title) = task_data.title {
             task.title = title;
         }
-        if let Some(description) = task_data.description {
-            task.description = Some(description);
-        }
-        if let Some(project_id) = task_data.project_id {
-            task.project_id = Some(project_id);
+        task_data.description.apply(&mut task.description);
+        task_data.project_id.apply(&mut task.project_id);
+        match task_data.milestone_id {
+            Patch::Set(milestone_id) => self.assign_milestone(&mut task, milestone_id)?,
+            Patch::Clear => task.milestone_id = None,
+            // Milestones don't travel with a task into another project
+            Patch::Unchanged if task.project_id != before.project_id => task.milestone_id = None,
+            Patch::Unchanged => {}
         }
         if let Some(status) = task_data.status {
             task.status = status;
@@ -326,8 +945,10 @@ impl Database {
         if let Some(priority) = task_data.priority {
             task.priority = priority;
         }
-        if let Some(due_date) = task_data.due_date {
-            task.due_date = Some(due_date);
+        task_data.due_date.apply(&mut task.due_date);
+        task_data.estimate_hours.apply(&mut task.estimate_hours);
+        if task.estimate_hours.is_some_and(|hours| hours < 0.0) {
+            return Err(rejected("An estimate cannot be negative"));
         }
         if let Some(completed) = task_data.completed {
             task.completed = completed;
@@ -338,63 +959,217 @@ impl Database {
             }
         }
         task.updated_at = now;
+        let assignees = match &task_data.assignee_ids {
+            Some(person_ids) => Some(self.check_assignees(person_ids)?),
+            None => None,
+        };
 
-        // Update in database
-        self.conn.execute(
-            "UPDATE tasks SET title = ?1, description = ?2, project_id = ?3, status = ?4, priority = ?5, due_date = ?6, completed = ?7, completed_at = ?8, updated_at = ?9 WHERE id = ?10",
-            (
-                &task.title,
-                &task.description,
-                &task.project_id,
-                task.status.to_string(),
-                task.priority.to_string(),
-                task.due_date.map(|d| d.to_rfc3339()),
-                task.completed,
-                task.completed_at.map(|d| d.to_rfc3339()),
-                task.updated_at.to_rfc3339(),
-                &task.id,
-            ),
-        )?;
-
-        Ok(task)
+        self.transaction(|db| {
+            db.save_task(&before, &mut task)?;
+            match assignees {
+                Some(assignees) => db.set_assignees(&task, &assignees),
+                None => Ok(task),
+            }
+        })
     }
 
-    pub fn delete_task(&self, id: &str) -> Result<()> {
-        self.conn.execute("DELETE FROM tasks WHERE id = ?1", [id])?;
-        Ok(())
+    /// Applies an update written against `base`, an earlier copy of the task.
+    /// Changes made since then are kept as long as they touch other fields.
+    pub fn merge_task_update(&self, base: &Task, mut task_data: UpdateTask) -> Result<Task> {
+        let current = self.get_task(&task_data.id)?;
+        let fields = merge::overlapping(base, &current, &task_data);
+        if !fields.is_empty() {
+            return Err(conflict(ChangeEntity::Task, base.revision, &current, fields));
+        }
+        task_data.expected_revision = Some(current.revision);
+        self.update_task(task_data)
     }
 
-    pub fn get_today_tasks(&self) -> Result<Vec<Task>> {
-        let today = Utc::now().date_naive();
-        let today_start = today.and_hms_opt(0, 0, 0).unwrap().and_utc().to_rfc3339();
-        let today_end = today.and_hms_opt(23, 59, 59).unwrap().and_utc().to_rfc3339();
+    // Writes an edited task under the next revision, recording what changed.
+    // Fails with a conflict if the stored task is no longer at `before`'s revision.
+    fn save_task(&self, before: &Task, task: &mut Task) -> Result<()> {
+        self.transaction(|db| {
+            task.revision = before.revision + 1;
+            let updated = db.conn.execute(
+                "UPDATE tasks SET title = ?1, description = ?2, project_id = ?3, status = ?4, priority = ?5, due_date = ?6, completed = ?7, completed_at = ?8, updated_at = ?9, milestone_id = ?10, estimate_hours = ?11, revision = ?12 
+                 WHERE id = ?13 AND revision = ?14",
+                (
+                    &task.title,
+                    &task.description,
+                    &task.project_id,
+                    task.status.to_string(),
+                    task.priority.to_string(),
+                    task.due_date.map(|d| d.to_rfc3339()),
+                    task.completed,
+                    task.completed_at.map(|d| d.to_rfc3339()),
+                    task.updated_at.to_rfc3339(),
+                    &task.milestone_id,
+                    task.estimate_hours,
+                    task.revision,
+                    &task.id,
+                    before.revision,
+                ),
+            )?;
+            if updated == 0 {
+                return Err(conflict(ChangeEntity::Task, before.revision, &db.get_task(&task.id)?, Vec::new()));
+            }
+
+            for (field, old_value, new_value) in task_changes(before, task) {
+                db.record_activity(ChangeEntity::Task, &task.id, ChangeKind::Updated, Some(field), old_value, new_value)?;
+            }
+            if before.status != task.status {
+                db.record_transition(&task.id, Some(&before.status), &task.status, task.updated_at)?;
+            }
+            if before.due_date != task.due_date {
+                // Reminders relative to the old due date get another go at the new one
+                db.conn.execute(
+                    "UPDATE reminders SET fired_at = NULL, snoozed_until = NULL 
+                     WHERE task_id = ?1 AND minutes_before_due IS NOT NULL",
+                    [&task.id],
+                )?;
+            }
+            db.publish(ChangeEvent::updated(ChangeEntity::Task, &task.id, task));
+            if let (true, false, Some(project_id)) = (task.completed, before.completed, &task.project_id) {
+                db.auto_archive_if_done(project_id)?;
+            }
+            Ok(())
+        })
+    }
+
+    /// Creates a fresh, not yet started copy of a task in the same project and
+    /// milestone, with its checklist and assignees as `options` says.
+    pub fn duplicate_task(&self, id: &str, title: Option<String>, options: DuplicateOptions) -> Result<Task> {
+        let original = self.get_task(id)?;
+        let checklist = self.get_checklist(id)?;
+        self.transaction(|db| {
+            let copy = db.create_task(CreateTask {
+                title: title.unwrap_or_else(|| format!("{} (copy)", original.title)),
+                description: original.description,
+                project_id: original.project_id,
+                status: TaskStatus::Todo,
+                priority: original.priority,
+                due_date: original.due_date,
+                estimate_hours: original.estimate_hours,
+                milestone_id: original.milestone_id,
+            })?;
+            if options.checklist {
+                for item in checklist {
+                    db.add_checklist_item(CreateChecklistItem {
+                        task_id: copy.id.clone(),
+                        text: item.text,
+                    })?;
+                }
+            }
+            if options.assignees && !original.assignee_ids.is_empty() {
+                db.set_assignees(&copy, &original.assignee_ids)?;
+            }
+            db.get_task(&copy.id)
+        })
+    }
+
+    /// Moves tasks into a project, or out of any project when `project_id` is
+    /// `None`, all or nothing. Tasks leave their milestones behind.
+    pub fn move_tasks(&self, task_ids: &[String], project_id: Option<&str>) -> Result<Vec<Task>> {
+        if let Some(project_id) = project_id {
+            self.get_project(project_id)?;
+        }
+
+        self.transaction(|db| {
+            let mut tasks = Vec::new();
+            for task_id in task_ids {
+                tasks.push(db.reassign_task(task_id, project_id, false)?);
+            }
+            Ok(tasks)
+        })
+    }
+
+    /// Moves every task and milestone of `source_id` into `target_id`, then
+    /// deletes the source project, in one transaction.
+    pub fn merge_projects(&self, source_id: &str, target_id: &str) -> Result<Project> {
+        if source_id == target_id {
+            return Err(rejected("A project cannot be merged into itself"));
+        }
+        self.get_project(source_id)?;
+        let target = self.get_project(target_id)?;
+
+        self.transaction(|db| db.merge_into(source_id, target_id))?;
+        Ok(target)
+    }
+
+    fn merge_into(&self, source_id: &str, target_id: &str) -> Result<()> {
+        let milestones = self.get_milestones(Some(source_id))?;
+        self.conn.execute(
+            "UPDATE milestones SET project_id = ?1, updated_at = ?2 WHERE project_id = ?3",
+            (target_id, Utc::now().to_rfc3339(), source_id),
+        )?;
+        for progress in milestones {
+            let mut milestone = progress.milestone;
+            self.record_activity(
+                ChangeEntity::Milestone,
+                &milestone.id,
+                ChangeKind::Updated,
+                Some("project_id"),
+                Some(source_id.to_string()),
+                Some(target_id.to_string()),
+            )?;
+            milestone.project_id = target_id.to_string();
+            self.publish(ChangeEvent::updated(ChangeEntity::Milestone, &milestone.id, &milestone));
+        }
+
+        for task in self.get_tasks_by_project(source_id)? {
+            self.reassign_task(&task.id, Some(target_id), true)?;
+        }
+        self.delete_project(source_id)
+    }
+
+    fn reassign_task(&self, task_id: &str, project_id: Option<&str>, keep_milestone: bool) -> Result<Task> {
+        let before = self.get_task(task_id)?;
+        let mut task = before.clone();
+        if task.project_id.as_deref() == project_id {
+            return Ok(task);
+        }
+
+        task.project_id = project_id.map(str::to_string);
+        if !keep_milestone {
+            task.milestone_id = None;
+        }
+        task.updated_at = Utc::now();
+        self.save_task(&before, &mut task)?;
+        Ok(task)
+    }
+
+    pub fn delete_task(&self, id: &str) -> Result<()> {
+        self.transaction(|db| {
+            let title: Option<String> = db
+                .conn
+                .query_row("SELECT title FROM tasks WHERE id = ?1", [id], |row| row.get(0))
+                .optional()?;
+
+            db.conn.execute("DELETE FROM sprint_tasks WHERE task_id = ?1", [id])?;
+            db.conn.execute("DELETE FROM reminders WHERE task_id = ?1", [id])?;
+            db.conn.execute("DELETE FROM comments WHERE task_id = ?1", [id])?;
+            db.conn.execute("DELETE FROM checklist_items WHERE task_id = ?1", [id])?;
+            db.conn.execute("DELETE FROM task_assignees WHERE task_id = ?1", [id])?;
+            db.conn.execute("DELETE FROM attachments WHERE task_id = ?1", [id])?;
+            db.conn.execute("DELETE FROM tasks WHERE id = ?1", [id])?;
+            if let Some(title) = title {
+                db.record_activity(ChangeEntity::Task, id, ChangeKind::Deleted, None, Some(title), None)?;
+                db.publish(ChangeEvent::deleted(ChangeEntity::Task, id));
+            }
+            Ok(())
+        })
+    }
+
+    pub fn get_today_tasks(&self) -> Result<Vec<Task>> {
+        let today = Utc::now().date_naive();
+        let today_start = today.and_hms_opt(0, 0, 0).unwrap().and_utc().to_rfc3339();
+        let today_end = today.and_hms_opt(23, 59, 59).unwrap().and_utc().to_rfc3339();
 
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, description, project_id, status, priority, due_date, completed, completed_at, created_at, updated_at 
-             FROM tasks WHERE due_date BETWEEN ?1 AND ?2 ORDER BY due_date ASC"
-        )?;
-
-        let task_iter = stmt.query_map([today_start, today_end], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                description: row.get(2)?,
-                project_id: row.get(3)?,
-                status: TaskStatus::from(row.get::<_, String>(4)?),
-                priority: TaskPriority::from(row.get::<_, String>(5)?),
-                due_date: row.get::<_, Option<String>>(6)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                completed: row.get(7)?,
-                completed_at: row.get::<_, Option<String>>(8)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-            })
-        })?;
+            &format!("SELECT {} FROM tasks WHERE due_date BETWEEN ?1 AND ?2 AND {} ORDER BY due_date ASC", TASK_COLUMNS, VISIBLE_TASKS),
+        )?;
+
+        let task_iter = stmt.query_map([today_start, today_end], task_from_row)?;
 
         let mut tasks = Vec::new();
         for task in task_iter {
@@ -411,31 +1186,10 @@ impl Database {
         let next_week_end = next_week.and_hms_opt(23, 59, 59).unwrap().and_utc().to_rfc3339();
 
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, description, project_id, status, priority, due_date, completed, completed_at, created_at, updated_at 
-             FROM tasks WHERE due_date BETWEEN ?1 AND ?2 ORDER BY due_date ASC"
-        )?;
-
-        let task_iter = stmt.query_map([tomorrow_start, next_week_end], |row| {
-            Ok(Task {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                description: row.get(2)?,
-                project_id: row.get(3)?,
-                status: TaskStatus::from(row.get::<_, String>(4)?),
-                priority: TaskPriority::from(row.get::<_, String>(5)?),
-                due_date: row.get::<_, Option<String>>(6)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                completed: row.get(7)?,
-                completed_at: row.get::<_, Option<String>>(8)?
-                    .map(|s| DateTime::parse_from_rfc3339(&s).unwrap().with_timezone(&Utc)),
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(9)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(10)?)
-                    .unwrap()
-                    .with_timezone(&Utc),
-            })
-        })?;
+            &format!("SELECT {} FROM tasks WHERE due_date BETWEEN ?1 AND ?2 AND {} ORDER BY due_date ASC", TASK_COLUMNS, VISIBLE_TASKS),
+        )?;
+
+        let task_iter = stmt.query_map([tomorrow_start, next_week_end], task_from_row)?;
 
         let mut tasks = Vec::new();
         for task in task_iter {
@@ -446,31 +1200,2559 @@ impl Database {
     }
 
     pub fn get_task_stats(&self) -> Result<TaskStats> {
-        let mut stmt = self.conn.prepare("SELECT COUNT(*) FROM tasks")?;
-        let total_tasks: i32 = stmt.query_row([], |row| row.get(0))?;
+        let today = Utc::now().date_naive();
+        let today_start = today.and_hms_opt(0, 0, 0).unwrap().and_utc().to_rfc3339();
+        let today_end = today.and_hms_opt(23, 59, 59).unwrap().and_utc().to_rfc3339();
+        let now = Utc::now().to_rfc3339();
+
+        Ok(self.conn.query_row(
+            &format!("SELECT COUNT(*),
+                    COALESCE(SUM(completed = 1), 0),
+                    COALESCE(SUM(due_date BETWEEN ?1 AND ?2), 0),
+                    COALESCE(SUM(due_date < ?3 AND completed = 0), 0)
+             FROM tasks WHERE {}", VISIBLE_TASKS),
+            [today_start, today_end, now],
+            |row| {
+                let total_tasks: i32 = row.get(0)?;
+                let completed_tasks: i32 = row.get(1)?;
+                Ok(TaskStats {
+                    total_tasks,
+                    completed_tasks,
+                    pending_tasks: total_tasks - completed_tasks,
+                    today_tasks: row.get(2)?,
+                    overdue_tasks: row.get(3)?,
+                })
+            },
+        )?)
+    }
+
+    /// Everything the dashboard shows, computed in one statement. Daily series
+    /// cover the last `window_days` days up to and including today (UTC).
+    pub fn get_dashboard_stats(&self, window_days: u32) -> Result<DashboardStats> {
+        let now = Utc::now();
+        let today = now.date_naive().format("%Y-%m-%d").to_string();
+
+        let json: String = self.conn.query_row(
+            &format!("WITH RECURSIVE
+             visible_tasks AS (
+                 SELECT * FROM tasks WHERE {}
+             ),
+             days(d) AS (
+                 SELECT date(?1, '-' || (?2 - 1) || ' days')
+                 UNION ALL
+                 SELECT date(d, '+1 day') FROM days WHERE d < ?1
+             ),
+             totals AS (
+                 SELECT COUNT(*) AS total,
+                        COALESCE(SUM(completed = 1), 0) AS completed,
+                        COALESCE(SUM(substr(due_date, 1, 10) = ?1), 0) AS due_today,
+                        COALESCE(SUM(due_date < ?3 AND completed = 0), 0) AS overdue,
+                        COALESCE(SUM(status = 'todo'), 0) AS todo,
+                        COALESCE(SUM(status = 'in_progress'), 0) AS in_progress,
+                        COALESCE(SUM(status = 'done'), 0) AS done,
+                        COALESCE(SUM(priority = 'low'), 0) AS low,
+                        COALESCE(SUM(priority = 'medium'), 0) AS medium,
+                        COALESCE(SUM(priority = 'high'), 0) AS high
+                 FROM visible_tasks
+             ),
+             project_progress AS (
+                 SELECT p.id, p.name, p.color, p.created_at,
+                        COUNT(t.id) AS total,
+                        COALESCE(SUM(t.completed = 1), 0) AS completed
+                 FROM projects p LEFT JOIN visible_tasks t ON t.project_id = p.id
+                 WHERE p.status != 'archived'
+                 GROUP BY p.id
+             ),
+             completions AS (
+                 SELECT days.d, COUNT(t.id) AS n
+                 FROM days LEFT JOIN visible_tasks t
+                   ON t.completed = 1 AND substr(t.completed_at, 1, 10) = days.d
+                 GROUP BY days.d
+             ),
+             overdue_by_day AS (
+                 SELECT days.d, COUNT(t.id) AS n
+                 FROM days LEFT JOIN visible_tasks t
+                   ON substr(t.due_date, 1, 10) < days.d
+                  AND substr(t.created_at, 1, 10) <= days.d
+                  AND (t.completed_at IS NULL OR substr(t.completed_at, 1, 10) > days.d)
+                  AND (t.completed = 0 OR t.completed_at IS NOT NULL)
+                 GROUP BY days.d
+             ),
+             completion_days AS (
+                 SELECT DISTINCT substr(completed_at, 1, 10) AS d
+                 FROM visible_tasks WHERE completed = 1 AND completed_at IS NOT NULL
+             ),
+             islands AS (
+                 SELECT d, julianday(d) - ROW_NUMBER() OVER (ORDER BY d) AS grp FROM completion_days
+             ),
+             streak AS (
+                 -- The run of consecutive days ending today, or yesterday if nothing is done yet today
+                 SELECT COUNT(*) AS n FROM islands
+                 WHERE grp = (SELECT grp FROM islands ORDER BY d DESC LIMIT 1)
+                   AND (SELECT MAX(d) FROM islands) >= date(?1, '-1 day')
+             )
+             SELECT json_object(
+                 'totals', (SELECT json_object(
+                     'total_tasks', total,
+                     'completed_tasks', completed,
+                     'pending_tasks', total - completed,
+                     'today_tasks', due_today,
+                     'overdue_tasks', overdue) FROM totals),
+                 'by_status', (SELECT json_object(
+                     'todo', todo, 'in_progress', in_progress, 'done', done) FROM totals),
+                 'by_priority', (SELECT json_object(
+                     'low', low, 'medium', medium, 'high', high) FROM totals),
+                 'projects', (SELECT json_group_array(json_object(
+                     'project_id', id,
+                     'name', name,
+                     'color', color,
+                     'total_tasks', total,
+                     'completed_tasks', completed,
+                     'progress', CASE WHEN total > 0 THEN completed * 1.0 / total ELSE 0.0 END))
+                     FROM (SELECT * FROM project_progress ORDER BY created_at DESC)),
+                 'completions_per_day', (SELECT json_group_array(json_object('date', d, 'count', n))
+                     FROM (SELECT * FROM completions ORDER BY d)),
+                 'overdue_trend', (SELECT json_group_array(json_object('date', d, 'count', n))
+                     FROM (SELECT * FROM overdue_by_day ORDER BY d)),
+                 'completion_streak', (SELECT n FROM streak)
+             )", VISIBLE_TASKS),
+            (&today, window_days.max(1), now.to_rfc3339()),
+            |row| row.get(0),
+        )?;
+
+        serde_json::from_str(&json)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)).into())
+    }
+
+    // Milestone operations
+    pub fn create_milestone(&self, milestone_data: CreateMilestone) -> Result<Milestone> {
+        self.transaction(|db| {
+            // Fails with QueryReturnedNoRows for an unknown project
+            db.get_project(&milestone_data.project_id)?;
+
+            let now = Utc::now();
+            let milestone = Milestone {
+                id: Uuid::new_v4().to_string(),
+                project_id: milestone_data.project_id,
+                name: milestone_data.name,
+                description: milestone_data.description,
+                target_date: milestone_data.target_date,
+                created_at: now,
+                updated_at: now,
+            };
+
+            db.conn.execute(
+                "INSERT INTO milestones (id, project_id, name, description, target_date, created_at, updated_at) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                (
+                    &milestone.id,
+                    &milestone.project_id,
+                    &milestone.name,
+                    &milestone.description,
+                    milestone.target_date.to_rfc3339(),
+                    milestone.created_at.to_rfc3339(),
+                    milestone.updated_at.to_rfc3339(),
+                ),
+            )?;
+
+            db.record_activity(ChangeEntity::Milestone, &milestone.id, ChangeKind::Created, None, None, Some(milestone.name.clone()))?;
+            db.publish(ChangeEvent::created(ChangeEntity::Milestone, &milestone.id, &milestone));
+            Ok(milestone)
+        })
+    }
 
-        let mut stmt = self.conn.prepare("SELECT COUNT(*) FROM tasks WHERE completed = 1")?;
-        let completed_tasks: i32 = stmt.query_row([], |row| row.get(0))?;
+    /// Milestones of one project, or of every project for a roadmap, soonest first.
+    pub fn get_milestones(&self, project_id: Option<&str>) -> Result<Vec<MilestoneProgress>> {
+        self.query_milestones(
+            "WHERE m.project_id = ?1 OR (?1 IS NULL AND m.project_id NOT IN (SELECT id FROM projects WHERE status = 'archived'))",
+            &[&project_id],
+        )
+    }
 
-        let pending_tasks = total_tasks - completed_tasks;
+    pub fn get_milestone(&self, id: &str) -> Result<MilestoneProgress> {
+        self.query_milestones("WHERE m.id = ?1", &[&id])?
+            .pop()
+            .ok_or_else(Error::not_found)
+    }
 
+    fn query_milestones(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<MilestoneProgress>> {
         let today = Utc::now().date_naive();
-        let today_start = today.and_hms_opt(0, 0, 0).unwrap().and_utc().to_rfc3339();
-        let today_end = today.and_hms_opt(23, 59, 59).unwrap().and_utc().to_rfc3339();
+        let since = (Utc::now() - chrono::Duration::days(MILESTONE_THROUGHPUT_DAYS)).to_rfc3339();
+
+        let mut stmt = self.conn.prepare(
+            "SELECT project_id, COUNT(*) FROM tasks 
+             WHERE completed = 1 AND completed_at >= ?1 AND project_id IS NOT NULL 
+             GROUP BY project_id",
+        )?;
+        let mut throughput = HashMap::new();
+        for row in stmt.query_map([since], |row| Ok((row.get::<_, String>(0)?, row.get::<_, i64>(1)?)))? {
+            let (project_id, completed) = row?;
+            throughput.insert(project_id, completed as f64 / MILESTONE_THROUGHPUT_DAYS as f64);
+        }
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, COUNT(t.id), COALESCE(SUM(t.completed = 1), 0) 
+             FROM milestones m LEFT JOIN tasks t ON t.milestone_id = m.id 
+             {} 
+             GROUP BY m.id ORDER BY m.target_date ASC",
+            qualified(MILESTONE_COLUMNS, "m"), filter
+        ))?;
+
+        let milestone_iter = stmt.query_map(params, |row| {
+            Ok((milestone_from_row(row)?, row.get::<_, i64>(7)?, row.get::<_, i64>(8)?))
+        })?;
+
+        let mut milestones = Vec::new();
+        for milestone in milestone_iter {
+            let (milestone, total_tasks, completed_tasks) = milestone?;
+            let days_left = (milestone.target_date.date_naive() - today).num_days();
+            let daily_throughput = throughput.get(&milestone.project_id).copied().unwrap_or(0.0);
+            milestones.push(MilestoneProgress {
+                total_tasks,
+                completed_tasks,
+                progress: if total_tasks > 0 {
+                    completed_tasks as f64 / total_tasks as f64
+                } else {
+                    0.0
+                },
+                days_left,
+                health: analytics::milestone_health(total_tasks, completed_tasks, days_left, daily_throughput),
+                milestone,
+            });
+        }
+
+        Ok(milestones)
+    }
+
+    pub fn update_milestone(&self, milestone_data: UpdateMilestone) -> Result<Milestone> {
+        self.transaction(|db| {
+            let mut stmt = db.conn.prepare(
+                &format!("SELECT {} FROM milestones WHERE id = ?1", MILESTONE_COLUMNS),
+            )?;
+
+            let mut milestone = stmt.query_row([&milestone_data.id], milestone_from_row)?;
+            let before = milestone.clone();
+
+            if let Some(name) = milestone_data.name {
+                milestone.name = name;
+            }
+            milestone_data.description.apply(&mut milestone.description);
+            if let Some(target_date) = milestone_data.target_date {
+                milestone.target_date = target_date;
+            }
+            milestone.updated_at = Utc::now();
+
+            db.conn.execute(
+                "UPDATE milestones SET name = ?1, description = ?2, target_date = ?3, updated_at = ?4 WHERE id = ?5",
+                (
+                    &milestone.name,
+                    &milestone.description,
+                    milestone.target_date.to_rfc3339(),
+                    milestone.updated_at.to_rfc3339(),
+                    &milestone.id,
+                ),
+            )?;
+
+            for (field, old_value, new_value) in milestone_changes(&before, &milestone) {
+                db.record_activity(ChangeEntity::Milestone, &milestone.id, ChangeKind::Updated, Some(field), old_value, new_value)?;
+            }
+            db.publish(ChangeEvent::updated(ChangeEntity::Milestone, &milestone.id, &milestone));
+            Ok(milestone)
+        })
+    }
+
+    /// Deletes the milestone; its tasks stay in the project without a milestone.
+    pub fn delete_milestone(&self, id: &str) -> Result<()> {
+        self.transaction(|db| {
+            let milestone = db.get_milestone(id)?.milestone;
+            let mut stmt = db.conn.prepare(
+                &format!("SELECT {} FROM tasks WHERE milestone_id = ?1", TASK_COLUMNS),
+            )?;
+            let task_iter = stmt.query_map([id], task_from_row)?;
+
+            let mut tasks = Vec::new();
+            for task in task_iter {
+                tasks.push(task?);
+            }
+
+            let now = Utc::now();
+            db.conn.execute(
+                "UPDATE tasks SET milestone_id = NULL, updated_at = ?1, revision = revision + 1 WHERE milestone_id = ?2",
+                (now.to_rfc3339(), id),
+            )?;
+            db.conn.execute("DELETE FROM milestones WHERE id = ?1", [id])?;
+
+            for mut task in tasks {
+                task.milestone_id = None;
+                task.updated_at = now;
+                task.revision += 1;
+                db.record_activity(ChangeEntity::Task, &task.id, ChangeKind::Updated, Some("milestone_id"), Some(id.to_string()), None)?;
+                db.publish(ChangeEvent::updated(ChangeEntity::Task, &task.id, &task));
+            }
+            db.record_activity(ChangeEntity::Milestone, id, ChangeKind::Deleted, None, Some(milestone.name), None)?;
+            db.publish(ChangeEvent::deleted(ChangeEntity::Milestone, id));
+            Ok(())
+        })
+    }
+
+    // A milestone belongs to one project, so assigning it moves the task there
+    fn assign_milestone(&self, task: &mut Task, milestone_id: String) -> Result<()> {
+        let project_id: String = self.conn.query_row(
+            "SELECT project_id FROM milestones WHERE id = ?1",
+            [&milestone_id],
+            |row| row.get(0),
+        )?;
+        task.project_id = Some(project_id);
+        task.milestone_id = Some(milestone_id);
+        Ok(())
+    }
+
+    // Sprint operations
+    pub fn create_sprint(&self, sprint_data: CreateSprint) -> Result<Sprint> {
+        self.transaction(|db| {
+            if sprint_data.end_date < sprint_data.start_date {
+                return Err(rejected("A sprint cannot end before it starts"));
+            }
+
+            let now = Utc::now();
+            let sprint = Sprint {
+                id: Uuid::new_v4().to_string(),
+                name: sprint_data.name,
+                goal: sprint_data.goal,
+                start_date: sprint_data.start_date,
+                end_date: sprint_data.end_date,
+                capacity: sprint_data.capacity,
+                status: SprintStatus::at(sprint_data.start_date, None, now),
+                closed_at: None,
+                created_at: now,
+                updated_at: now,
+            };
+
+            db.conn.execute(
+                "INSERT INTO sprints (id, name, goal, start_date, end_date, capacity, closed_at, created_at, updated_at) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, NULL, ?7, ?8)",
+                (
+                    &sprint.id,
+                    &sprint.name,
+                    &sprint.goal,
+                    sprint.start_date.to_rfc3339(),
+                    sprint.end_date.to_rfc3339(),
+                    sprint.capacity,
+                    sprint.created_at.to_rfc3339(),
+                    sprint.updated_at.to_rfc3339(),
+                ),
+            )?;
+
+            db.record_activity(ChangeEntity::Sprint, &sprint.id, ChangeKind::Created, None, None, Some(sprint.name.clone()))?;
+            db.publish(ChangeEvent::created(ChangeEntity::Sprint, &sprint.id, &sprint));
+            Ok(sprint)
+        })
+    }
+
+    pub fn get_sprints(&self) -> Result<Vec<Sprint>> {
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT {} FROM sprints ORDER BY start_date DESC", SPRINT_COLUMNS),
+        )?;
+
+        let sprint_iter = stmt.query_map([], sprint_from_row)?;
+
+        let mut sprints = Vec::new();
+        for sprint in sprint_iter {
+            sprints.push(sprint?);
+        }
+
+        Ok(sprints)
+    }
+
+    pub fn get_sprint(&self, id: &str) -> Result<Sprint> {
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT {} FROM sprints WHERE id = ?1", SPRINT_COLUMNS),
+        )?;
 
-        let mut stmt = self.conn.prepare("SELECT COUNT(*) FROM tasks WHERE due_date BETWEEN ?1 AND ?2")?;
-        let today_tasks: i32 = stmt.query_row([today_start, today_end], |row| row.get(0))?;
+        Ok(stmt.query_row([id], sprint_from_row)?)
+    }
+
+    pub fn update_sprint(&self, sprint_data: UpdateSprint) -> Result<Sprint> {
+        self.transaction(|db| {
+            let mut sprint = db.get_sprint(&sprint_data.id)?;
+            let before = sprint.clone();
+
+            if let Some(name) = sprint_data.name {
+                sprint.name = name;
+            }
+            sprint_data.goal.apply(&mut sprint.goal);
+            if let Some(start_date) = sprint_data.start_date {
+                sprint.start_date = start_date;
+            }
+            if let Some(end_date) = sprint_data.end_date {
+                sprint.end_date = end_date;
+            }
+            sprint_data.capacity.apply(&mut sprint.capacity);
+            if sprint.end_date < sprint.start_date {
+                return Err(rejected("A sprint cannot end before it starts"));
+            }
+            sprint.updated_at = Utc::now();
+            sprint.status = SprintStatus::at(sprint.start_date, sprint.closed_at, sprint.updated_at);
+
+            db.conn.execute(
+                "UPDATE sprints SET name = ?1, goal = ?2, start_date = ?3, end_date = ?4, capacity = ?5, updated_at = ?6 WHERE id = ?7",
+                (
+                    &sprint.name,
+                    &sprint.goal,
+                    sprint.start_date.to_rfc3339(),
+                    sprint.end_date.to_rfc3339(),
+                    sprint.capacity,
+                    sprint.updated_at.to_rfc3339(),
+                    &sprint.id,
+                ),
+            )?;
+
+            for (field, old_value, new_value) in sprint_changes(&before, &sprint) {
+                db.record_activity(ChangeEntity::Sprint, &sprint.id, ChangeKind::Updated, Some(field), old_value, new_value)?;
+            }
+            db.publish(ChangeEvent::updated(ChangeEntity::Sprint, &sprint.id, &sprint));
+            Ok(sprint)
+        })
+    }
+
+    /// Deletes the sprint and its task assignments; the tasks themselves are kept.
+    pub fn delete_sprint(&self, id: &str) -> Result<()> {
+        self.transaction(|db| {
+            let sprint = db.get_sprint(id)?;
+
+            db.conn.execute("DELETE FROM sprint_tasks WHERE sprint_id = ?1", [id])?;
+            db.conn.execute("DELETE FROM sprints WHERE id = ?1", [id])?;
+
+            db.record_activity(ChangeEntity::Sprint, id, ChangeKind::Deleted, None, Some(sprint.name), None)?;
+            db.publish(ChangeEvent::deleted(ChangeEntity::Sprint, id));
+            Ok(())
+        })
+    }
 
+    /// Adds tasks to an open sprint, taking them out of any other open sprint.
+    pub fn add_tasks_to_sprint(&self, sprint_id: &str, task_ids: &[String]) -> Result<SprintReport> {
         let now = Utc::now().to_rfc3339();
-        let mut stmt = self.conn.prepare("SELECT COUNT(*) FROM tasks WHERE due_date < ?1 AND completed = 0")?;
-        let overdue_tasks: i32 = stmt.query_row([now], |row| row.get(0))?;
+        self.transaction(|db| {
+            let sprint = db.get_sprint(sprint_id)?;
+            if sprint.closed_at.is_some() {
+                return Err(rejected("Tasks cannot be added to a closed sprint"));
+            }
+
+            for task_id in task_ids {
+                // Fails with QueryReturnedNoRows for an unknown task
+                db.get_task(task_id)?;
+                let previous: Option<String> = db
+                    .conn
+                    .query_row(
+                        "SELECT sprint_id FROM sprint_tasks WHERE task_id = ?1 AND sprint_id != ?2 AND outcome IS NULL",
+                        (task_id, sprint_id),
+                        |row| row.get(0),
+                    )
+                    .optional()?;
+                if let Some(previous) = &previous {
+                    db.conn.execute(
+                        "DELETE FROM sprint_tasks WHERE task_id = ?1 AND sprint_id = ?2",
+                        (task_id, previous),
+                    )?;
+                    let previous_sprint = db.get_sprint(previous)?;
+                    db.publish(ChangeEvent::updated(ChangeEntity::Sprint, previous, &previous_sprint));
+                }
+                let added = db.conn.execute(
+                    "INSERT OR IGNORE INTO sprint_tasks (sprint_id, task_id, added_at) VALUES (?1, ?2, ?3)",
+                    (sprint_id, task_id, &now),
+                )?;
+                if added > 0 {
+                    db.record_activity(ChangeEntity::Task, task_id, ChangeKind::Updated, Some("sprint_id"), previous, Some(sprint_id.to_string()))?;
+                }
+            }
+
+            db.publish(ChangeEvent::updated(ChangeEntity::Sprint, sprint_id, &sprint));
+            Ok(())
+        })?;
+        self.get_sprint_report(sprint_id)
+    }
+
+    pub fn remove_task_from_sprint(&self, sprint_id: &str, task_id: &str) -> Result<()> {
+        self.transaction(|db| {
+            let sprint = db.get_sprint(sprint_id)?;
+            if sprint.closed_at.is_some() {
+                return Err(rejected("Tasks cannot be removed from a closed sprint"));
+            }
+
+            let removed = db.conn.execute(
+                "DELETE FROM sprint_tasks WHERE sprint_id = ?1 AND task_id = ?2",
+                (sprint_id, task_id),
+            )?;
+            if removed == 0 {
+                return Err(Error::not_found());
+            }
+
+            db.record_activity(ChangeEntity::Task, task_id, ChangeKind::Updated, Some("sprint_id"), Some(sprint_id.to_string()), None)?;
+            db.publish(ChangeEvent::updated(ChangeEntity::Sprint, sprint_id, &sprint));
+            Ok(())
+        })
+    }
+
+    /// Closes the sprint, recording what was finished, and carries unfinished
+    /// tasks over to `next_sprint_id`, or else to the next open sprint by start
+    /// date. Without one they are simply left unscheduled.
+    pub fn close_sprint(&self, id: &str, next_sprint_id: Option<&str>) -> Result<SprintReport> {
+        let mut sprint = self.get_sprint(id)?;
+        if sprint.closed_at.is_some() {
+            return Err(rejected("The sprint is already closed"));
+        }
 
-        Ok(TaskStats {
-            total_tasks,
+        let next = match next_sprint_id {
+            Some(next_id) if next_id == id => return Err(rejected("A sprint cannot carry over into itself")),
+            Some(next_id) => {
+                let next = self.get_sprint(next_id)?;
+                if next.closed_at.is_some() {
+                    return Err(rejected("Work cannot be carried over into a closed sprint"));
+                }
+                Some(next)
+            }
+            None => {
+                let mut stmt = self.conn.prepare(&format!(
+                    "SELECT {} FROM sprints 
+                     WHERE closed_at IS NULL AND id != ?1 AND start_date >= ?2 
+                     ORDER BY start_date ASC LIMIT 1",
+                    SPRINT_COLUMNS
+                ))?;
+                stmt.query_row((id, sprint.start_date.to_rfc3339()), sprint_from_row)
+                    .optional()?
+            }
+        };
+
+        let now = Utc::now();
+        let unfinished = match &next {
+            Some(_) => SprintOutcome::CarriedOver,
+            None => SprintOutcome::Incomplete,
+        };
+        self.transaction(|db| {
+            db.conn.execute(
+                "UPDATE sprint_tasks SET outcome = ?1 
+                 WHERE sprint_id = ?2 AND task_id IN (SELECT id FROM tasks WHERE completed = 1 OR status = 'done')",
+                (SprintOutcome::Completed.as_str(), id),
+            )?;
+            if let Some(next) = &next {
+                db.conn.execute(
+                    "INSERT OR IGNORE INTO sprint_tasks (sprint_id, task_id, added_at, carried_over_from) 
+                     SELECT ?1, task_id, ?2, sprint_id FROM sprint_tasks WHERE sprint_id = ?3 AND outcome IS NULL",
+                    (&next.id, now.to_rfc3339(), id),
+                )?;
+            }
+            db.conn.execute(
+                "UPDATE sprint_tasks SET outcome = ?1 WHERE sprint_id = ?2 AND outcome IS NULL",
+                (unfinished.as_str(), id),
+            )?;
+            db.conn.execute(
+                "UPDATE sprints SET closed_at = ?1, updated_at = ?1 WHERE id = ?2",
+                (now.to_rfc3339(), id),
+            )?;
+
+            let before = sprint.status.clone();
+            sprint.closed_at = Some(now);
+            sprint.updated_at = now;
+            sprint.status = SprintStatus::Closed;
+            db.record_activity(
+                ChangeEntity::Sprint,
+                id,
+                ChangeKind::Updated,
+                Some("status"),
+                Some(before.as_str().to_string()),
+                Some(sprint.status.as_str().to_string()),
+            )?;
+            db.publish(ChangeEvent::updated(ChangeEntity::Sprint, id, &sprint));
+            if let Some(next) = &next {
+                db.publish(ChangeEvent::updated(ChangeEntity::Sprint, &next.id, next));
+            }
+            Ok(())
+        })?;
+        self.get_sprint_report(id)
+    }
+
+    /// Committed work is what was planned by the start date or carried over
+    /// from an earlier sprint; anything else counts as added mid-sprint.
+    pub fn get_sprint_report(&self, id: &str) -> Result<SprintReport> {
+        let sprint = self.get_sprint(id)?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {}, st.added_at, st.carried_over_from, st.outcome 
+             FROM sprint_tasks st JOIN tasks t ON t.id = st.task_id 
+             WHERE st.sprint_id = ?1 ORDER BY st.added_at ASC",
+            qualified(TASK_COLUMNS, "t")
+        ))?;
+
+        // The sprint's own columns come after the task's
+        let n = TASK_COLUMNS.split(", ").count();
+        let task_iter = stmt.query_map([id], |row| {
+            let task = task_from_row(row)?;
+            let added_at = parse_timestamp(row.get(n)?);
+            let carried_over_from: Option<String> = row.get(n + 1)?;
+            let outcome = row.get::<_, Option<String>>(n + 2)?.map(SprintOutcome::from);
+            Ok(SprintTask {
+                committed: carried_over_from.is_some() || added_at <= sprint.start_date,
+                completed: match &outcome {
+                    Some(outcome) => *outcome == SprintOutcome::Completed,
+                    None => task.completed || task.status == TaskStatus::Done,
+                },
+                task,
+                added_at,
+                carried_over_from,
+                outcome,
+            })
+        })?;
+
+        let mut tasks = Vec::new();
+        for task in task_iter {
+            tasks.push(task?);
+        }
+
+        let carried_over_to: Option<String> = self
+            .conn
+            .query_row(
+                "SELECT sprint_id FROM sprint_tasks WHERE carried_over_from = ?1 LIMIT 1",
+                [id],
+                |row| row.get(0),
+            )
+            .optional()?;
+
+        let count = |f: &dyn Fn(&SprintTask) -> bool| tasks.iter().filter(|t| f(t)).count() as i64;
+        let committed_tasks = count(&|t| t.committed);
+        let completed_tasks = count(&|t| t.completed);
+        let carried_over_tasks = count(&|t| t.outcome == Some(SprintOutcome::CarriedOver));
+
+        Ok(SprintReport {
+            committed_tasks,
+            added_tasks: tasks.len() as i64 - committed_tasks,
             completed_tasks,
-            pending_tasks,
-            today_tasks,
-            overdue_tasks,
+            carried_over_tasks,
+            completion_rate: if tasks.is_empty() {
+                0.0
+            } else {
+                completed_tasks as f64 / tasks.len() as f64
+            },
+            carried_over_to,
+            sprint,
+            tasks,
+        })
+    }
+
+    // Template operations
+    /// Saves the project's milestones and tasks as a reusable template.
+    pub fn save_project_as_template(&self, template_data: SaveProjectTemplate) -> Result<ProjectTemplate> {
+        self.transaction(|db| {
+            let project = db.get_project(&template_data.project_id)?;
+            let milestones: Vec<Milestone> = db
+                .get_milestones(Some(&project.id))?
+                .into_iter()
+                .map(|progress| progress.milestone)
+                .collect();
+            let mut tasks = db.get_tasks_by_project(&project.id)?;
+            tasks.reverse(); // oldest first, so instances list them in the original order
+
+            let now = Utc::now();
+            let body = templates::template_body(&project, &milestones, &tasks);
+            let template = ProjectTemplate {
+                id: Uuid::new_v4().to_string(),
+                name: template_data.name,
+                description: template_data.description,
+                variables: templates::variables(&body),
+                body,
+                created_at: now,
+                updated_at: now,
+            };
+
+            db.conn.execute(
+                "INSERT INTO templates (id, name, description, body, created_at, updated_at) 
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                (
+                    &template.id,
+                    &template.name,
+                    &template.description,
+                    serde_json::to_string(&template.body).unwrap(),
+                    template.created_at.to_rfc3339(),
+                    template.updated_at.to_rfc3339(),
+                ),
+            )?;
+
+            db.record_activity(ChangeEntity::Template, &template.id, ChangeKind::Created, None, None, Some(template.name.clone()))?;
+            db.publish(ChangeEvent::created(ChangeEntity::Template, &template.id, &template));
+            Ok(template)
+        })
+    }
+
+    pub fn get_templates(&self) -> Result<Vec<ProjectTemplate>> {
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT {} FROM templates ORDER BY name COLLATE NOCASE ASC", TEMPLATE_COLUMNS),
+        )?;
+
+        let template_iter = stmt.query_map([], template_from_row)?;
+
+        let mut templates = Vec::new();
+        for template in template_iter {
+            templates.push(template?);
+        }
+
+        Ok(templates)
+    }
+
+    pub fn get_template(&self, id: &str) -> Result<ProjectTemplate> {
+        let mut stmt = self.conn.prepare(
+            &format!("SELECT {} FROM templates WHERE id = ?1", TEMPLATE_COLUMNS),
+        )?;
+
+        Ok(stmt.query_row([id], template_from_row)?)
+    }
+
+    pub fn update_template(&self, template_data: UpdateProjectTemplate) -> Result<ProjectTemplate> {
+        self.transaction(|db| {
+            let mut template = db.get_template(&template_data.id)?;
+            let before = template.clone();
+
+            if let Some(name) = template_data.name {
+                template.name = name;
+            }
+            template_data.description.apply(&mut template.description);
+            template.updated_at = Utc::now();
+
+            db.conn.execute(
+                "UPDATE templates SET name = ?1, description = ?2, updated_at = ?3 WHERE id = ?4",
+                (
+                    &template.name,
+                    &template.description,
+                    template.updated_at.to_rfc3339(),
+                    &template.id,
+                ),
+            )?;
+
+            let changes = changed_fields(vec![
+                ("name", Some(before.name), Some(template.name.clone())),
+                ("description", before.description, template.description.clone()),
+            ]);
+            for (field, old_value, new_value) in changes {
+                db.record_activity(ChangeEntity::Template, &template.id, ChangeKind::Updated, Some(field), old_value, new_value)?;
+            }
+            db.publish(ChangeEvent::updated(ChangeEntity::Template, &template.id, &template));
+            Ok(template)
+        })
+    }
+
+    pub fn delete_template(&self, id: &str) -> Result<()> {
+        self.transaction(|db| {
+            let template = db.get_template(id)?;
+            db.conn.execute("DELETE FROM templates WHERE id = ?1", [id])?;
+
+            db.record_activity(ChangeEntity::Template, id, ChangeKind::Deleted, None, Some(template.name), None)?;
+            db.publish(ChangeEvent::deleted(ChangeEntity::Template, id));
+            Ok(())
+        })
+    }
+
+    /// Creates a new project from a template, scheduling it from `start_date`.
+    /// Every placeholder must have a value; `start_date` is filled in if not given.
+    pub fn instantiate_template(&self, request: InstantiateTemplate) -> Result<Project> {
+        let body = self.get_template(&request.template_id)?.body;
+
+        let mut values = request.variables;
+        values
+            .entry(templates::START_DATE_VARIABLE.to_string())
+            .or_insert_with(|| request.start_date.format("%Y-%m-%d").to_string());
+        let fill = |text: &str| templates::substitute(text, &values).map_err(|e| rejected(&e));
+        let fill_optional = |text: &Option<String>| text.as_deref().map(fill).transpose();
+
+        // Substitute everything up front so a missing variable fails before any writes
+        let project_name = match request.project_name {
+            Some(name) => name,
+            None => fill(&body.project_name)?,
+        };
+        let project_description = fill_optional(&body.project_description)?;
+        let mut milestones = Vec::new();
+        for milestone in &body.milestones {
+            milestones.push((fill(&milestone.name)?, fill_optional(&milestone.description)?, milestone));
+        }
+        let mut tasks = Vec::new();
+        for task in &body.tasks {
+            tasks.push((fill(&task.title)?, fill_optional(&task.description)?, task));
+        }
+
+        self.transaction(|db| {
+            let project = db.create_project(CreateProject {
+                name: project_name,
+                description: project_description,
+                color: body.color.clone(),
+                auto_archive: false,
+            })?;
+
+            let mut milestone_ids = Vec::new();
+            for (name, description, milestone) in milestones {
+                let created = db.create_milestone(CreateMilestone {
+                    project_id: project.id.clone(),
+                    name,
+                    description,
+                    target_date: templates::shifted(request.start_date, milestone.target_offset_minutes),
+                })?;
+                milestone_ids.push(created.id);
+            }
+
+            for (title, description, task) in tasks {
+                db.create_task(CreateTask {
+                    title,
+                    description,
+                    project_id: Some(project.id.clone()),
+                    status: TaskStatus::Todo,
+                    priority: task.priority.clone(),
+                    due_date: task
+                        .due_offset_minutes
+                        .map(|offset| templates::shifted(request.start_date, offset)),
+                    estimate_hours: task.estimate_hours,
+                    milestone_id: task.milestone.and_then(|i| milestone_ids.get(i).cloned()),
+                })?;
+            }
+
+            Ok(project)
+        })
+    }
+
+    // Reminder operations
+    pub fn create_reminder(&self, reminder_data: CreateReminder) -> Result<Reminder> {
+        self.transaction(|db| {
+            let task = db.get_task(&reminder_data.task_id)?;
+            match (&reminder_data.remind_at, reminder_data.minutes_before_due) {
+                (Some(_), None) => {}
+                (None, Some(minutes)) if minutes < 0 => {
+                    return Err(rejected("A reminder cannot come after the due date"));
+                }
+                (None, Some(_)) if task.due_date.is_none() => {
+                    return Err(rejected("The task has no due date to be reminded of"));
+                }
+                (None, Some(_)) => {}
+                _ => return Err(rejected("A reminder needs either a time or a number of minutes before the due date")),
+            }
+
+            let id = Uuid::new_v4().to_string();
+            db.conn.execute(
+                "INSERT INTO reminders (id, task_id, remind_at, minutes_before_due, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    &id,
+                    &task.id,
+                    reminder_data.remind_at.map(|at| at.to_rfc3339()),
+                    reminder_data.minutes_before_due,
+                    Utc::now().to_rfc3339(),
+                ),
+            )?;
+
+            let reminder = db.get_reminder(&id)?;
+            db.record_activity(
+                ChangeEntity::Reminder,
+                &id,
+                ChangeKind::Created,
+                None,
+                None,
+                reminder.next_at.map(|at| at.to_rfc3339()),
+            )?;
+            db.publish(ChangeEvent::created(ChangeEntity::Reminder, &id, &reminder));
+            Ok(reminder)
+        })
+    }
+
+    pub fn get_reminder(&self, id: &str) -> Result<Reminder> {
+        self.query_reminders("r.id = ?1", &[&id])?
+            .pop()
+            .ok_or_else(Error::not_found)
+    }
+
+    /// Reminders of one task, or of every task, soonest first.
+    pub fn get_reminders(&self, task_id: Option<&str>) -> Result<Vec<Reminder>> {
+        let mut reminders = match task_id {
+            Some(task_id) => self.query_reminders("r.task_id = ?1", &[&task_id])?,
+            None => self.query_reminders("1", &[])?,
+        };
+        // Those that won't fire again go last
+        reminders.sort_by_key(|reminder| (reminder.next_at.is_none(), reminder.next_at));
+        Ok(reminders)
+    }
+
+    /// Unfired reminders of open tasks that are due by `now`, with their tasks.
+    pub fn get_due_reminders(&self, now: DateTime<Utc>) -> Result<Vec<(Reminder, Task)>> {
+        let mut due = Vec::new();
+        for reminder in self.query_reminders("r.fired_at IS NULL AND t.completed = 0", &[])? {
+            if reminder.next_at.is_some_and(|at| at <= now) {
+                let task = self.get_task(&reminder.task_id)?;
+                due.push((reminder, task));
+            }
+        }
+        Ok(due)
+    }
+
+    /// When the next reminder is due, if any is still to fire.
+    pub fn next_reminder_at(&self) -> Result<Option<DateTime<Utc>>> {
+        Ok(self
+            .query_reminders("r.fired_at IS NULL AND t.completed = 0", &[])?
+            .into_iter()
+            .filter_map(|reminder| reminder.next_at)
+            .min())
+    }
+
+    pub fn mark_reminder_fired(&self, id: &str, at: DateTime<Utc>) -> Result<()> {
+        self.transaction(|db| {
+            let before = db.get_reminder(id)?;
+            db.conn.execute(
+                "UPDATE reminders SET fired_at = ?1 WHERE id = ?2",
+                (at.to_rfc3339(), id),
+            )?;
+
+            let reminder = db.get_reminder(id)?;
+            db.record_activity(
+                ChangeEntity::Reminder,
+                id,
+                ChangeKind::Updated,
+                Some("fired_at"),
+                before.fired_at.map(|at| at.to_rfc3339()),
+                Some(at.to_rfc3339()),
+            )?;
+            db.publish(ChangeEvent::updated(ChangeEntity::Reminder, id, &reminder));
+            Ok(())
+        })
+    }
+
+    /// Fires the reminder again at `until`, whether or not it has fired already.
+    pub fn snooze_reminder(&self, id: &str, until: DateTime<Utc>) -> Result<Reminder> {
+        self.transaction(|db| {
+            let before = db.get_reminder(id)?;
+            db.conn.execute(
+                "UPDATE reminders SET snoozed_until = ?1, fired_at = NULL WHERE id = ?2",
+                (until.to_rfc3339(), id),
+            )?;
+
+            let reminder = db.get_reminder(id)?;
+            db.record_activity(
+                ChangeEntity::Reminder,
+                id,
+                ChangeKind::Updated,
+                Some("snoozed_until"),
+                before.snoozed_until.map(|at| at.to_rfc3339()),
+                Some(until.to_rfc3339()),
+            )?;
+            db.publish(ChangeEvent::updated(ChangeEntity::Reminder, id, &reminder));
+            Ok(reminder)
         })
     }
+
+    pub fn delete_reminder(&self, id: &str) -> Result<()> {
+        self.transaction(|db| {
+            db.get_reminder(id)?;
+            db.conn.execute("DELETE FROM reminders WHERE id = ?1", [id])?;
+
+            db.record_activity(ChangeEntity::Reminder, id, ChangeKind::Deleted, None, None, None)?;
+            db.publish(ChangeEvent::deleted(ChangeEntity::Reminder, id));
+            Ok(())
+        })
+    }
+
+    fn query_reminders(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<Reminder>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM reminders r JOIN tasks t ON t.id = r.task_id WHERE {}",
+            REMINDER_COLUMNS, filter
+        ))?;
+        let reminders = stmt.query_map(params, reminder_from_row)?;
+        Ok(reminders.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // Comment operations
+    pub fn add_comment(&self, comment_data: CreateComment) -> Result<Comment> {
+        self.transaction(|db| {
+            let task = db.get_task(&comment_data.task_id)?;
+            let author = comment_data.author.trim();
+            if author.is_empty() {
+                return Err(rejected("A comment needs an author"));
+            }
+            if comment_data.body.trim().is_empty() {
+                return Err(rejected("A comment cannot be empty"));
+            }
+
+            let comment = Comment {
+                id: Uuid::new_v4().to_string(),
+                task_id: task.id,
+                author: author.to_string(),
+                body: comment_data.body,
+                created_at: Utc::now(),
+                edited_at: None,
+            };
+            db.conn.execute(
+                "INSERT INTO comments (id, task_id, author, body, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+                (
+                    &comment.id,
+                    &comment.task_id,
+                    &comment.author,
+                    &comment.body,
+                    comment.created_at.to_rfc3339(),
+                ),
+            )?;
+
+            db.record_activity(ChangeEntity::Comment, &comment.id, ChangeKind::Created, None, None, Some(comment.body.clone()))?;
+            db.publish(ChangeEvent::created(ChangeEntity::Comment, &comment.id, &comment));
+            Ok(comment)
+        })
+    }
+
+    pub fn get_comment(&self, id: &str) -> Result<Comment> {
+        self.query_comments("id = ?1", &[&id])?
+            .pop()
+            .ok_or_else(Error::not_found)
+    }
+
+    /// Comments on one task, or on every task, oldest first.
+    pub fn get_comments(&self, task_id: Option<&str>) -> Result<Vec<Comment>> {
+        match task_id {
+            Some(task_id) => self.query_comments("task_id = ?1", &[&task_id]),
+            None => self.query_comments("1", &[]),
+        }
+    }
+
+    pub fn edit_comment(&self, comment_data: UpdateComment) -> Result<Comment> {
+        self.transaction(|db| {
+            let before = db.get_comment(&comment_data.id)?;
+            if comment_data.body.trim().is_empty() {
+                return Err(rejected("A comment cannot be empty"));
+            }
+            if comment_data.body == before.body {
+                return Ok(before);
+            }
+
+            let mut comment = before.clone();
+            comment.body = comment_data.body;
+            comment.edited_at = Some(Utc::now());
+            db.conn.execute(
+                "UPDATE comments SET body = ?1, edited_at = ?2 WHERE id = ?3",
+                (&comment.body, comment.edited_at.map(|at| at.to_rfc3339()), &comment.id),
+            )?;
+
+            db.record_activity(
+                ChangeEntity::Comment,
+                &comment.id,
+                ChangeKind::Updated,
+                Some("body"),
+                Some(before.body),
+                Some(comment.body.clone()),
+            )?;
+            db.publish(ChangeEvent::updated(ChangeEntity::Comment, &comment.id, &comment));
+            Ok(comment)
+        })
+    }
+
+    pub fn delete_comment(&self, id: &str) -> Result<()> {
+        self.transaction(|db| {
+            let comment = db.get_comment(id)?;
+            db.conn.execute("DELETE FROM comments WHERE id = ?1", [id])?;
+
+            db.record_activity(ChangeEntity::Comment, id, ChangeKind::Deleted, None, Some(comment.body), None)?;
+            db.publish(ChangeEvent::deleted(ChangeEntity::Comment, id));
+            Ok(())
+        })
+    }
+
+    fn query_comments(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<Comment>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM comments WHERE {} ORDER BY created_at ASC",
+            COMMENT_COLUMNS, filter
+        ))?;
+        let comments = stmt.query_map(params, comment_from_row)?;
+        Ok(comments.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // People and assignees
+    pub fn create_person(&self, person_data: CreatePerson) -> Result<Person> {
+        self.transaction(|db| {
+            let name = person_data.name.trim();
+            db.check_person_name(name, None)?;
+
+            let person = Person {
+                id: Uuid::new_v4().to_string(),
+                name: name.to_string(),
+                email: person_data.email.filter(|email| !email.trim().is_empty()),
+                created_at: Utc::now(),
+            };
+            db.conn.execute(
+                "INSERT INTO people (id, name, email, created_at) VALUES (?1, ?2, ?3, ?4)",
+                (&person.id, &person.name, &person.email, person.created_at.to_rfc3339()),
+            )?;
+
+            db.record_activity(ChangeEntity::Person, &person.id, ChangeKind::Created, None, None, Some(person.name.clone()))?;
+            db.publish(ChangeEvent::created(ChangeEntity::Person, &person.id, &person));
+            Ok(person)
+        })
+    }
+
+    /// Everyone in the directory, by name.
+    pub fn get_people(&self) -> Result<Vec<Person>> {
+        self.query_people("1", &[])
+    }
+
+    pub fn get_person(&self, id: &str) -> Result<Person> {
+        self.query_people("id = ?1", &[&id])?
+            .pop()
+            .ok_or_else(Error::not_found)
+    }
+
+    pub fn update_person(&self, person_data: UpdatePerson) -> Result<Person> {
+        self.transaction(|db| {
+            let before = db.get_person(&person_data.id)?;
+            let mut person = before.clone();
+            if let Some(name) = person_data.name {
+                person.name = name.trim().to_string();
+                db.check_person_name(&person.name, Some(&person.id))?;
+            }
+            person_data.email.apply(&mut person.email);
+
+            db.conn.execute(
+                "UPDATE people SET name = ?1, email = ?2 WHERE id = ?3",
+                (&person.name, &person.email, &person.id),
+            )?;
+            for (field, old_value, new_value) in changed_fields(vec![
+                ("name", Some(before.name), Some(person.name.clone())),
+                ("email", before.email, person.email.clone()),
+            ]) {
+                db.record_activity(ChangeEntity::Person, &person.id, ChangeKind::Updated, Some(field), old_value, new_value)?;
+            }
+            db.publish(ChangeEvent::updated(ChangeEntity::Person, &person.id, &person));
+            Ok(person)
+        })
+    }
+
+    /// Removes someone from the directory and from every task they were
+    /// assigned to.
+    pub fn delete_person(&self, id: &str) -> Result<()> {
+        let person = self.get_person(id)?;
+        let tasks = self.query_tasks("id IN (SELECT task_id FROM task_assignees WHERE person_id = ?1)", &[&id])?;
+        self.transaction(|db| {
+            for task in tasks {
+                let remaining: Vec<String> = task.assignee_ids.iter().filter(|a| *a != id).cloned().collect();
+                db.set_assignees(&task, &remaining)?;
+            }
+            db.conn.execute("DELETE FROM people WHERE id = ?1", [id])?;
+            if db.get_setting::<String>(CURRENT_USER_SETTING)?.as_deref() == Some(id) {
+                db.set_setting::<String>(CURRENT_USER_SETTING, None)?;
+            }
+
+            db.record_activity(ChangeEntity::Person, id, ChangeKind::Deleted, None, Some(person.name), None)?;
+            db.publish(ChangeEvent::deleted(ChangeEntity::Person, id));
+            Ok(())
+        })
+    }
+
+    /// The person `assignee:me` refers to, if one has been chosen.
+    pub fn get_current_user(&self) -> Result<Option<Person>> {
+        match self.get_setting::<String>(CURRENT_USER_SETTING)? {
+            Some(id) => match self.get_person(&id) {
+                Err(e) if e.is_not_found() => Ok(None),
+                person => person.map(Some),
+            },
+            None => Ok(None),
+        }
+    }
+
+    pub fn set_current_user(&self, person_id: Option<&str>) -> Result<Option<Person>> {
+        let person = match person_id {
+            Some(id) => Some(self.get_person(id)?),
+            None => None,
+        };
+        self.set_setting(CURRENT_USER_SETTING, person.as_ref().map(|p| &p.id))?;
+        Ok(person)
+    }
+
+    /// Makes `person_ids` the assignees of each task, replacing whoever was
+    /// assigned before, all or nothing. No one at all unassigns the tasks.
+    pub fn assign_tasks(&self, task_ids: &[String], person_ids: &[String]) -> Result<Vec<Task>> {
+        let assignees = self.check_assignees(person_ids)?;
+        self.transaction(|db| {
+            let mut tasks = Vec::new();
+            for task_id in task_ids {
+                let task = db.get_task(task_id)?;
+                tasks.push(db.set_assignees(&task, &assignees)?);
+            }
+            Ok(tasks)
+        })
+    }
+
+    /// Passes every open task of `from_id` to `to_id`, or just takes them off
+    /// `from_id` when `to_id` is `None`. Other assignees stay on the tasks.
+    pub fn hand_over_tasks(&self, from_id: &str, to_id: Option<&str>) -> Result<Vec<Task>> {
+        self.get_person(from_id)?;
+        if let Some(to_id) = to_id {
+            self.get_person(to_id)?;
+        }
+        let open = self.query_tasks(
+            "completed = 0 AND id IN (SELECT task_id FROM task_assignees WHERE person_id = ?1)",
+            &[&from_id],
+        )?;
+
+        self.transaction(|db| {
+            let mut tasks = Vec::new();
+            for task in open {
+                let mut assignees: Vec<String> = task.assignee_ids.iter().filter(|id| *id != from_id).cloned().collect();
+                if let Some(to_id) = to_id {
+                    if !assignees.iter().any(|id| id == to_id) {
+                        assignees.push(to_id.to_string());
+                    }
+                }
+                tasks.push(db.set_assignees(&task, &assignees)?);
+            }
+            Ok(tasks)
+        })
+    }
+
+    /// Open tasks and estimates per person, by the week they are due.
+    pub fn get_workload(&self) -> Result<Vec<Workload>> {
+        let open = self.query_tasks(&format!("completed = 0 AND {}", VISIBLE_TASKS), &[])?;
+        Ok(analytics::workload(self.get_people()?, &open))
+    }
+
+    // Assignments are part of the task, so they show in its history, but they
+    // are not edits that could conflict and leave the revision alone
+    fn set_assignees(&self, task: &Task, assignees: &[String]) -> Result<Task> {
+        self.transaction(|db| {
+            if task.assignee_ids == assignees {
+                return Ok(task.clone());
+            }
+
+            let now = Utc::now();
+            db.conn.execute("DELETE FROM task_assignees WHERE task_id = ?1", [&task.id])?;
+            for person_id in assignees {
+                db.conn.execute(
+                    "INSERT INTO task_assignees (task_id, person_id, assigned_at) VALUES (?1, ?2, ?3)",
+                    (&task.id, person_id, now.to_rfc3339()),
+                )?;
+            }
+            db.conn.execute(
+                "UPDATE tasks SET updated_at = ?1 WHERE id = ?2",
+                (now.to_rfc3339(), &task.id),
+            )?;
+
+            let updated = db.get_task(&task.id)?;
+            db.record_activity(
+                ChangeEntity::Task,
+                &task.id,
+                ChangeKind::Updated,
+                Some("assignee_ids"),
+                Some(task.assignee_ids.join(", ")).filter(|ids| !ids.is_empty()),
+                Some(assignees.join(", ")).filter(|ids| !ids.is_empty()),
+            )?;
+            db.publish(ChangeEvent::updated(ChangeEntity::Task, &task.id, &updated));
+            Ok(updated)
+        })
+    }
+
+    // The people to assign, each once, failing for anyone not in the directory
+    fn check_assignees(&self, person_ids: &[String]) -> Result<Vec<String>> {
+        let mut assignees: Vec<String> = Vec::new();
+        for person_id in person_ids {
+            self.get_person(person_id)?;
+            if !assignees.contains(person_id) {
+                assignees.push(person_id.clone());
+            }
+        }
+        Ok(assignees)
+    }
+
+    // Names pick people out in filters and the CLI, so they must be unique
+    fn check_person_name(&self, name: &str, id: Option<&str>) -> Result<()> {
+        if name.is_empty() {
+            return Err(rejected("A person needs a name"));
+        }
+        let taken: bool = self.conn.query_row(
+            "SELECT COUNT(*) > 0 FROM people WHERE name = ?1 COLLATE NOCASE AND id IS NOT ?2",
+            (name, id),
+            |row| row.get(0),
+        )?;
+        if taken {
+            return Err(rejected(&format!("There is already someone called {}", name)));
+        }
+        Ok(())
+    }
+
+    fn query_people(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<Person>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM people WHERE {} ORDER BY name COLLATE NOCASE ASC",
+            PERSON_COLUMNS, filter
+        ))?;
+        let people = stmt.query_map(params, person_from_row)?;
+        Ok(people.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // Checklist operations
+    pub fn add_checklist_item(&self, item_data: CreateChecklistItem) -> Result<ChecklistItem> {
+        self.transaction(|db| {
+            let task = db.get_task(&item_data.task_id)?;
+            let text = item_data.text.trim();
+            if text.is_empty() {
+                return Err(rejected("A checklist item needs some text"));
+            }
+
+            let position: i64 = db.conn.query_row(
+                "SELECT COALESCE(MAX(position) + 1, 0) FROM checklist_items WHERE task_id = ?1",
+                [&task.id],
+                |row| row.get(0),
+            )?;
+            let item = ChecklistItem {
+                id: Uuid::new_v4().to_string(),
+                task_id: task.id,
+                text: text.to_string(),
+                checked: false,
+                position,
+                created_at: Utc::now(),
+            };
+            db.conn.execute(
+                "INSERT INTO checklist_items (id, task_id, text, checked, position, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                (
+                    &item.id,
+                    &item.task_id,
+                    &item.text,
+                    item.checked,
+                    item.position,
+                    item.created_at.to_rfc3339(),
+                ),
+            )?;
+
+            db.record_activity(ChangeEntity::ChecklistItem, &item.id, ChangeKind::Created, None, None, Some(item.text.clone()))?;
+            db.publish(ChangeEvent::created(ChangeEntity::ChecklistItem, &item.id, &item));
+            Ok(item)
+        })
+    }
+
+    pub fn get_checklist_item(&self, id: &str) -> Result<ChecklistItem> {
+        self.query_checklist("id = ?1", &[&id])?
+            .pop()
+            .ok_or_else(Error::not_found)
+    }
+
+    /// The task's checklist in order.
+    pub fn get_checklist(&self, task_id: &str) -> Result<Vec<ChecklistItem>> {
+        self.query_checklist("task_id = ?1", &[&task_id])
+    }
+
+    pub fn toggle_checklist_item(&self, id: &str) -> Result<ChecklistItem> {
+        self.transaction(|db| {
+            let mut item = db.get_checklist_item(id)?;
+            item.checked = !item.checked;
+            db.conn.execute(
+                "UPDATE checklist_items SET checked = ?1 WHERE id = ?2",
+                (item.checked, id),
+            )?;
+
+            db.record_activity(
+                ChangeEntity::ChecklistItem,
+                id,
+                ChangeKind::Updated,
+                Some("checked"),
+                Some((!item.checked).to_string()),
+                Some(item.checked.to_string()),
+            )?;
+            db.publish(ChangeEvent::updated(ChangeEntity::ChecklistItem, id, &item));
+            Ok(item)
+        })
+    }
+
+    /// Puts the task's checklist in the order of `item_ids`, which must list
+    /// every item exactly once.
+    pub fn reorder_checklist(&self, task_id: &str, item_ids: &[String]) -> Result<Vec<ChecklistItem>> {
+        let checklist = self.get_checklist(task_id)?;
+        let mut current: Vec<&str> = checklist.iter().map(|item| item.id.as_str()).collect();
+        let mut requested: Vec<&str> = item_ids.iter().map(String::as_str).collect();
+        current.sort_unstable();
+        requested.sort_unstable();
+        if current != requested {
+            return Err(rejected("The new order must list every item of the checklist exactly once"));
+        }
+
+        self.transaction(|db| {
+            for item in &checklist {
+                let position = item_ids.iter().position(|id| *id == item.id).unwrap_or_default() as i64;
+                if position == item.position {
+                    continue;
+                }
+                db.conn.execute(
+                    "UPDATE checklist_items SET position = ?1 WHERE id = ?2",
+                    (position, &item.id),
+                )?;
+                db.record_activity(
+                    ChangeEntity::ChecklistItem,
+                    &item.id,
+                    ChangeKind::Updated,
+                    Some("position"),
+                    Some(item.position.to_string()),
+                    Some(position.to_string()),
+                )?;
+                let moved = ChecklistItem { position, ..item.clone() };
+                db.publish(ChangeEvent::updated(ChangeEntity::ChecklistItem, &item.id, &moved));
+            }
+            db.get_checklist(task_id)
+        })
+    }
+
+    pub fn remove_checklist_item(&self, id: &str) -> Result<()> {
+        self.transaction(|db| {
+            let item = db.get_checklist_item(id)?;
+            db.conn.execute("DELETE FROM checklist_items WHERE id = ?1", [id])?;
+
+            db.record_activity(ChangeEntity::ChecklistItem, id, ChangeKind::Deleted, None, Some(item.text), None)?;
+            db.publish(ChangeEvent::deleted(ChangeEntity::ChecklistItem, id));
+            Ok(())
+        })
+    }
+
+    /// Turns a checklist item into a task of its own in the same project and
+    /// milestone, taking it off the checklist.
+    pub fn convert_checklist_item(&self, id: &str) -> Result<Task> {
+        let item = self.get_checklist_item(id)?;
+        let parent = self.get_task(&item.task_id)?;
+        self.transaction(|db| {
+            let task = db.create_task(CreateTask {
+                title: item.text.clone(),
+                description: None,
+                project_id: parent.project_id.clone(),
+                status: if item.checked { TaskStatus::Done } else { TaskStatus::Todo },
+                priority: parent.priority.clone(),
+                due_date: None,
+                estimate_hours: None,
+                milestone_id: parent.milestone_id.clone(),
+            })?;
+            db.remove_checklist_item(id)?;
+            Ok(task)
+        })
+    }
+
+    fn query_checklist(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<ChecklistItem>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM checklist_items WHERE {} ORDER BY position ASC, created_at ASC",
+            CHECKLIST_COLUMNS, filter
+        ))?;
+        let items = stmt.query_map(params, checklist_item_from_row)?;
+        Ok(items.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // Attachment operations
+    /// Records a file already put into the attachment store.
+    pub fn add_attachment(&self, attachment_data: CreateAttachment) -> Result<Attachment> {
+        self.transaction(|db| {
+            let task = db.get_task(&attachment_data.task_id)?;
+            let attachment = Attachment {
+                id: Uuid::new_v4().to_string(),
+                task_id: task.id,
+                name: attachment_data.name,
+                mime_type: attachment_data.mime_type,
+                size: attachment_data.size,
+                hash: attachment_data.hash,
+                created_at: Utc::now(),
+            };
+            db.conn.execute(
+                "INSERT INTO attachments (id, task_id, name, mime_type, size, hash, created_at) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+                (
+                    &attachment.id,
+                    &attachment.task_id,
+                    &attachment.name,
+                    &attachment.mime_type,
+                    attachment.size,
+                    &attachment.hash,
+                    attachment.created_at.to_rfc3339(),
+                ),
+            )?;
+
+            db.record_activity(ChangeEntity::Attachment, &attachment.id, ChangeKind::Created, None, None, Some(attachment.name.clone()))?;
+            db.publish(ChangeEvent::created(ChangeEntity::Attachment, &attachment.id, &attachment));
+            Ok(attachment)
+        })
+    }
+
+    pub fn get_attachment(&self, id: &str) -> Result<Attachment> {
+        self.query_attachments("id = ?1", &[&id])?
+            .pop()
+            .ok_or_else(Error::not_found)
+    }
+
+    /// Attachments of one task, oldest first.
+    pub fn get_attachments(&self, task_id: &str) -> Result<Vec<Attachment>> {
+        self.query_attachments("task_id = ?1", &[&task_id])
+    }
+
+    /// Removes the record only; the caller decides what happens to the file.
+    pub fn delete_attachment(&self, id: &str) -> Result<Attachment> {
+        self.transaction(|db| {
+            let attachment = db.get_attachment(id)?;
+            db.conn.execute("DELETE FROM attachments WHERE id = ?1", [id])?;
+
+            db.record_activity(ChangeEntity::Attachment, id, ChangeKind::Deleted, None, Some(attachment.name.clone()), None)?;
+            db.publish(ChangeEvent::deleted(ChangeEntity::Attachment, id));
+            Ok(attachment)
+        })
+    }
+
+    /// Every hash still referred to by an attachment.
+    pub fn attachment_hashes(&self) -> Result<HashSet<String>> {
+        let mut stmt = self.conn.prepare("SELECT DISTINCT hash FROM attachments")?;
+        let hashes = stmt.query_map([], |row| row.get(0))?;
+        Ok(hashes.collect::<rusqlite::Result<_>>()?)
+    }
+
+    fn query_attachments(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<Attachment>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM attachments WHERE {} ORDER BY created_at ASC",
+            ATTACHMENT_COLUMNS, filter
+        ))?;
+        let attachments = stmt.query_map(params, attachment_from_row)?;
+        Ok(attachments.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // Digests
+    /// Builds the digest for the day or week up to now.
+    pub fn get_digest(&self, period: DigestPeriod) -> Result<Digest> {
+        let now = Utc::now();
+        let since = match period {
+            DigestPeriod::Day => now - chrono::Duration::days(1),
+            DigestPeriod::Week => now - chrono::Duration::days(7),
+        };
+        let today_start = now.date_naive().and_hms_opt(0, 0, 0).unwrap().and_utc();
+
+        let project_names: HashMap<String, String> = self
+            .get_projects(true)?
+            .into_iter()
+            .map(|project| (project.id, project.name))
+            .collect();
+        let with_project = |tasks: Vec<Task>| -> Vec<DigestTask> {
+            tasks
+                .into_iter()
+                .map(|task| DigestTask {
+                    project_name: task.project_id.as_ref().and_then(|id| project_names.get(id)).cloned(),
+                    task,
+                })
+                .collect()
+        };
+        let open = |tasks: Vec<Task>| tasks.into_iter().filter(|task| !task.completed).collect::<Vec<_>>();
+
+        let overdue = self.query_tasks(
+            &format!("due_date < ?1 AND completed = 0 AND {} ORDER BY due_date ASC", VISIBLE_TASKS),
+            &[&today_start.to_rfc3339()],
+        )?;
+        let stalled = self.query_tasks(
+            &format!("status = 'in_progress' AND completed = 0 AND updated_at < ?1 AND {} ORDER BY updated_at ASC", VISIBLE_TASKS),
+            &[&(now - chrono::Duration::days(STALLED_DAYS)).to_rfc3339()],
+        )?;
+        let completed = self.query_tasks(
+            &format!("completed = 1 AND completed_at >= ?1 AND {} ORDER BY completed_at ASC", VISIBLE_TASKS),
+            &[&since.to_rfc3339()],
+        )?;
+
+        // Grouped by project, with tasks outside any project last
+        let mut by_project: Vec<CompletedInProject> = Vec::new();
+        for task in with_project(completed) {
+            match by_project.iter_mut().find(|group| group.project_name == task.project_name) {
+                Some(group) => group.tasks.push(task),
+                None => by_project.push(CompletedInProject {
+                    project_name: task.project_name.clone(),
+                    tasks: vec![task],
+                }),
+            }
+        }
+        by_project.sort_by(|a, b| match (&a.project_name, &b.project_name) {
+            (Some(a), Some(b)) => a.cmp(b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+
+        Ok(Digest {
+            period,
+            generated_at: now,
+            since,
+            due_today: with_project(open(self.get_today_tasks()?)),
+            overdue: with_project(overdue),
+            completed: by_project,
+            stalled: with_project(stalled),
+            upcoming: with_project(open(self.get_upcoming_tasks()?)),
+        })
+    }
+
+    fn query_tasks(&self, filter: &str, params: &[&dyn ToSql]) -> Result<Vec<Task>> {
+        let mut stmt = self.conn.prepare(&format!("SELECT {} FROM tasks WHERE {}", TASK_COLUMNS, filter))?;
+        let tasks = stmt.query_map(params, task_from_row)?;
+        Ok(tasks.collect::<rusqlite::Result<_>>()?)
+    }
+
+    // Settings
+    pub fn get_setting<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        let value: Option<String> = self
+            .conn
+            .query_row("SELECT value FROM settings WHERE key = ?1", [key], |row| row.get(0))
+            .optional()?;
+        match value {
+            Some(value) => serde_json::from_str(&value)
+                .map(Some)
+                .map_err(|e| rusqlite::Error::FromSqlConversionFailure(0, rusqlite::types::Type::Text, Box::new(e)).into()),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores the setting, or removes it when `value` is `None`.
+    pub fn set_setting<T: Serialize>(&self, key: &str, value: Option<&T>) -> Result<()> {
+        match value {
+            Some(value) => {
+                let value = serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))?;
+                self.conn.execute(
+                    "INSERT INTO settings (key, value) VALUES (?1, ?2) 
+                     ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                    (key, value),
+                )?;
+            }
+            None => {
+                self.conn.execute("DELETE FROM settings WHERE key = ?1", [key])?;
+            }
+        }
+        Ok(())
+    }
+
+    // Import operations
+    pub fn import_tasks(&self, imported: Vec<ImportedTask>) -> Result<Vec<Task>> {
+        self.with_source(ChangeSource::Import, |db| db.insert_imported_tasks(imported))
+    }
+
+    fn insert_imported_tasks(&self, imported: Vec<ImportedTask>) -> Result<Vec<Task>> {
+        self.transaction(|db| {
+            let now = Utc::now();
+
+            let mut project_ids: HashMap<String, String> = HashMap::new();
+            {
+                let mut stmt = db.conn.prepare("SELECT id, name FROM projects")?;
+                let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(1)?, row.get::<_, String>(0)?)))?;
+                for row in rows {
+                    let (name, id) = row?;
+                    project_ids.insert(name, id);
+                }
+            }
+
+            let mut tasks = Vec::new();
+            for item in imported {
+                let project_id = match item.project {
+                    Some(name) => {
+                        if let Some(id) = project_ids.get(&name) {
+                            Some(id.clone())
+                        } else {
+                            let id = Uuid::new_v4().to_string();
+                            db.conn.execute(
+                                "INSERT INTO projects (id, name, description, color, created_at, updated_at) 
+                                 VALUES (?1, ?2, NULL, ?3, ?4, ?4)",
+                                (&id, &name, DEFAULT_PROJECT_COLOR, now.to_rfc3339()),
+                            )?;
+                            db.record_activity(ChangeEntity::Project, &id, ChangeKind::Created, None, None, Some(name.clone()))?;
+                            let project = db.get_project(&id)?;
+                            db.publish(ChangeEvent::created(ChangeEntity::Project, &id, &project));
+                            project_ids.insert(name, id.clone());
+                            Some(id)
+                        }
+                    }
+                    None => None,
+                };
+
+                let completed_at = if item.completed {
+                    item.completed_at.or(Some(now))
+                } else {
+                    None
+                };
+
+                let task = Task {
+                    id: Uuid::new_v4().to_string(),
+                    title: item.title,
+                    description: None,
+                    project_id,
+                    status: item.status,
+                    priority: item.priority,
+                    due_date: item.due_date,
+                    estimate_hours: None,
+                    completed: item.completed,
+                    completed_at,
+                    created_at: item.created_at.unwrap_or(now),
+                    updated_at: now,
+                    milestone_id: None,
+                    assignee_ids: Vec::new(),
+                    revision: 1,
+                    comment_count: 0,
+                    checklist_total: 0,
+                    checklist_checked: 0,
+                };
+
+                db.conn.execute(
+                    "INSERT INTO tasks (id, title, description, project_id, status, priority, due_date, completed, completed_at, created_at, updated_at) 
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                    (
+                        &task.id,
+                        &task.title,
+                        &task.description,
+                        &task.project_id,
+                        task.status.to_string(),
+                        task.priority.to_string(),
+                        task.due_date.map(|d| d.to_rfc3339()),
+                        task.completed,
+                        task.completed_at.map(|d| d.to_rfc3339()),
+                        task.created_at.to_rfc3339(),
+                        task.updated_at.to_rfc3339(),
+                    ),
+                )?;
+
+                db.record_activity(ChangeEntity::Task, &task.id, ChangeKind::Created, None, None, Some(task.title.clone()))?;
+                db.record_transition(&task.id, None, &task.status, task.created_at)?;
+                db.publish(ChangeEvent::created(ChangeEntity::Task, &task.id, &task));
+                tasks.push(task);
+            }
+
+            Ok(tasks)
+        })
+    }
+
+    // Activity log
+    fn record_activity(
+        &self,
+        entity: ChangeEntity,
+        entity_id: &str,
+        action: ChangeKind,
+        field: Option<&str>,
+        old_value: Option<String>,
+        new_value: Option<String>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO activity (entity, entity_id, action, field, old_value, new_value, source, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            (
+                entity.as_str(),
+                entity_id,
+                action.as_str(),
+                field,
+                old_value,
+                new_value,
+                self.source.get().as_str(),
+                Utc::now().to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub fn get_task_activity(&self, task_id: &str, page: i64, per_page: i64) -> Result<ActivityPage> {
+        self.query_activity("WHERE entity = 'task' AND entity_id = ?1", &[&task_id], page, per_page)
+    }
+
+    pub fn get_activity_feed(&self, page: i64, per_page: i64) -> Result<ActivityPage> {
+        self.query_activity("", &[], page, per_page)
+    }
+
+    fn query_activity(
+        &self,
+        filter: &str,
+        params: &[&dyn ToSql],
+        page: i64,
+        per_page: i64,
+    ) -> Result<ActivityPage> {
+        let page = page.max(1);
+        let per_page = per_page.clamp(1, 200);
+
+        let total: i64 = self.conn.query_row(
+            &format!("SELECT COUNT(*) FROM activity {}", filter),
+            params,
+            |row| row.get(0),
+        )?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, entity, entity_id, action, field, old_value, new_value, source, created_at 
+             FROM activity {} ORDER BY id DESC LIMIT {} OFFSET {}",
+            filter,
+            per_page,
+            (page - 1) * per_page
+        ))?;
+
+        let activity_iter = stmt.query_map(params, activity_from_row)?;
+
+        let mut items = Vec::new();
+        for activity in activity_iter {
+            items.push(activity?);
+        }
+
+        Ok(ActivityPage {
+            items,
+            total,
+            page,
+            per_page,
+        })
+    }
+
+    // Status history
+    fn record_transition(
+        &self,
+        task_id: &str,
+        from_status: Option<&TaskStatus>,
+        to_status: &TaskStatus,
+        at: DateTime<Utc>,
+    ) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO task_transitions (task_id, from_status, to_status, transitioned_at) 
+             VALUES (?1, ?2, ?3, ?4)",
+            (
+                task_id,
+                from_status.map(|s| s.to_string()),
+                to_status.to_string(),
+                at.to_rfc3339(),
+            ),
+        )?;
+        Ok(())
+    }
+
+    pub fn get_task_transitions(&self, task_id: &str) -> Result<Vec<StatusTransition>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT id, task_id, from_status, to_status, transitioned_at FROM task_transitions 
+             WHERE task_id = ?1 ORDER BY transitioned_at ASC, id ASC",
+        )?;
+
+        let transition_iter = stmt.query_map([task_id], transition_from_row)?;
+
+        let mut transitions = Vec::new();
+        for transition in transition_iter {
+            transitions.push(transition?);
+        }
+
+        Ok(transitions)
+    }
+
+    pub fn get_flow_metrics(
+        &self,
+        project_id: Option<&str>,
+        range: &MetricsRange,
+    ) -> Result<FlowMetrics> {
+        let tasks = match project_id {
+            Some(project_id) => self.get_tasks_by_project(project_id)?,
+            None => self.get_tasks(true)?,
+        };
+
+        let mut stmt = self.conn.prepare(
+            "SELECT tr.id, tr.task_id, tr.from_status, tr.to_status, tr.transitioned_at 
+             FROM task_transitions tr JOIN tasks t ON t.id = tr.task_id 
+             WHERE ?1 IS NULL OR t.project_id = ?1",
+        )?;
+
+        let transition_iter = stmt.query_map([project_id], transition_from_row)?;
+
+        let mut transitions = Vec::new();
+        for transition in transition_iter {
+            transitions.push(transition?);
+        }
+
+        Ok(analytics::flow_metrics(&tasks, &transitions, range, Utc::now()))
+    }
+
+    pub fn get_burndown(
+        &self,
+        project_id: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+        unit: ChartUnit,
+    ) -> Result<Vec<BurndownPoint>> {
+        let (tasks, transitions, changes) = self.project_history(project_id)?;
+        let history = TaskHistory {
+            transitions: &transitions,
+            changes: &changes,
+        };
+        Ok(analytics::burndown(project_id, &tasks, &history, from, to, unit, Utc::now()))
+    }
+
+    pub fn get_cumulative_flow(
+        &self,
+        project_id: &str,
+        from: NaiveDate,
+        to: NaiveDate,
+        unit: ChartUnit,
+    ) -> Result<Vec<CumulativeFlowPoint>> {
+        let (tasks, transitions, changes) = self.project_history(project_id)?;
+        let history = TaskHistory {
+            transitions: &transitions,
+            changes: &changes,
+        };
+        Ok(analytics::cumulative_flow(project_id, &tasks, &history, from, to, unit, Utc::now()))
+    }
+
+    /// What `analytics::forecast` works from: the number of open tasks in the
+    /// project and the completion times of its finished ones. The simulation
+    /// itself is left to the caller, so it need not hold the database.
+    pub fn get_forecast_inputs(&self, project_id: &str) -> Result<(i64, Vec<DateTime<Utc>>)> {
+        let tasks = self.get_tasks_by_project(project_id)?;
+        let remaining = tasks
+            .iter()
+            .filter(|task| !task.completed && task.status != TaskStatus::Done)
+            .count() as i64;
+        let completions = tasks.iter().filter_map(|task| task.completed_at).collect();
+        Ok((remaining, completions))
+    }
+
+    // Every task that is or ever was in the project, with the history needed to replay it
+    fn project_history(&self, project_id: &str) -> Result<(Vec<Task>, Vec<StatusTransition>, Vec<Activity>)> {
+        let members = "SELECT id FROM tasks WHERE project_id = ?1
+                       UNION
+                       SELECT entity_id FROM activity
+                       WHERE entity = 'task' AND field = 'project_id' AND (old_value = ?1 OR new_value = ?1)";
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {} FROM tasks WHERE id IN ({})",
+            TASK_COLUMNS, members
+        ))?;
+        let tasks = stmt
+            .query_map([project_id], task_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, task_id, from_status, to_status, transitioned_at FROM task_transitions
+             WHERE task_id IN ({})",
+            members
+        ))?;
+        let transitions = stmt
+            .query_map([project_id], transition_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT id, entity, entity_id, action, field, old_value, new_value, source, created_at FROM activity
+             WHERE entity = 'task' AND field IN ('project_id', 'completed', 'estimate_hours') AND entity_id IN ({})",
+            members
+        ))?;
+        let changes = stmt
+            .query_map([project_id], activity_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok((tasks, transitions, changes))
+    }
+}
+
+fn activity_from_row(row: &Row) -> rusqlite::Result<Activity> {
+    Ok(Activity {
+        id: row.get(0)?,
+        entity: ChangeEntity::try_from(row.get::<_, String>(1)?)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, e.into()))?,
+        entity_id: row.get(2)?,
+        action: ChangeKind::from(row.get::<_, String>(3)?),
+        field: row.get(4)?,
+        old_value: row.get(5)?,
+        new_value: row.get(6)?,
+        source: ChangeSource::from(row.get::<_, String>(7)?),
+        created_at: parse_timestamp(row.get(8)?),
+    })
+}
+
+fn transition_from_row(row: &Row) -> rusqlite::Result<StatusTransition> {
+    Ok(StatusTransition {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        from_status: row.get::<_, Option<String>>(2)?.map(TaskStatus::from),
+        to_status: TaskStatus::from(row.get::<_, String>(3)?),
+        transitioned_at: parse_timestamp(row.get(4)?),
+    })
+}
+
+// A changed field as (name, old value, new value)
+type FieldChange = (&'static str, Option<String>, Option<String>);
+
+fn changed_fields(fields: Vec<FieldChange>) -> Vec<FieldChange> {
+    fields
+        .into_iter()
+        .filter(|(_, old_value, new_value)| old_value != new_value)
+        .collect()
+}
+
+fn project_changes(before: &Project, after: &Project) -> Vec<FieldChange> {
+    changed_fields(vec![
+        ("name", Some(before.name.clone()), Some(after.name.clone())),
+        ("description", before.description.clone(), after.description.clone()),
+        ("color", Some(before.color.clone()), Some(after.color.clone())),
+        ("status", Some(before.status.as_str().to_string()), Some(after.status.as_str().to_string())),
+        ("auto_archive", Some(before.auto_archive.to_string()), Some(after.auto_archive.to_string())),
+    ])
+}
+
+fn sprint_changes(before: &Sprint, after: &Sprint) -> Vec<FieldChange> {
+    changed_fields(vec![
+        ("name", Some(before.name.clone()), Some(after.name.clone())),
+        ("goal", before.goal.clone(), after.goal.clone()),
+        ("start_date", Some(before.start_date.to_rfc3339()), Some(after.start_date.to_rfc3339())),
+        ("end_date", Some(before.end_date.to_rfc3339()), Some(after.end_date.to_rfc3339())),
+        ("capacity", before.capacity.map(|c| c.to_string()), after.capacity.map(|c| c.to_string())),
+    ])
+}
+
+fn milestone_changes(before: &Milestone, after: &Milestone) -> Vec<FieldChange> {
+    changed_fields(vec![
+        ("name", Some(before.name.clone()), Some(after.name.clone())),
+        ("description", before.description.clone(), after.description.clone()),
+        ("target_date", Some(before.target_date.to_rfc3339()), Some(after.target_date.to_rfc3339())),
+    ])
+}
+
+fn task_changes(before: &Task, after: &Task) -> Vec<FieldChange> {
+    changed_fields(vec![
+        ("title", Some(before.title.clone()), Some(after.title.clone())),
+        ("description", before.description.clone(), after.description.clone()),
+        ("project_id", before.project_id.clone(), after.project_id.clone()),
+        ("status", Some(before.status.to_string()), Some(after.status.to_string())),
+        ("priority", Some(before.priority.to_string()), Some(after.priority.to_string())),
+        ("due_date", before.due_date.map(|d| d.to_rfc3339()), after.due_date.map(|d| d.to_rfc3339())),
+        ("completed", Some(before.completed.to_string()), Some(after.completed.to_string())),
+        ("milestone_id", before.milestone_id.clone(), after.milestone_id.clone()),
+        ("estimate_hours", before.estimate_hours.map(|h| h.to_string()), after.estimate_hours.map(|h| h.to_string())),
+    ])
+}
+
+// Column list with each column prefixed by a table alias, for joins.
+// Subqueries refer to the table by its name, so they get the alias instead.
+fn qualified(columns: &str, alias: &str) -> String {
+    columns
+        .split(", ")
+        .map(|column| {
+            if column.starts_with('(') {
+                column.replace("tasks.", &format!("{}.", alias))
+            } else {
+                format!("{}.{}", alias, column)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+// A request the database refuses, e.g. closing a sprint twice
+fn rejected(message: &str) -> Error {
+    Error::Invalid(message.to_string())
+}
+
+/// Whether opening a database failed because it is encrypted with another
+/// passphrase, or because it is encrypted at all when none was given.
+pub fn is_wrong_passphrase(e: &Error) -> bool {
+    matches!(e, Error::Db(rusqlite::Error::SqliteFailure(error, _)) if error.code == rusqlite::ErrorCode::NotADatabase)
+}
+
+fn conflict<T: Serialize>(entity: ChangeEntity, expected_revision: i64, current: &T, fields: Vec<String>) -> Error {
+    Error::Conflict(Conflict {
+        entity,
+        expected_revision,
+        current: serde_json::to_value(current).unwrap_or_default(),
+        fields,
+        operation: None,
+    })
+}
+
+fn parse_timestamp(value: String) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(&value)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+fn project_from_row(row: &Row) -> rusqlite::Result<Project> {
+    Ok(Project {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        color: row.get(3)?,
+        created_at: parse_timestamp(row.get(4)?),
+        updated_at: parse_timestamp(row.get(5)?),
+        status: ProjectStatus::from(row.get::<_, String>(6)?),
+        auto_archive: row.get(7)?,
+        revision: row.get(8)?,
+    })
+}
+
+fn template_from_row(row: &Row) -> rusqlite::Result<ProjectTemplate> {
+    let body: TemplateBody = serde_json::from_str(&row.get::<_, String>(3)?)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(3, rusqlite::types::Type::Text, Box::new(e)))?;
+    Ok(ProjectTemplate {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        description: row.get(2)?,
+        variables: templates::variables(&body),
+        body,
+        created_at: parse_timestamp(row.get(4)?),
+        updated_at: parse_timestamp(row.get(5)?),
+    })
+}
+
+fn sprint_from_row(row: &Row) -> rusqlite::Result<Sprint> {
+    let start_date = parse_timestamp(row.get(3)?);
+    let closed_at = row.get::<_, Option<String>>(6)?.map(parse_timestamp);
+    Ok(Sprint {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        goal: row.get(2)?,
+        start_date,
+        end_date: parse_timestamp(row.get(4)?),
+        capacity: row.get(5)?,
+        status: SprintStatus::at(start_date, closed_at, Utc::now()),
+        closed_at,
+        created_at: parse_timestamp(row.get(7)?),
+        updated_at: parse_timestamp(row.get(8)?),
+    })
+}
+
+fn reminder_from_row(row: &Row) -> rusqlite::Result<Reminder> {
+    let mut reminder = Reminder {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        remind_at: row.get::<_, Option<String>>(2)?.map(parse_timestamp),
+        minutes_before_due: row.get(3)?,
+        snoozed_until: row.get::<_, Option<String>>(4)?.map(parse_timestamp),
+        fired_at: row.get::<_, Option<String>>(5)?.map(parse_timestamp),
+        created_at: parse_timestamp(row.get(6)?),
+        next_at: None,
+    };
+    let due_date = row.get::<_, Option<String>>(7)?.map(parse_timestamp);
+    reminder.next_at = reminders::next_at(&reminder, due_date, row.get(8)?);
+    Ok(reminder)
+}
+
+fn comment_from_row(row: &Row) -> rusqlite::Result<Comment> {
+    Ok(Comment {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        author: row.get(2)?,
+        body: row.get(3)?,
+        created_at: parse_timestamp(row.get(4)?),
+        edited_at: row.get::<_, Option<String>>(5)?.map(parse_timestamp),
+    })
+}
+
+fn person_from_row(row: &Row) -> rusqlite::Result<Person> {
+    Ok(Person {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        email: row.get(2)?,
+        created_at: parse_timestamp(row.get(3)?),
+    })
+}
+
+fn checklist_item_from_row(row: &Row) -> rusqlite::Result<ChecklistItem> {
+    Ok(ChecklistItem {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        text: row.get(2)?,
+        checked: row.get(3)?,
+        position: row.get(4)?,
+        created_at: parse_timestamp(row.get(5)?),
+    })
+}
+
+fn attachment_from_row(row: &Row) -> rusqlite::Result<Attachment> {
+    Ok(Attachment {
+        id: row.get(0)?,
+        task_id: row.get(1)?,
+        name: row.get(2)?,
+        mime_type: row.get(3)?,
+        size: row.get(4)?,
+        hash: row.get(5)?,
+        created_at: parse_timestamp(row.get(6)?),
+    })
+}
+
+fn milestone_from_row(row: &Row) -> rusqlite::Result<Milestone> {
+    Ok(Milestone {
+        id: row.get(0)?,
+        project_id: row.get(1)?,
+        name: row.get(2)?,
+        description: row.get(3)?,
+        target_date: parse_timestamp(row.get(4)?),
+        created_at: parse_timestamp(row.get(5)?),
+        updated_at: parse_timestamp(row.get(6)?),
+    })
+}
+
+fn task_from_row(row: &Row) -> rusqlite::Result<Task> {
+    Ok(Task {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        description: row.get(2)?,
+        project_id: row.get(3)?,
+        status: TaskStatus::from(row.get::<_, String>(4)?),
+        priority: TaskPriority::from(row.get::<_, String>(5)?),
+        due_date: row.get::<_, Option<String>>(6)?.map(parse_timestamp),
+        completed: row.get(7)?,
+        completed_at: row.get::<_, Option<String>>(8)?.map(parse_timestamp),
+        created_at: parse_timestamp(row.get(9)?),
+        updated_at: parse_timestamp(row.get(10)?),
+        milestone_id: row.get(11)?,
+        revision: row.get(12)?,
+        comment_count: row.get(13)?,
+        checklist_total: row.get(14)?,
+        checklist_checked: row.get(15)?,
+        estimate_hours: row.get(16)?,
+        assignee_ids: serde_json::from_str(&row.get::<_, String>(17)?)
+            .map_err(|e| rusqlite::Error::FromSqlConversionFailure(17, rusqlite::types::Type::Text, Box::new(e)))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use serde_json::{json, Value};
+
+    fn date(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, day, 0, 0, 0).unwrap()
+    }
+
+    // `value` without the fields every update touches
+    fn fields<T: Serialize>(value: &T, volatile: &[&str]) -> Value {
+        let mut value = serde_json::to_value(value).unwrap();
+        for key in ["updated_at", "revision"].iter().chain(volatile) {
+            value.as_object_mut().unwrap().remove(*key);
+        }
+        value
+    }
+
+    fn changed(mut before: Value, changes: &Value) -> Value {
+        for (key, value) in changes.as_object().unwrap() {
+            before[key] = value.clone();
+        }
+        before
+    }
+
+    fn project(db: &Database, name: &str) -> Project {
+        db.create_project(CreateProject {
+            name: name.to_string(),
+            description: Some("About".to_string()),
+            color: "#3b82f6".to_string(),
+            auto_archive: false,
+        })
+        .unwrap()
+    }
+
+    fn milestone(db: &Database, project: &Project) -> Milestone {
+        db.create_milestone(CreateMilestone {
+            project_id: project.id.clone(),
+            name: format!("{} launch", project.name),
+            description: None,
+            target_date: date(20),
+        })
+        .unwrap()
+    }
+
+    fn person(db: &Database, name: &str) -> Person {
+        db.create_person(CreatePerson { name: name.to_string(), email: None }).unwrap()
+    }
+
+    fn sprint(db: &Database, name: &str, start: u32) -> Sprint {
+        db.create_sprint(CreateSprint {
+            name: name.to_string(),
+            goal: None,
+            start_date: date(start),
+            end_date: date(start + 13),
+            capacity: None,
+        })
+        .unwrap()
+    }
+
+    fn task(db: &Database, title: &str) -> Task {
+        db.create_task(CreateTask {
+            title: title.to_string(),
+            description: None,
+            project_id: None,
+            status: TaskStatus::Todo,
+            priority: TaskPriority::Medium,
+            due_date: None,
+            estimate_hours: None,
+            milestone_id: None,
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn update_task_applies_each_field() {
+        let db = Database::open(":memory:").unwrap();
+        let home = project(&db, "Home");
+        let work = project(&db, "Work");
+        let home_launch = milestone(&db, &home);
+        let work_launch = milestone(&db, &work);
+        let ann = person(&db, "Ann");
+        let bob = person(&db, "Bob");
+
+        let cases = vec![
+            (json!({}), json!({})),
+            (json!({ "title": "Renamed" }), json!({ "title": "Renamed" })),
+            (json!({ "description": "Details" }), json!({ "description": "Details" })),
+            (json!({ "description": null }), json!({ "description": null })),
+            // Milestones stay behind when the task changes project
+            (json!({ "project_id": work.id }), json!({ "project_id": work.id, "milestone_id": null })),
+            (json!({ "project_id": null }), json!({ "project_id": null, "milestone_id": null })),
+            (json!({ "status": "in_progress" }), json!({ "status": "in_progress" })),
+            (json!({ "priority": "high" }), json!({ "priority": "high" })),
+            (json!({ "due_date": date(9) }), json!({ "due_date": date(9) })),
+            (json!({ "due_date": null }), json!({ "due_date": null })),
+            (json!({ "estimate_hours": 3.5 }), json!({ "estimate_hours": 3.5 })),
+            (json!({ "estimate_hours": null }), json!({ "estimate_hours": null })),
+            (json!({ "completed": true }), json!({ "completed": true })),
+            // A milestone brings the task into its project
+            (json!({ "milestone_id": work_launch.id }), json!({ "milestone_id": work_launch.id, "project_id": work.id })),
+            (json!({ "milestone_id": null }), json!({ "milestone_id": null })),
+            (json!({ "assignee_ids": [bob.id] }), json!({ "assignee_ids": [bob.id] })),
+            (json!({ "assignee_ids": [] }), json!({ "assignee_ids": [] })),
+            // Fields that can't be cleared ignore null
+            (json!({ "title": null, "status": null, "priority": null, "completed": null }), json!({})),
+        ];
+
+        for (patch, changes) in cases {
+            let task = db
+                .create_task(CreateTask {
+                    title: "Write report".to_string(),
+                    description: Some("First draft".to_string()),
+                    project_id: Some(home.id.clone()),
+                    status: TaskStatus::Todo,
+                    priority: TaskPriority::Medium,
+                    due_date: Some(date(5)),
+                    estimate_hours: Some(2.0),
+                    milestone_id: Some(home_launch.id.clone()),
+                })
+                .unwrap();
+            let task = db.assign_tasks(std::slice::from_ref(&task.id), std::slice::from_ref(&ann.id)).unwrap().remove(0);
+
+            let mut payload = patch.clone();
+            payload["id"] = json!(task.id);
+            let updated = db.update_task(serde_json::from_value(payload).unwrap()).unwrap();
+
+            let volatile = ["completed_at"];
+            assert_eq!(fields(&updated, &volatile), changed(fields(&task, &volatile), &changes), "{}", patch);
+            assert_eq!(updated.revision, task.revision + 1);
+        }
+    }
+
+    #[test]
+    fn batch_keeps_conflicts() {
+        let db = Database::open(":memory:").unwrap();
+        let project = project(&db, "Home");
+        let stale: UpdateProject = serde_json::from_value(json!({ "id": project.id, "name": "Old", "expected_revision": 0 })).unwrap();
+
+        let error = db
+            .batch(vec![
+                BatchOperation::CreateProject {
+                    project: CreateProject {
+                        name: "Work".to_string(),
+                        description: None,
+                        color: "#3b82f6".to_string(),
+                        auto_archive: false,
+                    },
+                },
+                BatchOperation::UpdateProject { project: stale },
+            ])
+            .unwrap_err();
+        match error {
+            Error::Conflict(conflict) => {
+                assert_eq!(conflict.operation, Some(2));
+                assert_eq!(conflict.current["name"], "Home");
+                assert!(conflict.to_string().starts_with("Operation 2 failed: "));
+            }
+            e => panic!("expected a conflict, got {}", e),
+        }
+    }
+
+    #[test]
+    fn batch_applies_everything_or_nothing() {
+        let mut db = Database::open(":memory:").unwrap();
+        let home = project(&db, "Home");
+        let report = task(&db, "Write report");
+        let events = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        db.subscribe(move |event| recorded.lock().unwrap().push((event.entity.clone(), event.kind.clone())));
+
+        let operations = |missing: &str| -> Vec<BatchOperation> {
+            serde_json::from_value(json!([
+                { "op": "create_task", "task": { "title": "Call Ann", "description": null, "project_id": home.id, "status": "todo", "priority": "high", "due_date": null } },
+                { "op": "complete_task", "id": report.id },
+                { "op": "delete_task", "id": missing },
+            ]))
+            .unwrap()
+        };
+
+        // The third operation fails, so the first two are undone and nobody hears of them
+        let error = db.batch(operations("missing")).unwrap_err();
+        assert_eq!(error.to_string(), "Operation 3 (delete_task) failed: not found");
+        assert_eq!(db.get_tasks(false).unwrap().len(), 1);
+        assert!(!db.get_task(&report.id).unwrap().completed);
+        assert!(events.lock().unwrap().is_empty());
+
+        let results = db.batch(operations(&report.id)).unwrap();
+        match &results[..] {
+            [BatchResult::Task(created), BatchResult::Task(completed), BatchResult::Deleted(deleted)] => {
+                assert_eq!((created.title.as_str(), created.project_id.as_deref()), ("Call Ann", Some(home.id.as_str())));
+                assert!(completed.completed);
+                assert_eq!(deleted, &report.id);
+            }
+            results => panic!("unexpected results {:?}", results),
+        }
+        assert_eq!(db.get_tasks(false).unwrap().len(), 1);
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![
+                (ChangeEntity::Task, ChangeKind::Created),
+                (ChangeEntity::Task, ChangeKind::Updated),
+                (ChangeEntity::Task, ChangeKind::Deleted),
+            ]
+        );
+    }
+
+    #[test]
+    fn stale_updates_conflict_unless_they_merge() {
+        let db = Database::open(":memory:").unwrap();
+        let base = task(&db, "Write report");
+        let update = |changes: Value| -> UpdateTask {
+            let mut payload = changes;
+            payload["id"] = json!(base.id);
+            serde_json::from_value(payload).unwrap()
+        };
+        db.update_task(update(json!({ "title": "Write summary", "expected_revision": base.revision }))).unwrap();
+
+        // A stale revision gets the task as it is now
+        match db.update_task(update(json!({ "priority": "high", "expected_revision": base.revision }))).unwrap_err() {
+            Error::Conflict(conflict) => {
+                assert_eq!(conflict.expected_revision, base.revision);
+                assert_eq!(conflict.current["title"], "Write summary");
+                assert_eq!(conflict.current["revision"], base.revision + 1);
+                assert!(conflict.fields.is_empty());
+            }
+            e => panic!("expected a conflict, got {}", e),
+        }
+
+        // Edits to other fields, or to the same value, merge with the one made since
+        let merged = db.merge_task_update(&base, update(json!({ "priority": "high" }))).unwrap();
+        assert_eq!((merged.title.as_str(), &merged.priority), ("Write summary", &TaskPriority::High));
+        let current = db.get_task(&base.id).unwrap();
+        assert!(merge::overlapping(&base, &current, &update(json!({ "title": "Write summary" }))).is_empty());
+
+        // Changing the same field differently conflicts, naming the field
+        match db.merge_task_update(&base, update(json!({ "title": "Write draft" }))).unwrap_err() {
+            Error::Conflict(conflict) => {
+                assert_eq!(conflict.fields, vec!["title".to_string()]);
+                assert_eq!(conflict.current["priority"], "high");
+            }
+            e => panic!("expected a conflict, got {}", e),
+        }
+        assert_eq!(db.get_task(&base.id).unwrap().title, "Write summary");
+    }
+
+    #[test]
+    fn update_project_applies_each_field() {
+        let db = Database::open(":memory:").unwrap();
+
+        let cases = vec![
+            (json!({}), json!({})),
+            (json!({ "name": "Renamed" }), json!({ "name": "Renamed" })),
+            (json!({ "description": "Details" }), json!({ "description": "Details" })),
+            (json!({ "description": null }), json!({ "description": null })),
+            (json!({ "color": "#ef4444" }), json!({ "color": "#ef4444" })),
+            (json!({ "status": "on_hold" }), json!({ "status": "on_hold" })),
+            (json!({ "auto_archive": true }), json!({ "auto_archive": true })),
+            (json!({ "name": null, "color": null, "status": null, "auto_archive": null }), json!({})),
+        ];
+
+        for (i, (patch, changes)) in cases.into_iter().enumerate() {
+            let project = project(&db, &format!("Project {}", i));
+
+            let mut payload = patch.clone();
+            payload["id"] = json!(project.id);
+            let updated = db.update_project(serde_json::from_value(payload).unwrap()).unwrap();
+
+            assert_eq!(fields(&updated, &[]), changed(fields(&project, &[]), &changes), "{}", patch);
+            assert_eq!(updated.revision, project.revision + 1);
+        }
+    }
+
+    #[test]
+    fn update_sprint_applies_each_field() {
+        let db = Database::open(":memory:").unwrap();
+
+        let cases = vec![
+            (json!({}), json!({})),
+            (json!({ "name": "Renamed" }), json!({ "name": "Renamed" })),
+            (json!({ "goal": "Ship it" }), json!({ "goal": "Ship it" })),
+            (json!({ "goal": null }), json!({ "goal": null })),
+            (json!({ "start_date": date(2) }), json!({ "start_date": date(2) })),
+            (json!({ "end_date": date(28) }), json!({ "end_date": date(28) })),
+            (json!({ "capacity": 12 }), json!({ "capacity": 12 })),
+            (json!({ "capacity": null }), json!({ "capacity": null })),
+            (json!({ "name": null, "start_date": null, "end_date": null }), json!({})),
+        ];
+
+        for (patch, changes) in cases {
+            let sprint = db
+                .create_sprint(CreateSprint {
+                    name: "Sprint 1".to_string(),
+                    goal: Some("Finish the beta".to_string()),
+                    start_date: date(1),
+                    end_date: date(14),
+                    capacity: Some(8),
+                })
+                .unwrap();
+
+            let mut payload = patch.clone();
+            payload["id"] = json!(sprint.id);
+            let updated = db.update_sprint(serde_json::from_value(payload).unwrap()).unwrap();
+
+            assert_eq!(fields(&updated, &[]), changed(fields(&sprint, &[]), &changes), "{}", patch);
+        }
+    }
+
+    #[test]
+    fn sprint_membership_changes_are_recorded() {
+        let db = Database::open(":memory:").unwrap();
+        let first = sprint(&db, "Sprint 1", 1);
+        let second = sprint(&db, "Sprint 2", 15);
+        let task = task(&db, "Write report");
+        let sprint_changes = |db: &Database| -> Vec<(Option<String>, Option<String>)> {
+            let mut items = db.get_task_activity(&task.id, 1, 50).unwrap().items;
+            items.retain(|item| item.field.as_deref() == Some("sprint_id"));
+            items.reverse();
+            items.into_iter().map(|item| (item.old_value, item.new_value)).collect()
+        };
+
+        db.add_tasks_to_sprint(&first.id, std::slice::from_ref(&task.id)).unwrap();
+        // Adding it again changes nothing
+        db.add_tasks_to_sprint(&first.id, std::slice::from_ref(&task.id)).unwrap();
+        let report = db.add_tasks_to_sprint(&second.id, std::slice::from_ref(&task.id)).unwrap();
+        assert_eq!(report.tasks.len(), 1);
+        assert!(db.get_sprint_report(&first.id).unwrap().tasks.is_empty());
+
+        db.remove_task_from_sprint(&second.id, &task.id).unwrap();
+        assert!(db.remove_task_from_sprint(&second.id, &task.id).unwrap_err().is_not_found());
+
+        assert_eq!(
+            sprint_changes(&db),
+            vec![
+                (None, Some(first.id.clone())),
+                (Some(first.id.clone()), Some(second.id.clone())),
+                (Some(second.id.clone()), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn closing_a_sprint_carries_unfinished_tasks_over() {
+        let db = Database::open(":memory:").unwrap();
+        let first = sprint(&db, "Sprint 1", 1);
+        let second = sprint(&db, "Sprint 2", 15);
+        let done = task(&db, "Write report");
+        let open = task(&db, "Call Ann");
+        db.add_tasks_to_sprint(&first.id, &[done.id.clone(), open.id.clone()]).unwrap();
+        db.update_task(serde_json::from_value(json!({ "id": done.id, "completed": true })).unwrap()).unwrap();
+
+        let report = db.close_sprint(&first.id, None).unwrap();
+        assert_eq!(report.sprint.status, SprintStatus::Closed);
+        assert_eq!((report.committed_tasks, report.completed_tasks, report.carried_over_tasks), (2, 1, 1));
+        assert_eq!(report.carried_over_to, Some(second.id.clone()));
+        let outcomes: Vec<(&str, Option<SprintOutcome>)> =
+            report.tasks.iter().map(|t| (t.task.title.as_str(), t.outcome.clone())).collect();
+        assert_eq!(
+            outcomes,
+            vec![("Write report", Some(SprintOutcome::Completed)), ("Call Ann", Some(SprintOutcome::CarriedOver))]
+        );
+
+        // Carried-over work counts as committed in the next sprint
+        let next = db.get_sprint_report(&second.id).unwrap();
+        assert_eq!(next.tasks.len(), 1);
+        assert_eq!(next.tasks[0].task.id, open.id);
+        assert_eq!(next.tasks[0].carried_over_from, Some(first.id.clone()));
+        assert_eq!(next.committed_tasks, 1);
+
+        assert!(db.close_sprint(&first.id, None).is_err());
+        assert!(db.add_tasks_to_sprint(&first.id, std::slice::from_ref(&open.id)).is_err());
+    }
 }
@@ -0,0 +1,218 @@
+use crate::database::Database;
+use crate::models::*;
+use crate::workspaces::{self, WorkspaceState};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Daily digests and weekly reviews
+//
+// A digest is rendered as Markdown or as a standalone HTML page. With a
+// `DigestSchedule` saved in the settings, the app also writes one into a
+// folder once a day (or every Monday), catching up after it was closed.
+
+pub const SCHEDULE_SETTING: &str = "digest_schedule";
+
+// When the scheduled digest was last written
+const LAST_WRITTEN_SETTING: &str = "digest_last_written";
+
+const CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+pub fn render(digest: &Digest, format: DigestFormat) -> String {
+    let sections = sections(digest);
+    match format {
+        DigestFormat::Markdown => markdown(digest, &sections),
+        DigestFormat::Html => html(digest, &sections),
+    }
+}
+
+/// File name for a digest, e.g. `digest-day-2024-01-31.md`
+pub fn file_name(digest: &Digest, format: DigestFormat) -> String {
+    let extension = match format {
+        DigestFormat::Markdown => "md",
+        DigestFormat::Html => "html",
+    };
+    format!(
+        "digest-{}-{}.{}",
+        digest.period.as_str(),
+        digest.generated_at.format("%Y-%m-%d"),
+        extension
+    )
+}
+
+/// The latest time a scheduled digest should have been written by `now`.
+pub fn latest_slot(schedule: &DigestSchedule, now: DateTime<Utc>) -> DateTime<Utc> {
+    let hour = schedule.hour.min(23);
+    let mut slot = now.date_naive().and_hms_opt(hour, 0, 0).unwrap().and_utc();
+    if slot > now {
+        slot -= Duration::days(1);
+    }
+    if schedule.period == DigestPeriod::Week {
+        slot -= Duration::days(slot.weekday().num_days_from_monday() as i64);
+    }
+    slot
+}
+
+/// Writes the scheduled digest if one is due, returning where it went.
+pub fn write_if_due(db: &Database, now: DateTime<Utc>) -> Result<Option<PathBuf>, String> {
+    let schedule: DigestSchedule = match db.get_setting(SCHEDULE_SETTING).map_err(|e| e.to_string())? {
+        Some(schedule) => schedule,
+        None => return Ok(None),
+    };
+    let last_written: Option<DateTime<Utc>> = db.get_setting(LAST_WRITTEN_SETTING).map_err(|e| e.to_string())?;
+    if last_written.is_some_and(|at| at >= latest_slot(&schedule, now)) {
+        return Ok(None);
+    }
+
+    let digest = db.get_digest(schedule.period).map_err(|e| e.to_string())?;
+    let path = write(&digest, schedule.format, Path::new(&schedule.folder))?;
+    db.set_setting(LAST_WRITTEN_SETTING, Some(&now)).map_err(|e| e.to_string())?;
+    Ok(Some(path))
+}
+
+pub fn write(digest: &Digest, format: DigestFormat, folder: &Path) -> Result<PathBuf, String> {
+    let path = folder.join(file_name(digest, format));
+    fs::create_dir_all(folder)
+        .and_then(|_| fs::write(&path, render(digest, format)))
+        .map_err(|e| format!("Failed to write digest to {}: {}", path.display(), e))?;
+    Ok(path)
+}
+
+/// Writes scheduled digests of the active workspace for as long as its
+/// database is usable, pausing while it is locked.
+pub async fn run(workspaces: WorkspaceState) {
+    loop {
+        match workspaces.background_database() {
+            Ok(db) => {
+                if let Err(e) = write_if_due(&db, Utc::now()) {
+                    eprintln!("{}", e);
+                }
+            }
+            Err(e) if e == workspaces::LOCKED => {}
+            Err(_) => return,
+        }
+        tokio::time::sleep(CHECK_INTERVAL).await;
+    }
+}
+
+// Rendering
+
+enum Entries<'a> {
+    Tasks(&'a [DigestTask]),
+    ByProject(&'a [CompletedInProject]),
+}
+
+struct Section<'a> {
+    title: String,
+    entries: Entries<'a>,
+}
+
+fn sections(digest: &Digest) -> Vec<Section<'_>> {
+    vec![
+        Section { title: "Due today".to_string(), entries: Entries::Tasks(&digest.due_today) },
+        Section { title: "Overdue".to_string(), entries: Entries::Tasks(&digest.overdue) },
+        Section {
+            title: format!("Completed since {}", digest.since.format("%Y-%m-%d %H:%M")),
+            entries: Entries::ByProject(&digest.completed),
+        },
+        Section { title: "Stalled".to_string(), entries: Entries::Tasks(&digest.stalled) },
+        Section { title: "Upcoming deadlines".to_string(), entries: Entries::Tasks(&digest.upcoming) },
+    ]
+}
+
+fn heading(digest: &Digest) -> String {
+    let kind = match digest.period {
+        DigestPeriod::Day => "Daily digest",
+        DigestPeriod::Week => "Weekly review",
+    };
+    format!("{} for {}", kind, digest.generated_at.format("%Y-%m-%d"))
+}
+
+// Title, then project, due date and priority where they apply
+fn describe(entry: &DigestTask) -> (String, String) {
+    let mut details = Vec::new();
+    if let Some(project) = &entry.project_name {
+        details.push(project.clone());
+    }
+    if let (false, Some(due)) = (entry.task.completed, entry.task.due_date) {
+        details.push(format!("due {}", due.format("%Y-%m-%d")));
+    }
+    if entry.task.status == TaskStatus::InProgress && !entry.task.completed {
+        details.push(format!("last changed {}", entry.task.updated_at.format("%Y-%m-%d")));
+    }
+    details.push(entry.task.priority.to_string());
+    (entry.task.title.clone(), details.join(" · "))
+}
+
+fn markdown(digest: &Digest, sections: &[Section]) -> String {
+    let mut out = format!(
+        "# {}\n\n_Generated {}_\n",
+        heading(digest),
+        digest.generated_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    let list = |out: &mut String, tasks: &[DigestTask]| {
+        for entry in tasks {
+            let (title, details) = describe(entry);
+            out.push_str(&format!("- {} ({})\n", title, details));
+        }
+    };
+
+    for section in sections {
+        out.push_str(&format!("\n## {}\n\n", section.title));
+        match &section.entries {
+            Entries::Tasks([]) | Entries::ByProject([]) => out.push_str("Nothing here.\n"),
+            Entries::Tasks(tasks) => list(&mut out, tasks),
+            Entries::ByProject(groups) => {
+                for (i, group) in groups.iter().enumerate() {
+                    if i > 0 {
+                        out.push('\n');
+                    }
+                    let name = group.project_name.as_deref().unwrap_or("No project");
+                    out.push_str(&format!("### {} ({})\n\n", name, group.tasks.len()));
+                    list(&mut out, &group.tasks);
+                }
+            }
+        }
+    }
+    out
+}
+
+fn html(digest: &Digest, sections: &[Section]) -> String {
+    let mut out = format!(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{0}</title>\n</head>\n<body>\n<h1>{0}</h1>\n<p><em>Generated {1}</em></p>\n",
+        escape(&heading(digest)),
+        digest.generated_at.format("%Y-%m-%d %H:%M UTC")
+    );
+    let list = |out: &mut String, tasks: &[DigestTask]| {
+        out.push_str("<ul>\n");
+        for entry in tasks {
+            let (title, details) = describe(entry);
+            out.push_str(&format!("<li>{} <small>({})</small></li>\n", escape(&title), escape(&details)));
+        }
+        out.push_str("</ul>\n");
+    };
+
+    for section in sections {
+        out.push_str(&format!("<h2>{}</h2>\n", escape(&section.title)));
+        match &section.entries {
+            Entries::Tasks([]) | Entries::ByProject([]) => out.push_str("<p>Nothing here.</p>\n"),
+            Entries::Tasks(tasks) => list(&mut out, tasks),
+            Entries::ByProject(groups) => {
+                for group in groups.iter() {
+                    let name = group.project_name.as_deref().unwrap_or("No project");
+                    out.push_str(&format!("<h3>{} ({})</h3>\n", escape(name), group.tasks.len()));
+                    list(&mut out, &group.tasks);
+                }
+            }
+        }
+    }
+    out.push_str("</body>\n</html>\n");
+    out
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
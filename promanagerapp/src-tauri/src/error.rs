@@ -0,0 +1,89 @@
+use crate::events::ChangeEntity;
+use serde::Serialize;
+use std::fmt;
+
+// Errors of the data layer
+//
+// The driver's own errors stay in `Db`, so a record that does not exist is
+// still `QueryReturnedNoRows`. Requests the data layer turns down and edits
+// against an outdated revision get variants of their own, which the commands,
+// the REST API and the CLI each map to what they show.
+
+#[derive(Debug)]
+pub enum Error {
+    Db(rusqlite::Error),
+    /// A request the database refuses, e.g. closing a sprint twice
+    Invalid(String),
+    Conflict(Conflict),
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+impl Error {
+    pub fn not_found() -> Self {
+        Error::Db(rusqlite::Error::QueryReturnedNoRows)
+    }
+
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Error::Db(rusqlite::Error::QueryReturnedNoRows))
+    }
+}
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Db(e)
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::Db(rusqlite::Error::QueryReturnedNoRows) => write!(f, "Not found"),
+            Error::Db(e) => e.fmt(f),
+            Error::Invalid(message) => write!(f, "{}", message),
+            Error::Conflict(conflict) => conflict.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Db(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+/// An update made against an outdated revision of a record. Carries the record
+/// as it is now, so the caller can show it or merge with it.
+#[derive(Debug, Serialize)]
+pub struct Conflict {
+    pub entity: ChangeEntity,
+    pub expected_revision: i64,
+    pub current: serde_json::Value,
+    // Fields changed on both sides, when the update was merged
+    pub fields: Vec<String>,
+    // Which operation of a batch ran into it, counting from 1
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub operation: Option<usize>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(operation) = self.operation {
+            write!(f, "Operation {} failed: ", operation)?;
+        }
+        write!(
+            f,
+            "The {} was changed elsewhere: it is at revision {}, not {}",
+            self.entity.as_str(),
+            self.current["revision"],
+            self.expected_revision
+        )?;
+        if !self.fields.is_empty() {
+            write!(f, "; {} changed on both sides", self.fields.join(", "))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,150 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// Name of the Tauri event carrying a `ChangeEvent`
+pub const CHANGE_EVENT: &str = "db-change";
+
+// Name of the Tauri event carrying a `reminders::Notification` when a reminder fires
+pub const REMINDER_EVENT: &str = "reminder";
+
+// Name of the Tauri event sent when another process (e.g. the CLI) has
+// written to the database and everything should be reloaded
+pub const EXTERNAL_CHANGE_EVENT: &str = "db-external-change";
+
+// Name of the Tauri event carrying the `Workspace` switched to, after which
+// everything should be reloaded
+pub const WORKSPACE_EVENT: &str = "workspace-switched";
+
+// Name of the Tauri event carrying a `LockStatus` whenever the active
+// workspace is locked or unlocked
+pub const LOCK_EVENT: &str = "workspace-lock";
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ChangeEntity {
+    #[serde(rename = "project")]
+    Project,
+    #[serde(rename = "task")]
+    Task,
+    #[serde(rename = "milestone")]
+    Milestone,
+    #[serde(rename = "sprint")]
+    Sprint,
+    #[serde(rename = "template")]
+    Template,
+    #[serde(rename = "reminder")]
+    Reminder,
+    #[serde(rename = "comment")]
+    Comment,
+    #[serde(rename = "attachment")]
+    Attachment,
+    #[serde(rename = "checklist_item")]
+    ChecklistItem,
+    #[serde(rename = "person")]
+    Person,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ChangeKind {
+    #[serde(rename = "created")]
+    Created,
+    #[serde(rename = "updated")]
+    Updated,
+    #[serde(rename = "deleted")]
+    Deleted,
+}
+
+impl ChangeEntity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeEntity::Project => "project",
+            ChangeEntity::Task => "task",
+            ChangeEntity::Milestone => "milestone",
+            ChangeEntity::Sprint => "sprint",
+            ChangeEntity::Template => "template",
+            ChangeEntity::Reminder => "reminder",
+            ChangeEntity::Comment => "comment",
+            ChangeEntity::Attachment => "attachment",
+            ChangeEntity::ChecklistItem => "checklist_item",
+            ChangeEntity::Person => "person",
+        }
+    }
+}
+
+// Fails for a name no entity goes by, rather than guessing one
+impl TryFrom<String> for ChangeEntity {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        Ok(match s.as_str() {
+            "project" => ChangeEntity::Project,
+            "task" => ChangeEntity::Task,
+            "milestone" => ChangeEntity::Milestone,
+            "sprint" => ChangeEntity::Sprint,
+            "template" => ChangeEntity::Template,
+            "reminder" => ChangeEntity::Reminder,
+            "comment" => ChangeEntity::Comment,
+            "attachment" => ChangeEntity::Attachment,
+            "checklist_item" => ChangeEntity::ChecklistItem,
+            "person" => ChangeEntity::Person,
+            _ => return Err(format!("Unknown entity: {}", s)),
+        })
+    }
+}
+
+impl ChangeKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeKind::Created => "created",
+            ChangeKind::Updated => "updated",
+            ChangeKind::Deleted => "deleted",
+        }
+    }
+}
+
+impl From<String> for ChangeKind {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "created" => ChangeKind::Created,
+            "deleted" => ChangeKind::Deleted,
+            _ => ChangeKind::Updated,
+        }
+    }
+}
+
+/// Published by `Database` after every successful mutation. `value` holds the
+/// entity as it is now, and is `None` for deletions.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChangeEvent {
+    pub entity: ChangeEntity,
+    pub id: String,
+    pub kind: ChangeKind,
+    pub value: Option<Value>,
+}
+
+impl ChangeEvent {
+    pub fn created<T: Serialize>(entity: ChangeEntity, id: &str, value: &T) -> Self {
+        Self::with_value(entity, id, ChangeKind::Created, value)
+    }
+
+    pub fn updated<T: Serialize>(entity: ChangeEntity, id: &str, value: &T) -> Self {
+        Self::with_value(entity, id, ChangeKind::Updated, value)
+    }
+
+    pub fn deleted(entity: ChangeEntity, id: &str) -> Self {
+        ChangeEvent {
+            entity,
+            id: id.to_string(),
+            kind: ChangeKind::Deleted,
+            value: None,
+        }
+    }
+
+    fn with_value<T: Serialize>(entity: ChangeEntity, id: &str, kind: ChangeKind, value: &T) -> Self {
+        ChangeEvent {
+            entity,
+            id: id.to_string(),
+            kind,
+            value: serde_json::to_value(value).ok(),
+        }
+    }
+}
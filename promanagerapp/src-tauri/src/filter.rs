@@ -0,0 +1,212 @@
+use crate::models::*;
+use chrono::{Duration, Utc};
+
+/// A task query shared by the GUI and the CLI, e.g.
+/// `status:todo priority:high project:"Website Redesign" due:overdue invoice`.
+///
+/// Every `key:value` term must match; bare words are matched
+/// case-insensitively against the title, description and comments.
+/// `assignee:me` stands for the current user.
+#[derive(Debug, Default)]
+pub struct TaskFilter {
+    pub status: Option<TaskStatus>,
+    pub priority: Option<TaskPriority>,
+    pub project: Option<ProjectFilter>,
+    pub due: Option<DueFilter>,
+    pub completed: Option<bool>,
+    pub assignee: Option<AssigneeFilter>,
+    pub text: Vec<String>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum AssigneeFilter {
+    Me,
+    None,
+    Named(String),
+}
+
+/// What a task is matched against besides its own fields.
+#[derive(Debug, Default)]
+pub struct MatchContext<'a> {
+    pub project_name: Option<&'a str>,
+    // Text of the task's comments
+    pub comments: &'a str,
+    pub assignee_names: Vec<&'a str>,
+    // Id of the person `assignee:me` refers to
+    pub current_user: Option<&'a str>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProjectFilter {
+    None,
+    Named(String),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum DueFilter {
+    Today,
+    Upcoming,
+    Overdue,
+    None,
+}
+
+impl TaskFilter {
+    pub fn parse(query: &str) -> Result<TaskFilter, String> {
+        let mut filter = TaskFilter::default();
+
+        for term in tokenize(query) {
+            let (key, value) = match term.split_once(':') {
+                Some((key, value)) if !value.is_empty() => (key.to_lowercase(), value.to_string()),
+                _ => {
+                    filter.text.push(term.to_lowercase());
+                    continue;
+                }
+            };
+
+            match key.as_str() {
+                "status" => {
+                    filter.status = Some(match value.as_str() {
+                        "todo" => TaskStatus::Todo,
+                        "in_progress" | "in-progress" => TaskStatus::InProgress,
+                        "done" => TaskStatus::Done,
+                        _ => return Err(format!("Unknown status '{}'", value)),
+                    })
+                }
+                "priority" => {
+                    filter.priority = Some(match value.as_str() {
+                        "low" => TaskPriority::Low,
+                        "medium" => TaskPriority::Medium,
+                        "high" => TaskPriority::High,
+                        _ => return Err(format!("Unknown priority '{}'", value)),
+                    })
+                }
+                "project" => {
+                    filter.project = Some(if value == "none" {
+                        ProjectFilter::None
+                    } else {
+                        ProjectFilter::Named(value.to_lowercase())
+                    })
+                }
+                "due" => {
+                    filter.due = Some(match value.as_str() {
+                        "today" => DueFilter::Today,
+                        "upcoming" => DueFilter::Upcoming,
+                        "overdue" => DueFilter::Overdue,
+                        "none" => DueFilter::None,
+                        _ => return Err(format!("Unknown due filter '{}'", value)),
+                    })
+                }
+                "assignee" => {
+                    filter.assignee = Some(match value.as_str() {
+                        "me" => AssigneeFilter::Me,
+                        "none" => AssigneeFilter::None,
+                        _ => AssigneeFilter::Named(value.to_lowercase()),
+                    })
+                }
+                "is" => match value.as_str() {
+                    "completed" | "done" => filter.completed = Some(true),
+                    "pending" | "open" => filter.completed = Some(false),
+                    _ => return Err(format!("Unknown state '{}'", value)),
+                },
+                _ => filter.text.push(term.to_lowercase()),
+            }
+        }
+
+        Ok(filter)
+    }
+
+    pub fn matches(&self, task: &Task, context: &MatchContext) -> bool {
+        if let Some(status) = &self.status {
+            if &task.status != status {
+                return false;
+            }
+        }
+        if let Some(priority) = &self.priority {
+            if &task.priority != priority {
+                return false;
+            }
+        }
+        if let Some(completed) = self.completed {
+            if task.completed != completed {
+                return false;
+            }
+        }
+
+        match &self.project {
+            Some(ProjectFilter::None) if task.project_id.is_some() => return false,
+            Some(ProjectFilter::Named(name)) => {
+                let matches_name = context.project_name.is_some_and(|p| p.to_lowercase() == *name);
+                let matches_id = task.project_id.as_deref() == Some(name.as_str());
+                if !matches_name && !matches_id {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+
+        match &self.assignee {
+            Some(AssigneeFilter::Me) => {
+                let me = context.current_user;
+                if !task.assignee_ids.iter().any(|id| Some(id.as_str()) == me) {
+                    return false;
+                }
+            }
+            Some(AssigneeFilter::None) if !task.assignee_ids.is_empty() => return false,
+            Some(AssigneeFilter::Named(name)) => {
+                let matches_name = context.assignee_names.iter().any(|a| a.to_lowercase() == *name);
+                let matches_id = task.assignee_ids.iter().any(|id| id == name);
+                if !matches_name && !matches_id {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+
+        if let Some(due) = &self.due {
+            let today = Utc::now().date_naive();
+            let due_day = task.due_date.map(|d| d.date_naive());
+            let matches_due = match due {
+                DueFilter::None => due_day.is_none(),
+                DueFilter::Today => due_day == Some(today),
+                DueFilter::Upcoming => due_day.is_some_and(|d| d > today && d <= today + Duration::days(8)),
+                DueFilter::Overdue => !task.completed && task.due_date.is_some_and(|d| d < Utc::now()),
+            };
+            if !matches_due {
+                return false;
+            }
+        }
+
+        let haystack = format!(
+            "{} {} {}",
+            task.title,
+            task.description.as_deref().unwrap_or(""),
+            context.comments
+        )
+        .to_lowercase();
+        self.text.iter().all(|word| haystack.contains(word.as_str()))
+    }
+}
+
+// Splits on whitespace, keeping "double quoted" runs together
+fn tokenize(query: &str) -> Vec<String> {
+    let mut terms = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+
+    for c in query.chars() {
+        match c {
+            '"' => quoted = !quoted,
+            c if c.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    terms.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+    if !current.is_empty() {
+        terms.push(current);
+    }
+
+    terms
+}
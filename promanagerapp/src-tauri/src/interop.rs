@@ -0,0 +1,521 @@
+use crate::models::*;
+use chrono::{DateTime, NaiveDate, Utc};
+use std::collections::HashMap;
+
+// todo.txt format
+//
+// A line looks like `x 2024-01-03 2024-01-01 (A) Title +Project @context due:2024-01-05`.
+// ProManager has no notion of contexts, so `@context` tokens and unknown
+// `key:value` pairs stay in the title and survive a round-trip untouched.
+// Title words that would read back as metadata (`+tag`, `due:`, `pri:`,
+// `status:in_progress`) are exported with a leading `\`, which the importer
+// strips again. Spaces in project tags become `_`, and a literal `_` or `%`
+// is percent-escaped so that names with underscores come back unchanged.
+
+pub fn export_todotxt(tasks: &[Task], projects: &[Project]) -> String {
+    let project_names: HashMap<&str, &str> = projects
+        .iter()
+        .map(|p| (p.id.as_str(), p.name.as_str()))
+        .collect();
+
+    let mut out = String::new();
+    for task in tasks {
+        let mut parts: Vec<String> = Vec::new();
+
+        if task.completed {
+            parts.push("x".to_string());
+            parts.push(format_date(task.completed_at.unwrap_or(task.updated_at)));
+        } else {
+            parts.push(format!("({})", priority_letter(&task.priority)));
+        }
+        parts.push(format_date(task.created_at));
+        parts.extend(task.title.split_whitespace().map(escape_title_word));
+
+        if task.completed {
+            // Completed tasks drop the priority prefix, so keep it as a key
+            parts.push(format!("pri:{}", priority_letter(&task.priority)));
+        }
+        if let Some(name) = task
+            .project_id
+            .as_deref()
+            .and_then(|id| project_names.get(id))
+        {
+            parts.push(format!("+{}", project_tag(name)));
+        }
+        if let TaskStatus::InProgress = task.status {
+            parts.push("status:in_progress".to_string());
+        }
+        if let Some(due_date) = task.due_date {
+            parts.push(format!("due:{}", format_date(due_date)));
+        }
+
+        out.push_str(&parts.join(" "));
+        out.push('\n');
+    }
+
+    out
+}
+
+pub fn parse_todotxt(input: &str) -> Vec<ImportedTask> {
+    input
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(parse_todotxt_line)
+        .collect()
+}
+
+fn parse_todotxt_line(line: &str) -> ImportedTask {
+    let mut tokens: Vec<&str> = line.split_whitespace().collect();
+    let mut completed = false;
+    let mut completed_at = None;
+    let mut created_at = None;
+    let mut priority = None;
+
+    if tokens.first() == Some(&"x") {
+        completed = true;
+        tokens.remove(0);
+        // A completed task carries its completion date first, then the creation date
+        if let Some(date) = tokens.first().and_then(|t| parse_date(t)) {
+            completed_at = Some(date);
+            tokens.remove(0);
+        }
+    } else if let Some(letter) = tokens.first().and_then(|t| parse_priority(t)) {
+        priority = Some(letter);
+        tokens.remove(0);
+    }
+
+    if let Some(date) = tokens.first().and_then(|t| parse_date(t)) {
+        created_at = Some(date);
+        tokens.remove(0);
+    }
+
+    let mut project = None;
+    let mut status = None;
+    let mut due_date = None;
+    let mut title_parts = Vec::new();
+
+    for token in tokens {
+        if let Some(tag) = token.strip_prefix('+').filter(|t| !t.is_empty()) {
+            if project.is_none() {
+                project = Some(project_name(tag));
+                continue;
+            }
+        } else if let Some(value) = token.strip_prefix("due:") {
+            if let Some(date) = parse_date(value) {
+                due_date = Some(date);
+                continue;
+            }
+        } else if let Some(value) = token.strip_prefix("pri:") {
+            if let Some(p) = letter_priority(value) {
+                priority = Some(p);
+                continue;
+            }
+        } else if token == "status:in_progress" {
+            status = Some(TaskStatus::InProgress);
+            continue;
+        } else if let Some(word) = token.strip_prefix('\\') {
+            title_parts.push(word);
+            continue;
+        }
+        title_parts.push(token);
+    }
+
+    let status = if completed {
+        TaskStatus::Done
+    } else {
+        status.unwrap_or(TaskStatus::Todo)
+    };
+
+    ImportedTask {
+        title: title_parts.join(" "),
+        project,
+        status,
+        priority: priority.unwrap_or(TaskPriority::Low),
+        due_date,
+        completed,
+        completed_at,
+        created_at,
+    }
+}
+
+// GitHub-style Markdown checklists
+//
+// Tasks without a project come first, then one `## Project` section per
+// project. Comments follow their task as an indented block quote, which the
+// importer skips: only the title, the checkbox and the project survive a
+// round-trip. Only `##` headings name a project; a `#` heading ends the
+// current project and deeper headings are read as part of it.
+
+pub fn export_markdown(tasks: &[Task], projects: &[Project], comments: &[Comment]) -> String {
+    let mut discussion: HashMap<&str, Vec<&Comment>> = HashMap::new();
+    for comment in comments {
+        discussion.entry(comment.task_id.as_str()).or_default().push(comment);
+    }
+    let item = |out: &mut String, task: &Task| {
+        out.push_str(&checklist_item(task));
+        for comment in discussion.get(task.id.as_str()).into_iter().flatten() {
+            out.push_str(&quoted_comment(comment));
+        }
+    };
+
+    let mut out = String::new();
+
+    for task in tasks.iter().filter(|t| t.project_id.is_none()) {
+        item(&mut out, task);
+    }
+
+    for project in projects {
+        let project_tasks: Vec<&Task> = tasks
+            .iter()
+            .filter(|t| t.project_id.as_deref() == Some(project.id.as_str()))
+            .collect();
+        if project_tasks.is_empty() {
+            continue;
+        }
+
+        if !out.is_empty() {
+            out.push('\n');
+        }
+        out.push_str(&format!("## {}\n\n", heading_text(&project.name)));
+        for task in project_tasks {
+            item(&mut out, task);
+        }
+    }
+
+    out
+}
+
+pub fn parse_markdown(input: &str) -> Vec<ImportedTask> {
+    let mut project: Option<String> = None;
+    let mut tasks = Vec::new();
+
+    for line in input.lines() {
+        let trimmed = line.trim();
+
+        if let Some((level, name)) = heading(trimmed) {
+            match level {
+                1 => project = None,
+                2 => project = if name.is_empty() { None } else { Some(name.to_string()) },
+                _ => {}
+            }
+            continue;
+        }
+
+        let item = trimmed
+            .strip_prefix("- ")
+            .or_else(|| trimmed.strip_prefix("* "));
+        let (completed, title) = match item {
+            Some(rest) if rest.starts_with("[ ] ") => (false, &rest[4..]),
+            Some(rest) if rest.starts_with("[x] ") || rest.starts_with("[X] ") => (true, &rest[4..]),
+            _ => continue,
+        };
+
+        tasks.push(ImportedTask {
+            title: title.trim().to_string(),
+            project: project.clone(),
+            status: if completed { TaskStatus::Done } else { TaskStatus::Todo },
+            priority: TaskPriority::Low,
+            due_date: None,
+            completed,
+            completed_at: None,
+            created_at: None,
+        });
+    }
+
+    tasks
+}
+
+// ATX heading level and text, `## Name` gives `(2, "Name")`
+fn heading(line: &str) -> Option<(usize, &str)> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level == 0 || level > 6 {
+        return None;
+    }
+    let rest = &line[level..];
+    if !rest.is_empty() && !rest.starts_with(char::is_whitespace) {
+        return None;
+    }
+    // A closing run of `#` only counts after a space, so "C#" keeps its `#`
+    let text = rest.trim();
+    let unclosed = text.trim_end_matches('#');
+    let text = if unclosed.is_empty() || unclosed.ends_with(char::is_whitespace) {
+        unclosed.trim_end()
+    } else {
+        text
+    };
+    Some((level, text))
+}
+
+// A name ending in `#` gets a closing sequence, which `heading` takes off again
+fn heading_text(name: &str) -> String {
+    let name = single_line(name);
+    if name.ends_with('#') {
+        format!("{} ##", name)
+    } else {
+        name
+    }
+}
+
+fn checklist_item(task: &Task) -> String {
+    let mark = if task.completed { "x" } else { " " };
+    format!("- [{}] {}\n", mark, single_line(&task.title))
+}
+
+// `> Author, 2024-01-31: body`, with every line of the body quoted
+fn quoted_comment(comment: &Comment) -> String {
+    let mut out = format!(
+        "  > {}, {}:",
+        single_line(&comment.author),
+        format_date(comment.created_at)
+    );
+    for (i, line) in comment.body.trim().lines().enumerate() {
+        out.push_str(if i == 0 { " " } else { "\n  > " });
+        out.push_str(line.trim_end());
+    }
+    out.push('\n');
+    out
+}
+
+fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn escape_title_word(word: &str) -> String {
+    let is_metadata = word.starts_with('\\')
+        || (word.len() > 1 && word.starts_with('+'))
+        || word.starts_with("due:")
+        || word.starts_with("pri:")
+        || word == "status:in_progress";
+    if is_metadata {
+        format!("\\{}", word)
+    } else {
+        word.to_string()
+    }
+}
+
+fn project_tag(name: &str) -> String {
+    let mut tag = String::new();
+    for c in name.chars() {
+        if c == ' ' {
+            tag.push('_');
+        } else if c == '_' || c == '%' || c.is_whitespace() {
+            let mut buf = [0; 4];
+            for byte in c.encode_utf8(&mut buf).bytes() {
+                tag.push_str(&format!("%{:02X}", byte));
+            }
+        } else {
+            tag.push(c);
+        }
+    }
+    tag
+}
+
+// Reverses `project_tag`; a `%` that doesn't start an escape is kept as is
+fn project_name(tag: &str) -> String {
+    let bytes = tag.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = if bytes[i] == b'%' {
+            tag.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok())
+        } else {
+            None
+        };
+        match (bytes[i], escaped) {
+            (_, Some(byte)) => {
+                out.push(byte);
+                i += 3;
+                continue;
+            }
+            (b'_', None) => out.push(b' '),
+            (byte, None) => out.push(byte),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+    date.format("%Y-%m-%d").to_string()
+}
+
+fn parse_date(s: &str) -> Option<DateTime<Utc>> {
+    NaiveDate::parse_from_str(s, "%Y-%m-%d")
+        .ok()
+        .and_then(|d| d.and_hms_opt(0, 0, 0))
+        .map(|d| d.and_utc())
+}
+
+fn priority_letter(priority: &TaskPriority) -> char {
+    match priority {
+        TaskPriority::High => 'A',
+        TaskPriority::Medium => 'B',
+        TaskPriority::Low => 'C',
+    }
+}
+
+fn letter_priority(s: &str) -> Option<TaskPriority> {
+    match s {
+        "A" => Some(TaskPriority::High),
+        "B" => Some(TaskPriority::Medium),
+        _ if s.len() == 1 && s.chars().all(|c| c.is_ascii_uppercase()) => Some(TaskPriority::Low),
+        _ => None,
+    }
+}
+
+fn parse_priority(token: &str) -> Option<TaskPriority> {
+    token
+        .strip_prefix('(')
+        .and_then(|t| t.strip_suffix(')'))
+        .and_then(letter_priority)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn date(day: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, 0, 0, 0).unwrap()
+    }
+
+    fn project(id: &str, name: &str) -> Project {
+        Project {
+            id: id.to_string(),
+            name: name.to_string(),
+            description: None,
+            color: "#3b82f6".to_string(),
+            created_at: date(1),
+            updated_at: date(1),
+            status: ProjectStatus::Active,
+            auto_archive: false,
+            revision: 1,
+        }
+    }
+
+    fn task(title: &str, project_id: Option<&str>) -> Task {
+        Task {
+            id: title.to_string(),
+            title: title.to_string(),
+            description: None,
+            project_id: project_id.map(str::to_string),
+            status: TaskStatus::Todo,
+            priority: TaskPriority::Medium,
+            due_date: None,
+            estimate_hours: None,
+            completed: false,
+            completed_at: None,
+            created_at: date(2),
+            updated_at: date(2),
+            milestone_id: None,
+            assignee_ids: Vec::new(),
+            revision: 1,
+            comment_count: 0,
+            checklist_total: 0,
+            checklist_checked: 0,
+        }
+    }
+
+    fn projects() -> Vec<Project> {
+        vec![
+            project("p1", "Home Office"),
+            project("p2", "home_office"),
+            project("p3", "50% done"),
+            project("p4", "C#"),
+            project("p5", "Ticket #"),
+        ]
+    }
+
+    #[test]
+    fn todotxt_round_trip() {
+        let mut done = task("Ship it", Some("p1"));
+        done.completed = true;
+        done.completed_at = Some(date(5));
+        done.status = TaskStatus::Done;
+        done.priority = TaskPriority::High;
+        let mut started = task("Call +1 555 about due:tomorrow", Some("p2"));
+        started.status = TaskStatus::InProgress;
+        started.due_date = Some(date(9));
+        let tasks = vec![
+            done,
+            started,
+            task("Set pri:A and status:in_progress by hand", Some("p3")),
+            task("Escape \\+tag @home", None),
+        ];
+
+        let imported = parse_todotxt(&export_todotxt(&tasks, &projects()));
+        assert_eq!(imported.len(), tasks.len());
+        for (task, imported) in tasks.iter().zip(&imported) {
+            let name = projects()
+                .into_iter()
+                .find(|p| task.project_id.as_deref() == Some(p.id.as_str()))
+                .map(|p| p.name);
+            assert_eq!(imported.title, task.title);
+            assert_eq!(imported.project, name);
+            assert_eq!(imported.status, task.status);
+            assert_eq!(imported.priority, task.priority);
+            assert_eq!(imported.due_date, task.due_date);
+            assert_eq!(imported.completed, task.completed);
+            assert_eq!(imported.completed_at, task.completed_at);
+            assert_eq!(imported.created_at, Some(task.created_at));
+        }
+    }
+
+    #[test]
+    fn todotxt_reads_plain_underscores_as_spaces() {
+        let imported = parse_todotxt("(A) Title +Home_Office +other");
+        assert_eq!(imported[0].project.as_deref(), Some("Home Office"));
+        assert_eq!(imported[0].title, "Title +other");
+    }
+
+    #[test]
+    fn markdown_round_trip() {
+        let mut done = task("Done already", Some("p1"));
+        done.completed = true;
+        let tasks = vec![
+            task("No project", None),
+            done,
+            task("Second project", Some("p2")),
+            task("Port to .NET", Some("p4")),
+            task("Number them", Some("p5")),
+        ];
+
+        let imported = parse_markdown(&export_markdown(&tasks, &projects(), &[]));
+        let read: Vec<(&str, Option<&str>, bool)> = imported
+            .iter()
+            .map(|t| (t.title.as_str(), t.project.as_deref(), t.completed))
+            .collect();
+        assert_eq!(
+            read,
+            vec![
+                ("No project", None, false),
+                ("Done already", Some("Home Office"), true),
+                ("Second project", Some("home_office"), false),
+                ("Port to .NET", Some("C#"), false),
+                ("Number them", Some("Ticket #"), false),
+            ]
+        );
+    }
+
+    #[test]
+    fn markdown_projects_come_from_level_two_headings() {
+        let input = "# Notes\n- [ ] loose\n## Work ##\n- [ ] a\n### Details\n- [x] b\n#hashtag\n- [ ] c\n# Later\n- [ ] d\n## F#\n- [ ] e\n";
+        let read: Vec<(String, Option<String>)> = parse_markdown(input)
+            .into_iter()
+            .map(|t| (t.title, t.project))
+            .collect();
+        let work = Some("Work".to_string());
+        assert_eq!(
+            read,
+            vec![
+                ("loose".to_string(), None),
+                ("a".to_string(), work.clone()),
+                ("b".to_string(), work.clone()),
+                ("c".to_string(), work),
+                ("d".to_string(), None),
+                ("e".to_string(), Some("F#".to_string())),
+            ]
+        );
+    }
+}
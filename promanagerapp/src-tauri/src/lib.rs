@@ -0,0 +1,14 @@
+// Data layer shared by the Tauri app and the `promanager` CLI
+pub mod analytics;
+pub mod attachments;
+pub mod database;
+pub mod digest;
+pub mod error;
+pub mod events;
+pub mod filter;
+pub mod interop;
+pub mod merge;
+pub mod models;
+pub mod reminders;
+pub mod templates;
+pub mod workspaces;
@@ -3,34 +3,267 @@
   windows_subsystem = "windows"
 )]
 
-mod database;
-mod models;
+mod api_server;
 mod commands;
 
-use database::Database;
-use std::sync::Mutex;
+use app::{analytics, attachments, database, digest, error, events, filter, interop, models, reminders, workspaces};
+
+use api_server::ApiServer;
+use attachments::AttachmentStores;
+use events::{ChangeEntity, CHANGE_EVENT, EXTERNAL_CHANGE_EVENT, LOCK_EVENT, REMINDER_EVENT, WORKSPACE_EVENT};
+use reminders::{Notification, Notifier};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Manager};
+use tokio::sync::Notify;
+use workspaces::{WorkspaceState, Workspaces, LOCKED};
+
+// How often to check whether another process has written to the database
+const EXTERNAL_CHANGE_POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+// How often to check whether an encrypted workspace has been idle long enough to lock
+const AUTO_LOCK_POLL_INTERVAL: Duration = Duration::from_secs(15);
 
 fn main() {
-  // Initialize database
-  let db = Database::new().expect("Failed to initialize database");
-  
+  // Initialize the active workspace's database; an encrypted one waits to be unlocked
+  let workspaces = Workspaces::new().expect("Failed to load workspaces");
+  workspaces.open().expect("Failed to initialize database");
+  let workspaces = Arc::new(workspaces);
+
+  // The REST API can also be started from the environment, for headless use
+  let api_server = std::env::var("PROMANAGER_API_PORT").ok().map(|port| {
+    let port = port.parse().expect("PROMANAGER_API_PORT must be a port number");
+    let token = std::env::var("PROMANAGER_API_TOKEN").ok();
+    let server = ApiServer::start(workspaces.clone(), port, token).expect("Failed to start REST API server");
+    // The token stays out of logs; the app shows it through get_api_server_info
+    println!("REST API listening on {}", server.info.url);
+    server
+  });
+
+  let events_workspaces = workspaces.clone();
+
   tauri::Builder::default()
-    .manage(Mutex::new(db))
+    .setup(move |app| {
+      let handle = app.handle();
+      manage_attachments(&events_workspaces, &handle);
+      forward_change_events(&events_workspaces, handle.clone());
+      schedule_reminders(&events_workspaces, handle.clone());
+      tauri::async_runtime::spawn(digest::run(events_workspaces.clone()));
+      watch_external_changes(events_workspaces.clone(), handle.clone());
+      lock_when_idle(events_workspaces, handle);
+      Ok(())
+    })
+    .manage(workspaces)
+    .manage(Mutex::new(api_server))
     .invoke_handler(tauri::generate_handler![
+      commands::get_workspaces,
+      commands::get_active_workspace,
+      commands::create_workspace,
+      commands::switch_workspace,
+      commands::rename_workspace,
+      commands::delete_workspace,
+      commands::get_lock_status,
+      commands::unlock_workspace,
+      commands::lock_workspace,
+      commands::encrypt_workspace,
+      commands::decrypt_workspace,
+      commands::change_passphrase,
+      commands::set_auto_lock,
       commands::create_project,
       commands::get_projects,
       commands::update_project,
+      commands::archive_project,
+      commands::unarchive_project,
+      commands::merge_projects,
       commands::delete_project,
       commands::create_task,
       commands::get_tasks,
       commands::get_tasks_by_project,
+      commands::filter_tasks,
       commands::update_task,
+      commands::duplicate_task,
+      commands::move_tasks,
+      commands::batch,
       commands::delete_task,
       commands::get_today_tasks,
       commands::get_upcoming_tasks,
       commands::mark_task_complete,
-      commands::get_task_stats
+      commands::create_milestone,
+      commands::get_milestones,
+      commands::get_milestone,
+      commands::update_milestone,
+      commands::delete_milestone,
+      commands::save_project_as_template,
+      commands::get_templates,
+      commands::update_template,
+      commands::delete_template,
+      commands::instantiate_template,
+      commands::create_sprint,
+      commands::get_sprints,
+      commands::update_sprint,
+      commands::delete_sprint,
+      commands::add_tasks_to_sprint,
+      commands::remove_task_from_sprint,
+      commands::close_sprint,
+      commands::get_sprint_report,
+      commands::create_reminder,
+      commands::get_reminders,
+      commands::snooze_reminder,
+      commands::delete_reminder,
+      commands::add_comment,
+      commands::get_comments,
+      commands::edit_comment,
+      commands::delete_comment,
+      commands::create_person,
+      commands::get_people,
+      commands::update_person,
+      commands::delete_person,
+      commands::get_current_user,
+      commands::set_current_user,
+      commands::assign_tasks,
+      commands::hand_over_tasks,
+      commands::get_workload,
+      commands::add_checklist_item,
+      commands::get_checklist,
+      commands::toggle_checklist_item,
+      commands::reorder_checklist,
+      commands::remove_checklist_item,
+      commands::convert_checklist_item,
+      commands::attach_file,
+      commands::get_attachments,
+      commands::open_attachment,
+      commands::export_attachment,
+      commands::delete_attachment,
+      commands::collect_attachment_garbage,
+      commands::generate_digest,
+      commands::get_digest_schedule,
+      commands::set_digest_schedule,
+      commands::get_task_stats,
+      commands::get_dashboard_stats,
+      commands::import_todotxt,
+      commands::export_todotxt,
+      commands::import_markdown,
+      commands::export_markdown,
+      commands::start_api_server,
+      commands::stop_api_server,
+      commands::get_api_server_info,
+      commands::get_task_activity,
+      commands::get_activity_feed,
+      commands::get_task_transitions,
+      commands::get_flow_metrics,
+      commands::get_burndown,
+      commands::get_cumulative_flow,
+      commands::forecast_project
     ])
     .run(tauri::generate_context!())
     .expect("error while running tauri application");
 }
+
+// Relays every database mutation to all open windows
+fn forward_change_events(workspaces: &WorkspaceState, handle: AppHandle) {
+  let _ = workspaces.subscribe(move |event| {
+    let _ = handle.emit_all(CHANGE_EVENT, event.clone());
+  });
+}
+
+// Keeps attachments under the app data directory and clears out files left
+// behind since the last run, e.g. by tasks deleted from the CLI. A locked
+// workspace is cleaned up once it is unlocked instead.
+fn manage_attachments(workspaces: &WorkspaceState, handle: &AppHandle) {
+  let root = handle.path_resolver().app_data_dir().unwrap_or_default().join("attachments");
+  let stores = AttachmentStores::new(root);
+  if let Ok((workspace, db)) = workspaces.active_database() {
+    if let Err(e) = attachments::collect_garbage(&db, &stores.store(&workspace)) {
+      eprintln!("Failed to clean up attachments: {}", e);
+    }
+  }
+  handle.manage(stores);
+}
+
+// Shows fired reminders as desktop notifications and tells open windows
+struct DesktopNotifier {
+  handle: AppHandle,
+}
+
+impl Notifier for DesktopNotifier {
+  fn notify(&self, notification: &Notification) {
+    let identifier = self.handle.config().tauri.bundle.identifier.clone();
+    let shown = tauri::api::notification::Notification::new(identifier)
+      .title(&notification.title)
+      .body(&notification.body)
+      .show();
+    if let Err(e) = shown {
+      eprintln!("Failed to show reminder notification: {}", e);
+    }
+    let _ = self.handle.emit_all(REMINDER_EVENT, notification.clone());
+  }
+}
+
+// Runs the reminder scheduler, waking it whenever a reminder or task changes
+// since either can move the next reminder
+fn schedule_reminders(workspaces: &WorkspaceState, handle: AppHandle) {
+  let wake = Arc::new(Notify::new());
+  let waker = wake.clone();
+  let _ = workspaces.subscribe(move |event| {
+    if matches!(event.entity, ChangeEntity::Reminder | ChangeEntity::Task) {
+      waker.notify_one();
+    }
+  });
+  let notifier = Arc::new(DesktopNotifier { handle });
+  tauri::async_runtime::spawn(reminders::run(workspaces.clone(), notifier, wake));
+}
+
+// Writes made by the CLI or another app instance do not go through our
+// listeners, so notice them via SQLite's data_version and ask windows to reload.
+// Workspaces switched to from the CLI are followed the same way.
+fn watch_external_changes(workspaces: WorkspaceState, handle: AppHandle) {
+  thread::spawn(move || {
+    let mut last_version = None;
+    loop {
+      thread::sleep(EXTERNAL_CHANGE_POLL_INTERVAL);
+      match workspaces.reload() {
+        Ok(Some(workspace)) => {
+          last_version = None;
+          let _ = handle.emit_all(WORKSPACE_EVENT, workspace);
+          if let Ok(status) = workspaces.lock_status() {
+            if status.locked {
+              let _ = handle.emit_all(LOCK_EVENT, status);
+            }
+          }
+        }
+        Ok(None) => {}
+        Err(e) => eprintln!("Failed to reload workspaces: {}", e),
+      }
+      let version = match workspaces.background_database() {
+        Ok(db) => db.data_version().ok(),
+        Err(e) if e == LOCKED => None,
+        Err(_) => return,
+      };
+      if last_version.is_some() && version != last_version {
+        let _ = handle.emit_all(EXTERNAL_CHANGE_EVENT, ());
+      }
+      last_version = version;
+    }
+  });
+}
+
+// Locks an encrypted workspace once it has been left alone for its
+// auto-lock time, and tells windows to show the unlock screen
+fn lock_when_idle(workspaces: WorkspaceState, handle: AppHandle) {
+  thread::spawn(move || loop {
+    thread::sleep(AUTO_LOCK_POLL_INTERVAL);
+    match workspaces.lock_if_idle(Instant::now()) {
+      Ok(true) => {
+        if let Err(e) = attachments::remove_open_copies() {
+          eprintln!("Failed to remove opened attachments: {}", e);
+        }
+        if let Ok(status) = workspaces.lock_status() {
+          let _ = handle.emit_all(LOCK_EVENT, status);
+        }
+      }
+      Ok(false) => {}
+      Err(_) => return,
+    }
+  });
+}
@@ -0,0 +1,32 @@
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+// Field-level merging of concurrent edits
+//
+// An update is written against the copy of a record its author last saw (the
+// base). If the record has been changed since, the update can still be applied
+// on top of the current copy as long as every field it sets was either left
+// alone in the meantime or already holds the value the update wants.
+
+/// Fields the update sets that were also changed, differently, since `base`.
+/// Empty when the update can be applied to `current` without losing anything.
+pub fn overlapping<T: Serialize, U: Serialize>(base: &T, current: &T, update: &U) -> Vec<String> {
+    let (base, current, update) = (object(base), object(current), object(update));
+
+    update
+        .iter()
+        .filter(|(field, value)| match current.get(*field) {
+            Some(now) => field.as_str() != "id" && base.get(*field) != Some(now) && now != *value,
+            // Not a field of the record, e.g. `expected_revision`
+            None => false,
+        })
+        .map(|(field, _)| field.clone())
+        .collect()
+}
+
+fn object<T: Serialize>(value: &T) -> Map<String, Value> {
+    match serde_json::to_value(value) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
@@ -1,5 +1,8 @@
-use serde::{Deserialize, Serialize};
-use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use chrono::{DateTime, NaiveDate, Utc};
+use crate::events::{ChangeEntity, ChangeKind};
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Project {
@@ -9,6 +12,23 @@ pub struct Project {
     pub color: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub status: ProjectStatus,
+    // Archive automatically once every task is completed
+    pub auto_archive: bool,
+    // Increases with every change; see `UpdateProject::expected_revision`
+    pub revision: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ProjectStatus {
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "on_hold")]
+    OnHold,
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "archived")]
+    Archived,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -20,13 +40,24 @@ pub struct Task {
     pub status: TaskStatus,
     pub priority: TaskPriority,
     pub due_date: Option<DateTime<Utc>>,
+    // Expected effort in hours
+    pub estimate_hours: Option<f64>,
     pub completed: bool,
     pub completed_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
+    pub milestone_id: Option<String>,
+    // People the task is assigned to, see `Database::assign_tasks`
+    pub assignee_ids: Vec<String>,
+    // Increases with every change; see `UpdateTask::expected_revision`
+    pub revision: i64,
+    pub comment_count: i64,
+    // Checklist progress, e.g. 2 of 5 items checked
+    pub checklist_total: i64,
+    pub checklist_checked: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum TaskStatus {
     #[serde(rename = "todo")]
     Todo,
@@ -36,7 +67,7 @@ pub enum TaskStatus {
     Done,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
 pub enum TaskPriority {
     #[serde(rename = "low")]
     Low,
@@ -46,19 +77,41 @@ pub enum TaskPriority {
     High,
 }
 
+/// A clearable field of an update payload, following JSON merge patch: a
+/// missing field keeps the current value and `null` clears it.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Patch<T> {
+    #[default]
+    Unchanged,
+    Clear,
+    Set(T),
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateProject {
     pub name: String,
     pub description: Option<String>,
     pub color: String,
+    #[serde(default)]
+    pub auto_archive: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateProject {
     pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
-    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub description: Patch<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub color: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<ProjectStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub auto_archive: Option<bool>,
+    // Reject the update with a conflict unless the project is still at this revision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_revision: Option<i64>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -69,18 +122,669 @@ pub struct CreateTask {
     pub status: TaskStatus,
     pub priority: TaskPriority,
     pub due_date: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub estimate_hours: Option<f64>,
+    // Also places the task in the milestone's project
+    #[serde(default)]
+    pub milestone_id: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct UpdateTask {
     pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub title: Option<String>,
-    pub description: Option<String>,
-    pub project_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub description: Patch<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub project_id: Patch<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub status: Option<TaskStatus>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub priority: Option<TaskPriority>,
-    pub due_date: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub due_date: Patch<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub estimate_hours: Patch<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub completed: Option<bool>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub milestone_id: Patch<String>,
+    // Replaces the task's assignees; an empty list unassigns everyone
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub assignee_ids: Option<Vec<String>>,
+    // Reject the update with a conflict unless the task is still at this revision
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expected_revision: Option<i64>,
+}
+
+/// What a duplicated task takes along besides its own fields. Comments,
+/// attachments, reminders, sprints and history always stay with the original.
+#[derive(Debug, Clone, Copy)]
+pub struct DuplicateOptions {
+    // Copy the checklist, the task's subtasks, with every item unchecked
+    pub checklist: bool,
+    pub assignees: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Milestone {
+    pub id: String,
+    pub project_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub target_date: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateMilestone {
+    pub project_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub target_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateMilestone {
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub description: Patch<String>,
+    pub target_date: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum MilestoneHealth {
+    #[serde(rename = "on_track")]
+    OnTrack,
+    #[serde(rename = "at_risk")]
+    AtRisk,
+    #[serde(rename = "overdue")]
+    Overdue,
+    #[serde(rename = "completed")]
+    Completed,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct MilestoneProgress {
+    #[serde(flatten)]
+    pub milestone: Milestone,
+    pub total_tasks: i64,
+    pub completed_tasks: i64,
+    pub progress: f64,
+    // Negative once the target date has passed
+    pub days_left: i64,
+    pub health: MilestoneHealth,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Sprint {
+    pub id: String,
+    pub name: String,
+    pub goal: Option<String>,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub capacity: Option<i64>, // number of tasks the team expects to finish
+    pub status: SprintStatus,
+    pub closed_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum SprintStatus {
+    #[serde(rename = "planned")]
+    Planned,
+    #[serde(rename = "active")]
+    Active,
+    #[serde(rename = "closed")]
+    Closed,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateSprint {
+    pub name: String,
+    pub goal: Option<String>,
+    pub start_date: DateTime<Utc>,
+    pub end_date: DateTime<Utc>,
+    pub capacity: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateSprint {
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub goal: Patch<String>,
+    pub start_date: Option<DateTime<Utc>>,
+    pub end_date: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub capacity: Patch<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum SprintOutcome {
+    #[serde(rename = "completed")]
+    Completed,
+    #[serde(rename = "carried_over")]
+    CarriedOver,
+    // Unfinished with no later sprint to carry it into
+    #[serde(rename = "incomplete")]
+    Incomplete,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SprintTask {
+    #[serde(flatten)]
+    pub task: Task,
+    pub added_at: DateTime<Utc>,
+    pub carried_over_from: Option<String>,
+    pub committed: bool,
+    pub completed: bool,
+    pub outcome: Option<SprintOutcome>, // set once the sprint is closed
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SprintReport {
+    pub sprint: Sprint,
+    pub committed_tasks: i64,
+    pub added_tasks: i64,
+    pub completed_tasks: i64,
+    pub carried_over_tasks: i64,
+    pub completion_rate: f64,
+    pub carried_over_to: Option<String>,
+    pub tasks: Vec<SprintTask>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProjectTemplate {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(flatten)]
+    pub body: TemplateBody,
+    // Placeholders used in the template, which must be given values when instantiating
+    pub variables: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// The part of a template stored as JSON; offsets are minutes from the start of the start day
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateBody {
+    pub project_name: String,
+    pub project_description: Option<String>,
+    pub color: String,
+    pub milestones: Vec<TemplateMilestone>,
+    pub tasks: Vec<TemplateTask>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateMilestone {
+    pub name: String,
+    pub description: Option<String>,
+    pub target_offset_minutes: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TemplateTask {
+    pub title: String,
+    pub description: Option<String>,
+    pub priority: TaskPriority,
+    pub due_offset_minutes: Option<i64>,
+    // Absent from templates saved before tasks had estimates
+    #[serde(default)]
+    pub estimate_hours: Option<f64>,
+    pub milestone: Option<usize>, // index into `milestones`
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveProjectTemplate {
+    pub project_id: String,
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateProjectTemplate {
+    pub id: String,
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub description: Patch<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct InstantiateTemplate {
+    pub template_id: String,
+    pub start_date: NaiveDate,
+    // Defaults to the template's project name, after substitution
+    pub project_name: Option<String>,
+    #[serde(default)]
+    pub variables: HashMap<String, String>,
+}
+
+/// What burndown and cumulative flow charts add up: tasks, or their
+/// estimated hours with unestimated tasks counting as zero.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ChartUnit {
+    #[serde(rename = "tasks")]
+    Tasks,
+    #[serde(rename = "estimate_hours")]
+    EstimateHours,
+}
+
+// Amounts are in the chart's `ChartUnit`
+#[derive(Debug, Serialize, Deserialize)]
+pub struct BurndownPoint {
+    pub date: NaiveDate,
+    pub remaining: f64,
+    pub ideal: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CumulativeFlowPoint {
+    pub date: NaiveDate,
+    pub todo: f64,
+    pub in_progress: f64,
+    pub done: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ForecastPercentile {
+    pub percentile: u8,
+    pub days: i64,
+    pub date: NaiveDate,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Forecast {
+    pub remaining_tasks: i64,
+    pub trials: u32,
+    pub history_days: u32,
+    pub seed: u64,
+    pub percentiles: Vec<ForecastPercentile>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusCounts {
+    pub todo: i64,
+    pub in_progress: i64,
+    pub done: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PriorityCounts {
+    pub low: i64,
+    pub medium: i64,
+    pub high: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ProjectProgress {
+    pub project_id: String,
+    pub name: String,
+    pub color: String,
+    pub total_tasks: i64,
+    pub completed_tasks: i64,
+    pub progress: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DailyCount {
+    pub date: String, // YYYY-MM-DD
+    pub count: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DashboardStats {
+    pub totals: TaskStats,
+    pub by_status: StatusCounts,
+    pub by_priority: PriorityCounts,
+    pub projects: Vec<ProjectProgress>,
+    pub completions_per_day: Vec<DailyCount>,
+    pub overdue_trend: Vec<DailyCount>,
+    pub completion_streak: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ImportedTask {
+    pub title: String,
+    pub project: Option<String>,
+    pub status: TaskStatus,
+    pub priority: TaskPriority,
+    pub due_date: Option<DateTime<Utc>>,
+    pub completed: bool,
+    pub completed_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+/// A reminder for a task, either at a fixed time or a number of minutes
+/// before the task is due. Relative reminders follow the due date around.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Reminder {
+    pub id: String,
+    pub task_id: String,
+    pub remind_at: Option<DateTime<Utc>>,
+    pub minutes_before_due: Option<i64>,
+    pub snoozed_until: Option<DateTime<Utc>>,
+    pub fired_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    // When it fires next; `None` once fired, when the task is completed, or
+    // when a relative reminder's task has no due date
+    pub next_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateReminder {
+    pub task_id: String,
+    // Exactly one of these
+    #[serde(default)]
+    pub remind_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub minutes_before_due: Option<i64>,
+}
+
+/// A comment on a task; `body` is Markdown.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Comment {
+    pub id: String,
+    pub task_id: String,
+    pub author: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    // `None` until the body is first changed
+    pub edited_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateComment {
+    pub task_id: String,
+    pub author: String,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdateComment {
+    pub id: String,
+    pub body: String,
+}
+
+/// A named set of projects and tasks with a database file of its own.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Workspace {
+    pub id: String,
+    pub name: String,
+    // Database file, relative to the folder holding the workspace registry
+    pub path: String,
+    // Whether the database is encrypted and has to be unlocked to be used
+    #[serde(default)]
+    pub encrypted: bool,
+    // Idle time after which an encrypted workspace locks itself, if ever
+    #[serde(default)]
+    pub auto_lock_minutes: Option<u32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Whether the active workspace has to be unlocked before anything else can
+/// be done with it.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LockStatus {
+    pub workspace: Workspace,
+    pub locked: bool,
+    // No database existed before this run, so now is the time to offer
+    // encrypting it
+    pub first_run: bool,
+}
+
+/// Someone tasks can be assigned to.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Person {
+    pub id: String,
+    pub name: String,
+    pub email: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreatePerson {
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct UpdatePerson {
+    pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(default, skip_serializing_if = "Patch::is_unchanged")]
+    pub email: Patch<String>,
+}
+
+/// Open tasks assigned to someone, in total and by the week they are due.
+/// A task with several assignees counts in full for each of them.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Workload {
+    pub person: Person,
+    pub open_tasks: i64,
+    pub estimate_hours: f64,
+    // Open tasks without an estimate, left out of `estimate_hours`
+    pub unestimated_tasks: i64,
+    pub weeks: Vec<WeekLoad>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct WeekLoad {
+    // Monday of the week; `None` for tasks without a due date, listed last
+    pub week_start: Option<NaiveDate>,
+    pub open_tasks: i64,
+    pub estimate_hours: f64,
+}
+
+/// A step on a task's checklist, too small to be a task of its own.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ChecklistItem {
+    pub id: String,
+    pub task_id: String,
+    pub text: String,
+    pub checked: bool,
+    // Items are listed by position, lowest first
+    pub position: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateChecklistItem {
+    pub task_id: String,
+    pub text: String,
+}
+
+/// A file attached to a task. The content lives in the attachment store
+/// under `hash`, shared by every attachment with the same content.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Attachment {
+    pub id: String,
+    pub task_id: String,
+    pub name: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateAttachment {
+    pub task_id: String,
+    pub name: String,
+    pub mime_type: String,
+    pub size: i64,
+    pub hash: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DigestPeriod {
+    #[serde(rename = "day")]
+    Day,
+    #[serde(rename = "week")]
+    Week,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum DigestFormat {
+    #[serde(rename = "markdown")]
+    Markdown,
+    #[serde(rename = "html")]
+    Html,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DigestTask {
+    #[serde(flatten)]
+    pub task: Task,
+    pub project_name: Option<String>,
+}
+
+/// Tasks completed during the digest period in one project, or in none
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompletedInProject {
+    pub project_name: Option<String>,
+    pub tasks: Vec<DigestTask>,
+}
+
+/// What needs attention today, and what got done over the last day or week
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Digest {
+    pub period: DigestPeriod,
+    pub generated_at: DateTime<Utc>,
+    pub since: DateTime<Utc>,
+    pub due_today: Vec<DigestTask>,
+    pub overdue: Vec<DigestTask>,
+    pub completed: Vec<CompletedInProject>,
+    // In progress but untouched for a while
+    pub stalled: Vec<DigestTask>,
+    pub upcoming: Vec<DigestTask>,
+}
+
+/// Writes a digest into `folder` every day, or every Monday for weekly ones,
+/// once `hour` (UTC) has passed
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DigestSchedule {
+    pub folder: String,
+    pub period: DigestPeriod,
+    pub format: DigestFormat,
+    #[serde(default)]
+    pub hour: u32,
+}
+
+/// One step of a batch, tagged by `op`, e.g. `{"op": "delete_task", "id": "..."}`
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "op")]
+pub enum BatchOperation {
+    #[serde(rename = "create_project")]
+    CreateProject { project: CreateProject },
+    #[serde(rename = "update_project")]
+    UpdateProject { project: UpdateProject },
+    #[serde(rename = "delete_project")]
+    DeleteProject { id: String },
+    #[serde(rename = "create_task")]
+    CreateTask { task: CreateTask },
+    #[serde(rename = "update_task")]
+    UpdateTask { task: UpdateTask },
+    #[serde(rename = "complete_task")]
+    CompleteTask {
+        id: String,
+        #[serde(default = "default_true")]
+        completed: bool,
+    },
+    #[serde(rename = "delete_task")]
+    DeleteTask { id: String },
+}
+
+/// What a batch operation produced, in the same order as the operations
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value")]
+pub enum BatchResult {
+    #[serde(rename = "project")]
+    Project(Project),
+    #[serde(rename = "task")]
+    Task(Task),
+    #[serde(rename = "deleted")]
+    Deleted(String),
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Where a change came from, recorded in the activity log
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum ChangeSource {
+    #[serde(rename = "gui")]
+    Gui,
+    #[serde(rename = "cli")]
+    Cli,
+    #[serde(rename = "api")]
+    Api,
+    #[serde(rename = "import")]
+    Import,
+}
+
+/// One entry of the append-only activity log. Updates produce one entry per
+/// changed field; creations and deletions have no `field`.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Activity {
+    pub id: i64,
+    pub entity: ChangeEntity,
+    pub entity_id: String,
+    pub action: ChangeKind,
+    pub field: Option<String>,
+    pub old_value: Option<String>,
+    pub new_value: Option<String>,
+    pub source: ChangeSource,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ActivityPage {
+    pub items: Vec<Activity>,
+    pub total: i64,
+    pub page: i64,
+    pub per_page: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StatusTransition {
+    pub id: i64,
+    pub task_id: String,
+    pub from_status: Option<TaskStatus>,
+    pub to_status: TaskStatus,
+    pub transitioned_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DurationStats {
+    pub count: usize,
+    pub mean_hours: f64,
+    pub median_hours: f64,
+    pub p85_hours: f64,
+    pub max_hours: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StatusDuration {
+    pub status: TaskStatus,
+    pub stats: DurationStats,
+}
+
+/// Lead time, cycle time and time-in-status distributions. Each is `None`
+/// when no task in the range contributes to it.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct FlowMetrics {
+    pub lead_time: Option<DurationStats>,
+    pub cycle_time: Option<DurationStats>,
+    pub time_in_status: Vec<StatusDuration>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -92,23 +796,23 @@ pub struct TaskStats {
     pub overdue_tasks: i32,
 }
 
-impl ToString for TaskStatus {
-    fn to_string(&self) -> String {
-        match self {
-            TaskStatus::Todo => "todo".to_string(),
-            TaskStatus::InProgress => "in_progress".to_string(),
-            TaskStatus::Done => "done".to_string(),
-        }
+impl fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            TaskStatus::Todo => "todo",
+            TaskStatus::InProgress => "in_progress",
+            TaskStatus::Done => "done",
+        })
     }
 }
 
-impl ToString for TaskPriority {
-    fn to_string(&self) -> String {
-        match self {
-            TaskPriority::Low => "low".to_string(),
-            TaskPriority::Medium => "medium".to_string(),
-            TaskPriority::High => "high".to_string(),
-        }
+impl fmt::Display for TaskPriority {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            TaskPriority::Low => "low",
+            TaskPriority::Medium => "medium",
+            TaskPriority::High => "high",
+        })
     }
 }
 
@@ -131,3 +835,191 @@ impl From<String> for TaskPriority {
         }
     }
 }
+
+impl ChangeSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChangeSource::Gui => "gui",
+            ChangeSource::Cli => "cli",
+            ChangeSource::Api => "api",
+            ChangeSource::Import => "import",
+        }
+    }
+}
+
+impl From<String> for ChangeSource {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "cli" => ChangeSource::Cli,
+            "api" => ChangeSource::Api,
+            "import" => ChangeSource::Import,
+            _ => ChangeSource::Gui,
+        }
+    }
+}
+
+impl SprintStatus {
+    pub fn at(start_date: DateTime<Utc>, closed_at: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Self {
+        match closed_at {
+            Some(_) => SprintStatus::Closed,
+            None if now < start_date => SprintStatus::Planned,
+            None => SprintStatus::Active,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SprintStatus::Planned => "planned",
+            SprintStatus::Active => "active",
+            SprintStatus::Closed => "closed",
+        }
+    }
+}
+
+impl SprintOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SprintOutcome::Completed => "completed",
+            SprintOutcome::CarriedOver => "carried_over",
+            SprintOutcome::Incomplete => "incomplete",
+        }
+    }
+}
+
+impl From<String> for SprintOutcome {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "completed" => SprintOutcome::Completed,
+            "carried_over" => SprintOutcome::CarriedOver,
+            _ => SprintOutcome::Incomplete,
+        }
+    }
+}
+
+impl ProjectStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ProjectStatus::Active => "active",
+            ProjectStatus::OnHold => "on_hold",
+            ProjectStatus::Completed => "completed",
+            ProjectStatus::Archived => "archived",
+        }
+    }
+}
+
+impl From<String> for ProjectStatus {
+    fn from(s: String) -> Self {
+        match s.as_str() {
+            "on_hold" => ProjectStatus::OnHold,
+            "completed" => ProjectStatus::Completed,
+            "archived" => ProjectStatus::Archived,
+            _ => ProjectStatus::Active,
+        }
+    }
+}
+
+impl BatchOperation {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BatchOperation::CreateProject { .. } => "create_project",
+            BatchOperation::UpdateProject { .. } => "update_project",
+            BatchOperation::DeleteProject { .. } => "delete_project",
+            BatchOperation::CreateTask { .. } => "create_task",
+            BatchOperation::UpdateTask { .. } => "update_task",
+            BatchOperation::CompleteTask { .. } => "complete_task",
+            BatchOperation::DeleteTask { .. } => "delete_task",
+        }
+    }
+}
+
+impl<T> Patch<T> {
+    pub fn is_unchanged(&self) -> bool {
+        matches!(self, Patch::Unchanged)
+    }
+
+    pub fn apply(self, field: &mut Option<T>) {
+        match self {
+            Patch::Unchanged => {}
+            Patch::Clear => *field = None,
+            Patch::Set(value) => *field = Some(value),
+        }
+    }
+}
+
+impl<T> From<Option<T>> for Patch<T> {
+    fn from(value: Option<T>) -> Self {
+        match value {
+            Some(value) => Patch::Set(value),
+            None => Patch::Clear,
+        }
+    }
+}
+
+// Only reached for fields that are present, so absence is left to `#[serde(default)]`
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Patch<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<T>::deserialize(deserializer).map(Patch::from)
+    }
+}
+
+impl<T: Serialize> Serialize for Patch<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Patch::Set(value) => serializer.serialize_some(value),
+            _ => serializer.serialize_none(),
+        }
+    }
+}
+
+impl DigestPeriod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestPeriod::Day => "day",
+            DigestPeriod::Week => "week",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn patch_reads_absent_null_and_value() {
+        let absent: UpdateSprint = serde_json::from_value(json!({ "id": "s1" })).unwrap();
+        assert_eq!(absent.goal, Patch::Unchanged);
+        let null: UpdateSprint = serde_json::from_value(json!({ "id": "s1", "goal": null })).unwrap();
+        assert_eq!(null.goal, Patch::Clear);
+        let set: UpdateSprint = serde_json::from_value(json!({ "id": "s1", "goal": "Ship" })).unwrap();
+        assert_eq!(set.goal, Patch::Set("Ship".to_string()));
+    }
+
+    #[test]
+    fn patch_round_trips_through_json() {
+        let payloads = vec![
+            json!({ "id": "t1" }),
+            json!({ "id": "t1", "description": null, "project_id": null, "due_date": null, "estimate_hours": null, "milestone_id": null }),
+            json!({
+                "id": "t1",
+                "description": "Details",
+                "project_id": "p1",
+                "due_date": "2030-01-05T00:00:00Z",
+                "estimate_hours": 1.5,
+                "milestone_id": "m1",
+            }),
+        ];
+        for payload in payloads {
+            let update: UpdateTask = serde_json::from_value(payload.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&update).unwrap(), payload);
+        }
+
+        let mut field = Some(1);
+        Patch::Unchanged.apply(&mut field);
+        assert_eq!(field, Some(1));
+        Patch::Set(2).apply(&mut field);
+        assert_eq!(field, Some(2));
+        Patch::Clear.apply(&mut field);
+        assert_eq!(field, None);
+    }
+}
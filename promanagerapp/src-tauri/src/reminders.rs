@@ -0,0 +1,266 @@
+use crate::database::Database;
+use crate::error::Result;
+use crate::models::*;
+use crate::workspaces::{self, WorkspaceState};
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+// Reminder scheduling
+//
+// Reminders live in the database, so the scheduler keeps no state of its own:
+// it sleeps until the next reminder is due, fires everything that is, and
+// goes back to sleep. Reminders that came due while the app was closed fire as
+// soon as it runs again. Sleeps are capped so reminders added by another
+// process (e.g. the CLI) are picked up without a wake-up.
+
+pub const MAX_SLEEP: std::time::Duration = std::time::Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Notification {
+    pub reminder_id: String,
+    pub task_id: String,
+    pub title: String,
+    pub body: String,
+}
+
+/// Delivers fired reminders, e.g. as desktop notifications
+pub trait Notifier: Send + Sync {
+    fn notify(&self, notification: &Notification);
+}
+
+/// Keeps notifications instead of delivering them, for tests and headless runs
+#[derive(Default)]
+pub struct RecordingNotifier {
+    sent: Mutex<Vec<Notification>>,
+}
+
+impl RecordingNotifier {
+    pub fn sent(&self) -> Vec<Notification> {
+        self.sent.lock().map(|sent| sent.clone()).unwrap_or_default()
+    }
+}
+
+impl Notifier for RecordingNotifier {
+    fn notify(&self, notification: &Notification) {
+        if let Ok(mut sent) = self.sent.lock() {
+            sent.push(notification.clone());
+        }
+    }
+}
+
+/// When the reminder fires next, given its task's due date and completion.
+pub fn next_at(reminder: &Reminder, due_date: Option<DateTime<Utc>>, completed: bool) -> Option<DateTime<Utc>> {
+    if completed || reminder.fired_at.is_some() {
+        return None;
+    }
+    let scheduled = match reminder.minutes_before_due {
+        Some(minutes) => due_date.map(|due| due - Duration::minutes(minutes)),
+        None => reminder.remind_at,
+    };
+    reminder.snoozed_until.or(scheduled)
+}
+
+/// Notifies about every reminder due at `now` and marks it fired, returning
+/// how many there were.
+pub fn fire_due(db: &Database, notifier: &dyn Notifier, now: DateTime<Utc>) -> Result<usize> {
+    let due = db.get_due_reminders(now)?;
+    for (reminder, task) in &due {
+        notifier.notify(&notification(reminder, task));
+        db.mark_reminder_fired(&reminder.id, now)?;
+    }
+    Ok(due.len())
+}
+
+/// Fires reminders of the active workspace as they come due, for as long as
+/// its database is usable, pausing while it is locked. Notifying `wake` makes
+/// the scheduler look again straight away, e.g. after a reminder or due date
+/// has changed.
+pub async fn run(workspaces: WorkspaceState, notifier: Arc<dyn Notifier>, wake: Arc<Notify>) {
+    loop {
+        let next = match workspaces.background_database() {
+            Ok(db) => {
+                if let Err(e) = fire_due(&db, notifier.as_ref(), Utc::now()) {
+                    eprintln!("Failed to fire reminders: {}", e);
+                }
+                db.next_reminder_at().unwrap_or(None)
+            }
+            // Reminders that come due meanwhile fire once it is unlocked
+            Err(e) if e == workspaces::LOCKED => None,
+            Err(_) => return,
+        };
+
+        let sleep = next
+            .and_then(|at| (at - Utc::now()).to_std().ok())
+            .map_or(MAX_SLEEP, |until| until.min(MAX_SLEEP));
+        tokio::select! {
+            _ = tokio::time::sleep(sleep) => {}
+            _ = wake.notified() => {}
+        }
+    }
+}
+
+fn notification(reminder: &Reminder, task: &Task) -> Notification {
+    let body = match task.due_date {
+        Some(due) => format!("Due {}", due.format("%Y-%m-%d %H:%M UTC")),
+        None => "Reminder".to_string(),
+    };
+    Notification {
+        reminder_id: reminder.id.clone(),
+        task_id: task.id.clone(),
+        title: task.title.clone(),
+        body,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2030, 1, 10, hour, minute, 0).unwrap()
+    }
+
+    fn reminder(remind_at: Option<DateTime<Utc>>, minutes_before_due: Option<i64>) -> Reminder {
+        Reminder {
+            id: "r1".to_string(),
+            task_id: "t1".to_string(),
+            remind_at,
+            minutes_before_due,
+            snoozed_until: None,
+            fired_at: None,
+            created_at: at(0, 0),
+            next_at: None,
+        }
+    }
+
+    fn task(db: &Database, title: &str, due_date: Option<DateTime<Utc>>) -> Task {
+        db.create_task(CreateTask {
+            title: title.to_string(),
+            description: None,
+            project_id: None,
+            status: TaskStatus::Todo,
+            priority: TaskPriority::Medium,
+            due_date,
+            estimate_hours: None,
+            milestone_id: None,
+        })
+        .unwrap()
+    }
+
+    fn remind(db: &Database, task: &Task, remind_at: Option<DateTime<Utc>>, minutes_before_due: Option<i64>) -> Reminder {
+        db.create_reminder(CreateReminder {
+            task_id: task.id.clone(),
+            remind_at,
+            minutes_before_due,
+        })
+        .unwrap()
+    }
+
+    fn titles(notifier: &RecordingNotifier) -> Vec<String> {
+        notifier.sent().into_iter().map(|n| n.title).collect()
+    }
+
+    #[test]
+    fn next_at_follows_time_due_date_and_snooze() {
+        assert_eq!(next_at(&reminder(Some(at(9, 0)), None), None, false), Some(at(9, 0)));
+        assert_eq!(next_at(&reminder(None, Some(30)), Some(at(12, 0)), false), Some(at(11, 30)));
+        assert_eq!(next_at(&reminder(None, Some(30)), None, false), None);
+
+        let mut snoozed = reminder(Some(at(9, 0)), None);
+        snoozed.snoozed_until = Some(at(9, 15));
+        assert_eq!(next_at(&snoozed, None, false), Some(at(9, 15)));
+
+        let mut fired = reminder(Some(at(9, 0)), None);
+        fired.fired_at = Some(at(9, 0));
+        assert_eq!(next_at(&fired, None, false), None);
+        assert_eq!(next_at(&reminder(Some(at(9, 0)), None), None, true), None);
+    }
+
+    #[test]
+    fn fires_once_when_due() {
+        let db = Database::open(":memory:").unwrap();
+        let notifier = RecordingNotifier::default();
+        let report = task(&db, "Send report", Some(at(12, 0)));
+        let reminder = remind(&db, &report, None, Some(60));
+        assert_eq!(db.next_reminder_at().unwrap(), Some(at(11, 0)));
+
+        assert_eq!(fire_due(&db, &notifier, at(10, 59)).unwrap(), 0);
+        assert_eq!(fire_due(&db, &notifier, at(11, 0)).unwrap(), 1);
+        assert_eq!(fire_due(&db, &notifier, at(11, 1)).unwrap(), 0);
+
+        let sent = notifier.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].reminder_id, reminder.id);
+        assert_eq!(sent[0].task_id, report.id);
+        assert_eq!(sent[0].title, "Send report");
+        assert_eq!(sent[0].body, "Due 2030-01-10 12:00 UTC");
+        assert_eq!(db.get_reminder(&reminder.id).unwrap().fired_at, Some(at(11, 0)));
+        assert_eq!(db.next_reminder_at().unwrap(), None);
+
+        let fired = db.get_activity_feed(1, 1).unwrap().items.remove(0);
+        assert_eq!((fired.entity_id, fired.field), (reminder.id, Some("fired_at".to_string())));
+        assert_eq!(fired.new_value, Some(at(11, 0).to_rfc3339()));
+    }
+
+    #[test]
+    fn snoozed_reminder_fires_again() {
+        let db = Database::open(":memory:").unwrap();
+        let notifier = RecordingNotifier::default();
+        let call = task(&db, "Call Ann", None);
+        let reminder = remind(&db, &call, Some(at(9, 0)), None);
+
+        assert_eq!(fire_due(&db, &notifier, at(9, 0)).unwrap(), 1);
+        let snoozed = db.snooze_reminder(&reminder.id, at(9, 10)).unwrap();
+        assert_eq!(snoozed.fired_at, None);
+        assert_eq!(snoozed.next_at, Some(at(9, 10)));
+
+        assert_eq!(fire_due(&db, &notifier, at(9, 5)).unwrap(), 0);
+        assert_eq!(fire_due(&db, &notifier, at(9, 10)).unwrap(), 1);
+        assert_eq!(titles(&notifier), vec!["Call Ann", "Call Ann"]);
+    }
+
+    #[test]
+    fn catches_up_after_restart() {
+        let path = std::env::temp_dir().join(format!("promanager-reminders-{}.db", uuid::Uuid::new_v4()));
+        {
+            let db = Database::open(&path).unwrap();
+            let first = task(&db, "Water plants", None);
+            let second = task(&db, "Pay rent", Some(at(12, 0)));
+            remind(&db, &first, Some(at(8, 0)), None);
+            remind(&db, &second, None, Some(120));
+            remind(&db, &second, Some(at(18, 0)), None);
+        }
+
+        // Both missed while closed fire at the first check, the later one waits
+        let db = Database::open(&path).unwrap();
+        let notifier = RecordingNotifier::default();
+        assert_eq!(fire_due(&db, &notifier, at(13, 0)).unwrap(), 2);
+        let mut sent = titles(&notifier);
+        sent.sort();
+        assert_eq!(sent, vec!["Pay rent", "Water plants"]);
+        assert_eq!(db.next_reminder_at().unwrap(), Some(at(18, 0)));
+        assert_eq!(fire_due(&db, &notifier, at(13, 1)).unwrap(), 0);
+
+        drop(db);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn skips_completed_tasks() {
+        let db = Database::open(":memory:").unwrap();
+        let notifier = RecordingNotifier::default();
+        let done = task(&db, "Already done", None);
+        let open = task(&db, "Still open", None);
+        let reminder = remind(&db, &done, Some(at(9, 0)), None);
+        remind(&db, &open, Some(at(9, 0)), None);
+        db.update_task(serde_json::from_value(serde_json::json!({ "id": done.id, "completed": true })).unwrap())
+            .unwrap();
+
+        assert_eq!(db.get_reminder(&reminder.id).unwrap().next_at, None);
+        assert_eq!(fire_due(&db, &notifier, at(10, 0)).unwrap(), 1);
+        assert_eq!(titles(&notifier), vec!["Still open"]);
+    }
+}
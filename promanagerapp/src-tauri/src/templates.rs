@@ -0,0 +1,119 @@
+use crate::models::*;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use std::collections::HashMap;
+
+// Project templates
+//
+// A template captures a project's milestones and tasks with dates stored as
+// offsets from the project's start (its creation day), so instantiating it at
+// a new start date shifts the whole schedule. Titles and descriptions may hold
+// `{{placeholders}}` that are filled in from caller-supplied variables.
+
+// Always available when instantiating, unless the caller overrides it
+pub const START_DATE_VARIABLE: &str = "start_date";
+
+pub fn template_body(project: &Project, milestones: &[Milestone], tasks: &[Task]) -> TemplateBody {
+    let start = start_of_day(project.created_at.date_naive());
+    let offset = |at: DateTime<Utc>| (at - start).num_minutes();
+
+    let milestone_index: HashMap<&str, usize> = milestones
+        .iter()
+        .enumerate()
+        .map(|(i, milestone)| (milestone.id.as_str(), i))
+        .collect();
+
+    TemplateBody {
+        project_name: project.name.clone(),
+        project_description: project.description.clone(),
+        color: project.color.clone(),
+        milestones: milestones
+            .iter()
+            .map(|milestone| TemplateMilestone {
+                name: milestone.name.clone(),
+                description: milestone.description.clone(),
+                target_offset_minutes: offset(milestone.target_date),
+            })
+            .collect(),
+        tasks: tasks
+            .iter()
+            .map(|task| TemplateTask {
+                title: task.title.clone(),
+                description: task.description.clone(),
+                priority: task.priority.clone(),
+                due_offset_minutes: task.due_date.map(offset),
+                estimate_hours: task.estimate_hours,
+                milestone: task
+                    .milestone_id
+                    .as_deref()
+                    .and_then(|id| milestone_index.get(id).copied()),
+            })
+            .collect(),
+    }
+}
+
+/// Names of every `{{placeholder}}` in the template, sorted and without duplicates.
+pub fn variables(body: &TemplateBody) -> Vec<String> {
+    let mut texts = vec![Some(&body.project_name), body.project_description.as_ref()];
+    for milestone in &body.milestones {
+        texts.push(Some(&milestone.name));
+        texts.push(milestone.description.as_ref());
+    }
+    for task in &body.tasks {
+        texts.push(Some(&task.title));
+        texts.push(task.description.as_ref());
+    }
+
+    let mut names: Vec<String> = texts.into_iter().flatten().flat_map(|text| placeholders(text)).collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+/// Replaces every `{{name}}` in `text`, failing on the first one without a value.
+pub fn substitute(text: &str, values: &HashMap<String, String>) -> Result<String, String> {
+    replace_placeholders(text, |name| {
+        values
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("No value given for template variable '{}'", name))
+    })
+}
+
+pub fn start_of_day(date: NaiveDate) -> DateTime<Utc> {
+    date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+}
+
+pub fn shifted(start: NaiveDate, offset_minutes: i64) -> DateTime<Utc> {
+    start_of_day(start) + Duration::minutes(offset_minutes)
+}
+
+fn placeholders(text: &str) -> Vec<String> {
+    let mut names = Vec::new();
+    let _ = replace_placeholders(text, |name| {
+        names.push(name.to_string());
+        Ok(String::new())
+    });
+    names
+}
+
+// An unterminated `{{` is kept as literal text
+fn replace_placeholders(
+    text: &str,
+    mut replace: impl FnMut(&str) -> Result<String, String>,
+) -> Result<String, String> {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+
+    while let Some(open) = rest.find("{{") {
+        let close = match rest[open + 2..].find("}}") {
+            Some(close) => open + 2 + close,
+            None => break,
+        };
+        result.push_str(&rest[..open]);
+        result.push_str(&replace(rest[open + 2..close].trim())?);
+        rest = &rest[close + 2..];
+    }
+    result.push_str(rest);
+
+    Ok(result)
+}
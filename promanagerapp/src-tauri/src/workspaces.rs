@@ -0,0 +1,837 @@
+use crate::database::{self, ChangeListener, Database, DEFAULT_DATABASE_PATH};
+use crate::events::ChangeEvent;
+use crate::models::{LockStatus, Workspace};
+use chrono::Utc;
+use fs2::FileExt;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, File};
+use std::io;
+use std::ops::{Deref, DerefMut};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+// Workspace registry
+//
+// Each workspace is a separate SQLite file, so work and personal tasks never
+// share a database. The registry in `workspaces.json` lists them and says
+// which one is active. The database used before workspaces existed stays
+// where it was as the "Default" workspace; new ones are created under
+// `workspaces/`. Commands, the REST API and background jobs all reach the
+// active database through the registry, so switching workspaces moves all of
+// them over at once.
+//
+// The CLI changes the registry while the app is running, so every change
+// rereads the file under a lock file, applies itself and writes it back, and
+// the app polls for switches made elsewhere with `reload`.
+//
+// A workspace can be encrypted with SQLCipher. Its passphrase is never
+// stored: an encrypted workspace starts out locked, with no database open,
+// and everything fails with `LOCKED` until it is unlocked. Locking it again,
+// by hand or once it has been left alone for `auto_lock_minutes`, closes the
+// database and so forgets the key. Change listeners wait in the session
+// meanwhile and are handed to the database once it is open again.
+//
+// Encrypting, decrypting, rekeying and deleting replace or remove the file
+// under any other connection to it. A process with a workspace open holds a
+// shared lock on its `.lock` file, and those changes need it exclusively, so
+// the CLI turns them down while the app has the workspace open.
+
+const REGISTRY_FILE: &str = "workspaces.json";
+
+// Held while the registry is read, changed and written back
+const REGISTRY_LOCK_FILE: &str = "workspaces.lock";
+
+const WORKSPACES_DIR: &str = "workspaces";
+
+pub const DEFAULT_WORKSPACE_ID: &str = "default";
+
+const DEFAULT_WORKSPACE_NAME: &str = "Default";
+
+// Given to workspaces when they are encrypted; it can be changed or turned off
+const DEFAULT_AUTO_LOCK_MINUTES: u32 = 15;
+
+const MIN_PASSPHRASE_LENGTH: usize = 8;
+
+/// Error of everything that needs the database while the active workspace
+/// is locked. The app shows its unlock screen when it sees it.
+pub const LOCKED: &str = "The workspace is locked";
+
+// Shared between the Tauri commands, the optional REST API server and the
+// background jobs
+pub type WorkspaceState = Arc<Workspaces>;
+
+#[derive(Clone, Serialize, Deserialize)]
+struct Registry {
+    active: String,
+    workspaces: Vec<Workspace>,
+}
+
+// The active workspace's database while it is open
+struct Session {
+    db: Option<Database>,
+    // Our shared lock on the database's lock file while it is open, see `hold`
+    held: Option<File>,
+    // Subscribed while no database was open
+    listeners: Vec<ChangeListener>,
+    last_used: Instant,
+}
+
+pub struct Workspaces {
+    root: PathBuf,
+    registry: Mutex<Registry>,
+    session: Mutex<Session>,
+    first_run: bool,
+}
+
+/// The open database of the active workspace, held until dropped.
+pub struct DatabaseGuard<'a>(MutexGuard<'a, Session>);
+
+impl Deref for DatabaseGuard<'_> {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        self.0.db.as_ref().expect("database is open")
+    }
+}
+
+impl DerefMut for DatabaseGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Database {
+        self.0.db.as_mut().expect("database is open")
+    }
+}
+
+impl Workspaces {
+    pub fn new() -> Result<Self, String> {
+        Self::load(".")
+    }
+
+    /// Reads the registry in `root`, creating it with just the default
+    /// workspace on first use. No database is opened until `open` is called.
+    pub fn load(root: impl Into<PathBuf>) -> Result<Self, String> {
+        let root = root.into();
+        let _lock = lock_registry_file(&root)?;
+        let mut first_run = false;
+        let registry = match read(&root)? {
+            Some(registry) => registry,
+            None => {
+                first_run = !root.join(DEFAULT_DATABASE_PATH).exists();
+                let registry = Registry {
+                    active: DEFAULT_WORKSPACE_ID.to_string(),
+                    workspaces: vec![Workspace {
+                        id: DEFAULT_WORKSPACE_ID.to_string(),
+                        name: DEFAULT_WORKSPACE_NAME.to_string(),
+                        path: DEFAULT_DATABASE_PATH.to_string(),
+                        encrypted: false,
+                        auto_lock_minutes: None,
+                        created_at: Utc::now(),
+                    }],
+                };
+                save(&root, &registry)?;
+                registry
+            }
+        };
+
+        Ok(Workspaces {
+            root,
+            registry: Mutex::new(registry),
+            session: Mutex::new(Session {
+                db: None,
+                held: None,
+                listeners: Vec::new(),
+                last_used: Instant::now(),
+            }),
+            first_run,
+        })
+    }
+
+    /// Opens the active workspace's database, unless it is encrypted and so
+    /// has to wait for `unlock`.
+    pub fn open(&self) -> Result<(), String> {
+        let active = self.active()?;
+        if active.encrypted {
+            return Ok(());
+        }
+        let held = hold(&self.path(&active), &active)?;
+        let db = Database::open(self.path(&active)).map_err(|e| open_failed(&active, e))?;
+        install(&mut *self.lock_session()?, db, held);
+        Ok(())
+    }
+
+    /// The active workspace's database. Every call counts as use of the app
+    /// and so puts off locking it.
+    pub fn database(&self) -> Result<DatabaseGuard<'_>, String> {
+        let mut db = self.open_database()?;
+        db.0.last_used = Instant::now();
+        Ok(db)
+    }
+
+    /// Like `database`, for background jobs, which should not keep the
+    /// workspace from locking itself.
+    pub fn background_database(&self) -> Result<DatabaseGuard<'_>, String> {
+        self.open_database()
+    }
+
+    /// The active workspace together with its database, which cannot be
+    /// switched in between.
+    pub fn active_database(&self) -> Result<(Workspace, DatabaseGuard<'_>), String> {
+        let registry = self.lock_registry()?;
+        let active = find(&registry, &registry.active)?.clone();
+        let mut session = self.lock_session()?;
+        if session.db.is_none() {
+            return Err(not_open(&active));
+        }
+        session.last_used = Instant::now();
+        Ok((active, DatabaseGuard(session)))
+    }
+
+    /// Fails with `LOCKED` unless the active workspace can be used.
+    pub fn ensure_unlocked(&self) -> Result<(), String> {
+        self.database().map(|_| ())
+    }
+
+    /// Subscribes to the changes of whichever database is open, now and
+    /// after switching or unlocking.
+    pub fn subscribe<F: Fn(&ChangeEvent) + Send + 'static>(&self, listener: F) -> Result<(), String> {
+        let mut session = self.lock_session()?;
+        match session.db.as_mut() {
+            Some(db) => db.subscribe(listener),
+            None => session.listeners.push(Box::new(listener)),
+        }
+        Ok(())
+    }
+
+    /// Where a workspace's database file is.
+    pub fn path(&self, workspace: &Workspace) -> PathBuf {
+        self.root.join(&workspace.path)
+    }
+
+    pub fn list(&self) -> Result<Vec<Workspace>, String> {
+        Ok(self.lock_registry()?.workspaces.clone())
+    }
+
+    pub fn active(&self) -> Result<Workspace, String> {
+        let registry = self.lock_registry()?;
+        find(&registry, &registry.active).cloned()
+    }
+
+    pub fn lock_status(&self) -> Result<LockStatus, String> {
+        let workspace = self.active()?;
+        let locked = workspace.encrypted && self.lock_session()?.db.is_none();
+        Ok(LockStatus {
+            workspace,
+            locked,
+            first_run: self.first_run,
+        })
+    }
+
+    /// Creates a workspace with an empty database, encrypted if a passphrase
+    /// is given, without switching to it.
+    pub fn create(&self, name: &str, passphrase: Option<&str>) -> Result<Workspace, String> {
+        if let Some(passphrase) = passphrase {
+            check_passphrase(passphrase)?;
+        }
+        self.edit(|registry| {
+            let name = check_name(registry, name, None)?;
+            let id = Uuid::new_v4().to_string();
+            let workspace = Workspace {
+                path: Path::new(WORKSPACES_DIR)
+                    .join(format!("{}.db", id))
+                    .to_string_lossy()
+                    .to_string(),
+                id,
+                name,
+                encrypted: passphrase.is_some(),
+                auto_lock_minutes: passphrase.map(|_| DEFAULT_AUTO_LOCK_MINUTES),
+                created_at: Utc::now(),
+            };
+
+            fs::create_dir_all(self.root.join(WORKSPACES_DIR)).map_err(|e| e.to_string())?;
+            Database::open_encrypted(self.path(&workspace), passphrase).map_err(|e| open_failed(&workspace, e))?;
+            registry.workspaces.push(workspace.clone());
+            Ok(workspace)
+        })
+    }
+
+    /// Makes another workspace the active one. If a database is open it is
+    /// swapped for the new workspace's, or just closed when that one is
+    /// encrypted and has to be unlocked first.
+    pub fn switch(&self, id: &str) -> Result<Workspace, String> {
+        // The open database is the one that was active when we last looked
+        let opened = self.active()?.id;
+        self.edit(|registry| {
+            let workspace = find(registry, id)?.clone();
+            let mut session = self.lock_session()?;
+            if session.db.is_some() && opened != workspace.id {
+                self.activate(&mut session, &workspace)?;
+            }
+            registry.active = workspace.id.clone();
+            Ok(workspace)
+        })
+    }
+
+    /// Rereads the registry, which the CLI may have changed, and moves over
+    /// to the active workspace if another one was switched to meanwhile.
+    /// Returns the workspace it moved to.
+    pub fn reload(&self) -> Result<Option<Workspace>, String> {
+        let mut registry = self.lock_registry()?;
+        // The file is only ever replaced whole, so it needs no lock to be read
+        let current = match read(&self.root)? {
+            Some(current) => current,
+            None => return Ok(None),
+        };
+        let switched = current.active != registry.active;
+        *registry = current;
+        let active = find(&registry, &registry.active)?.clone();
+        let mut session = self.lock_session()?;
+        // Also opens the workspace if it was decrypted while locked here
+        if !switched && (session.db.is_some() || active.encrypted) {
+            return Ok(None);
+        }
+
+        self.activate(&mut session, &active)?;
+        Ok(Some(active))
+    }
+
+    pub fn rename(&self, id: &str, name: &str) -> Result<Workspace, String> {
+        self.edit(|registry| {
+            find(registry, id)?;
+            let name = check_name(registry, name, Some(id))?;
+            update(registry, id, |workspace| workspace.name = name)
+        })
+    }
+
+    /// Deletes a workspace along with its database file. The active
+    /// workspace cannot be deleted, so there is always one left.
+    pub fn delete(&self, id: &str) -> Result<Workspace, String> {
+        let (workspace, claimed) = self.edit(|registry| {
+            let workspace = find(registry, id)?.clone();
+            if registry.active == workspace.id {
+                return Err("Switch to another workspace before deleting this one".to_string());
+            }
+            let claimed = claim(&self.path(&workspace), &workspace)?;
+            registry.workspaces.retain(|other| other.id != workspace.id);
+            Ok((workspace, claimed))
+        })?;
+
+        // SQLite may have left a journal or WAL files next to the database
+        let path = self.path(&workspace);
+        let _ = fs::remove_file(lock_path(&path));
+        drop(claimed);
+        for suffix in &["", "-journal", "-wal", "-shm"] {
+            let mut file = path.clone().into_os_string();
+            file.push(suffix);
+            match fs::remove_file(&file) {
+                Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                    return Err(format!("Failed to delete {}: {}", Path::new(&file).display(), e));
+                }
+                _ => {}
+            }
+        }
+        Ok(workspace)
+    }
+
+    /// Opens the active workspace's encrypted database.
+    pub fn unlock(&self, passphrase: &str) -> Result<(), String> {
+        let active = self.active()?;
+        if !active.encrypted {
+            return Err("This workspace is not encrypted".to_string());
+        }
+        let mut session = self.lock_session()?;
+        if session.db.is_none() {
+            let held = hold(&self.path(&active), &active)?;
+            let db = open_with_passphrase(&self.path(&active), &active, passphrase)?;
+            install(&mut session, db, held);
+        }
+        Ok(())
+    }
+
+    /// Closes the active workspace's encrypted database until it is unlocked
+    /// again.
+    pub fn lock(&self) -> Result<(), String> {
+        if !self.active()?.encrypted {
+            return Err("Only encrypted workspaces can be locked".to_string());
+        }
+        close(&mut *self.lock_session()?);
+        Ok(())
+    }
+
+    /// Locks the active workspace if it has not been used for its
+    /// `auto_lock_minutes`, returning whether it did.
+    pub fn lock_if_idle(&self, now: Instant) -> Result<bool, String> {
+        let active = self.active()?;
+        let minutes = match active.auto_lock_minutes {
+            Some(minutes) if active.encrypted => minutes,
+            _ => return Ok(false),
+        };
+        let mut session = self.lock_session()?;
+        let idle = now.saturating_duration_since(session.last_used);
+        if session.db.is_none() || idle < Duration::from_secs(u64::from(minutes) * 60) {
+            return Ok(false);
+        }
+        close(&mut session);
+        Ok(true)
+    }
+
+    /// Encrypts the active workspace's database, which has to be unlocked
+    /// while the app is running. Its attachments are left as they are.
+    pub fn encrypt(&self, passphrase: &str) -> Result<Workspace, String> {
+        check_passphrase(passphrase)?;
+        self.edit(|registry| {
+            let active = find(registry, &registry.active)?.clone();
+            if active.encrypted {
+                return Err("This workspace is already encrypted".to_string());
+            }
+
+            self.rewrite(&active, None, Some(passphrase))?;
+            update(registry, &active.id, |workspace| {
+                workspace.encrypted = true;
+                workspace.auto_lock_minutes = Some(DEFAULT_AUTO_LOCK_MINUTES);
+            })
+        })
+    }
+
+    /// Turns the active workspace's database back into a plain one, once the
+    /// passphrase has been confirmed.
+    pub fn decrypt(&self, passphrase: &str) -> Result<Workspace, String> {
+        self.edit(|registry| {
+            let active = find(registry, &registry.active)?.clone();
+            if !active.encrypted {
+                return Err("This workspace is not encrypted".to_string());
+            }
+
+            self.rewrite(&active, Some(passphrase), None)?;
+            update(registry, &active.id, |workspace| {
+                workspace.encrypted = false;
+                workspace.auto_lock_minutes = None;
+            })
+        })
+    }
+
+    /// Re-encrypts the active workspace's database with a new passphrase.
+    pub fn change_passphrase(&self, current: &str, new: &str) -> Result<(), String> {
+        check_passphrase(new)?;
+        let active = self.active()?;
+        if !active.encrypted {
+            return Err("This workspace is not encrypted".to_string());
+        }
+
+        // The current passphrase is asked for even while the workspace is unlocked
+        let checked = open_with_passphrase(&self.path(&active), &active, current)?;
+        let session = self.lock_session()?;
+        // The open connection has to do it, or it would be left with the old
+        // key; without one here, nobody else may have it open either
+        let _claimed = match session.db {
+            Some(_) => None,
+            None => Some(claim(&self.path(&active), &active)?),
+        };
+        let db = session.db.as_ref().unwrap_or(&checked);
+        db.rekey(new).map_err(|e| e.to_string())
+    }
+
+    /// Sets how long the active workspace can be left alone before it locks
+    /// itself; `None` keeps it unlocked until it is locked by hand.
+    pub fn set_auto_lock(&self, minutes: Option<u32>) -> Result<Workspace, String> {
+        if minutes == Some(0) {
+            return Err("Auto-lock needs at least a minute".to_string());
+        }
+        self.edit(|registry| {
+            let active = find(registry, &registry.active)?.clone();
+            if !active.encrypted {
+                return Err("Only encrypted workspaces can be locked".to_string());
+            }
+            update(registry, &active.id, |workspace| {
+                workspace.auto_lock_minutes = minutes;
+            })
+        })
+    }
+
+    // Copies the workspace's database into a new file with another
+    // passphrase, or none, and puts it in place of the old one, since
+    // SQLCipher cannot encrypt or decrypt a database in place
+    fn rewrite(&self, workspace: &Workspace, from: Option<&str>, to: Option<&str>) -> Result<(), String> {
+        let path = self.path(workspace);
+        let mut copy = path.clone().into_os_string();
+        copy.push(".rewrite");
+        let copy = PathBuf::from(copy);
+
+        let checked = match from {
+            Some(passphrase) => Some(open_with_passphrase(&path, workspace, passphrase)?),
+            None => None,
+        };
+        let mut session = self.lock_session()?;
+        let was_open = session.db.is_some();
+        // Our own shared lock would stand in the way of claiming it
+        session.held = None;
+        let claimed = match claim(&path, workspace) {
+            Ok(claimed) => claimed,
+            Err(e) => {
+                if was_open {
+                    session.held = Some(hold(&path, workspace)?);
+                }
+                return Err(e);
+            }
+        };
+        let db = match (session.db.take(), checked) {
+            (Some(db), _) | (None, Some(db)) => db,
+            (None, None) => Database::open(&path).map_err(|e| open_failed(workspace, e))?,
+        };
+
+        let _ = fs::remove_file(&copy);
+        if let Err(e) = db.export_copy(&copy, to) {
+            let _ = fs::remove_file(&copy);
+            drop(claimed);
+            if was_open {
+                install(&mut session, db, hold(&path, workspace)?);
+            }
+            return Err(format!("Failed to rewrite workspace {}: {}", workspace.name, e));
+        }
+        session.listeners.extend(db.close());
+
+        let renamed = fs::rename(&copy, &path);
+        drop(claimed);
+        if was_open {
+            let passphrase = if renamed.is_ok() { to } else { from };
+            let held = hold(&path, workspace)?;
+            let db = Database::open_encrypted(&path, passphrase).map_err(|e| open_failed(workspace, e))?;
+            install(&mut session, db, held);
+        }
+        renamed.map_err(|e| format!("Failed to replace {}: {}", path.display(), e))
+    }
+
+    // Applies `change` to the registry as it is on disk and saves it, leaving
+    // both it and our copy as they were if the change fails
+    fn edit<T>(&self, change: impl FnOnce(&mut Registry) -> Result<T, String>) -> Result<T, String> {
+        let mut registry = self.lock_registry()?;
+        let _lock = lock_registry_file(&self.root)?;
+        let mut next = match read(&self.root)? {
+            Some(current) => current,
+            None => registry.clone(),
+        };
+        let value = change(&mut next)?;
+        save(&self.root, &next)?;
+        *registry = next;
+        Ok(value)
+    }
+
+    // Closes the open database, if any, and opens `workspace`'s instead,
+    // unless it is encrypted and has to be unlocked first
+    fn activate(&self, session: &mut Session, workspace: &Workspace) -> Result<(), String> {
+        close(session);
+        if !workspace.encrypted {
+            let held = hold(&self.path(workspace), workspace)?;
+            let db = Database::open(self.path(workspace)).map_err(|e| open_failed(workspace, e))?;
+            install(session, db, held);
+        }
+        Ok(())
+    }
+
+    fn open_database(&self) -> Result<DatabaseGuard<'_>, String> {
+        let session = self.lock_session()?;
+        if session.db.is_some() {
+            return Ok(DatabaseGuard(session));
+        }
+        drop(session);
+        Err(not_open(&self.active()?))
+    }
+
+    fn lock_registry(&self) -> Result<MutexGuard<'_, Registry>, String> {
+        self.registry.lock().map_err(|_| "Failed to lock workspaces".to_string())
+    }
+
+    fn lock_session(&self) -> Result<MutexGuard<'_, Session>, String> {
+        self.session.lock().map_err(|_| "Failed to lock database".to_string())
+    }
+}
+
+// Makes `db` the open database, subscribing everyone who was waiting for one
+fn install(session: &mut Session, mut db: Database, held: File) {
+    db.resubscribe(std::mem::take(&mut session.listeners));
+    session.db = Some(db);
+    session.held = Some(held);
+    session.last_used = Instant::now();
+}
+
+// Closes the open database, if any, keeping its listeners for the next one
+fn close(session: &mut Session) {
+    if let Some(db) = session.db.take() {
+        session.listeners.extend(db.close());
+    }
+    session.held = None;
+}
+
+// Shares the database with other processes for as long as the returned file
+// is kept, which keeps them from claiming it
+fn hold(path: &Path, workspace: &Workspace) -> Result<File, String> {
+    let file = open_lock_file(path)?;
+    match FileExt::try_lock_shared(&file) {
+        Ok(()) => Ok(file),
+        Err(e) if is_contended(&e) => Err(format!("Workspace {} is being changed by another process", workspace.name)),
+        Err(e) => Err(format!("Failed to lock {}: {}", lock_path(path).display(), e)),
+    }
+}
+
+// Takes the database for ourselves, failing if any other process has it open
+fn claim(path: &Path, workspace: &Workspace) -> Result<File, String> {
+    let file = open_lock_file(path)?;
+    match FileExt::try_lock_exclusive(&file) {
+        Ok(()) => Ok(file),
+        Err(e) if is_contended(&e) => Err(format!(
+            "Workspace {} is open in another process, e.g. the app; close or lock it there first",
+            workspace.name
+        )),
+        Err(e) => Err(format!("Failed to lock {}: {}", lock_path(path).display(), e)),
+    }
+}
+
+fn lock_path(path: &Path) -> PathBuf {
+    let mut lock = path.as_os_str().to_owned();
+    lock.push(".lock");
+    PathBuf::from(lock)
+}
+
+fn open_lock_file(path: &Path) -> Result<File, String> {
+    let lock = lock_path(path);
+    fs::OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock)
+        .map_err(|e| format!("Failed to lock {}: {}", lock.display(), e))
+}
+
+fn is_contended(e: &io::Error) -> bool {
+    e.raw_os_error() == fs2::lock_contended_error().raw_os_error()
+}
+
+fn not_open(active: &Workspace) -> String {
+    if active.encrypted {
+        LOCKED.to_string()
+    } else {
+        "No workspace is open".to_string()
+    }
+}
+
+fn open_with_passphrase(path: &Path, workspace: &Workspace, passphrase: &str) -> Result<Database, String> {
+    Database::open_encrypted(path, Some(passphrase)).map_err(|e| {
+        if database::is_wrong_passphrase(&e) {
+            "Wrong passphrase".to_string()
+        } else {
+            open_failed(workspace, e)
+        }
+    })
+}
+
+fn check_passphrase(passphrase: &str) -> Result<(), String> {
+    if passphrase.chars().count() < MIN_PASSPHRASE_LENGTH {
+        return Err(format!("Use a passphrase of at least {} characters", MIN_PASSPHRASE_LENGTH));
+    }
+    Ok(())
+}
+
+fn find<'a>(registry: &'a Registry, id: &str) -> Result<&'a Workspace, String> {
+    registry
+        .workspaces
+        .iter()
+        .find(|workspace| workspace.id == id)
+        .ok_or_else(|| format!("No workspace with id {}", id))
+}
+
+// Changes one workspace in the registry
+fn update(registry: &mut Registry, id: &str, change: impl FnOnce(&mut Workspace)) -> Result<Workspace, String> {
+    let workspace = registry
+        .workspaces
+        .iter_mut()
+        .find(|workspace| workspace.id == id)
+        .ok_or_else(|| format!("No workspace with id {}", id))?;
+    change(workspace);
+    Ok(workspace.clone())
+}
+
+// Trims a workspace name and checks no other workspace has it, ignoring case
+fn check_name(registry: &Registry, name: &str, except_id: Option<&str>) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Workspace name cannot be empty".to_string());
+    }
+    let taken = registry.workspaces.iter().any(|other| {
+        Some(other.id.as_str()) != except_id && other.name.to_lowercase() == name.to_lowercase()
+    });
+    if taken {
+        return Err(format!("There is already a workspace called {}", name));
+    }
+    Ok(name.to_string())
+}
+
+// The registry on disk, `None` before it has first been saved
+fn read(root: &Path) -> Result<Option<Registry>, String> {
+    let path = root.join(REGISTRY_FILE);
+    match fs::read_to_string(&path) {
+        Ok(json) => serde_json::from_str(&json)
+            .map(Some)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(format!("Failed to read {}: {}", path.display(), e)),
+    }
+}
+
+// Waits for other processes to finish changing the registry; the lock is
+// released when the file is dropped
+fn lock_registry_file(root: &Path) -> Result<File, String> {
+    let path = root.join(REGISTRY_LOCK_FILE);
+    fs::create_dir_all(root)
+        .and_then(|_| fs::OpenOptions::new().create(true).truncate(false).write(true).open(&path))
+        .and_then(|file| FileExt::lock_exclusive(&file).map(|_| file))
+        .map_err(|e| format!("Failed to lock {}: {}", path.display(), e))
+}
+
+// Writes the registry next to its final name first, so a crash never leaves
+// it half written
+fn save(root: &Path, registry: &Registry) -> Result<(), String> {
+    let path = root.join(REGISTRY_FILE);
+    let partial = root.join(format!("{}.partial", REGISTRY_FILE));
+    let json = serde_json::to_string_pretty(registry).map_err(|e| e.to_string())?;
+    fs::create_dir_all(root)
+        .and_then(|_| fs::write(&partial, json))
+        .and_then(|_| fs::rename(&partial, &path))
+        .map_err(|e| format!("Failed to save {}: {}", path.display(), e))
+}
+
+fn open_failed(workspace: &Workspace, e: crate::error::Error) -> String {
+    format!("Failed to open workspace {}: {}", workspace.name, e)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CreateProject;
+
+    // A registry of its own, removed again when dropped
+    struct TempRoot(PathBuf);
+
+    impl TempRoot {
+        fn new() -> Self {
+            TempRoot(std::env::temp_dir().join(format!("promanager-workspaces-{}", Uuid::new_v4())))
+        }
+    }
+
+    impl Drop for TempRoot {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    #[test]
+    fn changes_from_another_process_are_kept() {
+        let root = TempRoot::new();
+        let app = Workspaces::load(&root.0).unwrap();
+        app.open().unwrap();
+        let cli = Workspaces::load(&root.0).unwrap();
+
+        let work = cli.create("Work", None).unwrap();
+        cli.rename(DEFAULT_WORKSPACE_ID, "Personal").unwrap();
+        // The app's next change starts from what the CLI wrote
+        let home = app.create("Home", None).unwrap();
+
+        let names: Vec<String> = Workspaces::load(&root.0).unwrap().list().unwrap().into_iter().map(|w| w.name).collect();
+        assert_eq!(names, vec!["Personal", "Work", "Home"]);
+        assert!(app.rename(&home.id, "work").is_err());
+        assert_eq!(app.list().unwrap().len(), 3);
+
+        cli.switch(&work.id).unwrap();
+        assert_eq!(app.reload().unwrap().map(|w| w.id), Some(work.id.clone()));
+        assert_eq!(app.active().unwrap().id, work.id);
+        // Moved over to the work database
+        app.database()
+            .unwrap()
+            .create_project(CreateProject {
+                name: "Launch".to_string(),
+                description: None,
+                color: "#3b82f6".to_string(),
+                auto_archive: false,
+            })
+            .unwrap();
+        assert_eq!(Database::open(app.path(&work)).unwrap().get_projects(false).unwrap().len(), 1);
+        assert!(app.reload().unwrap().is_none());
+    }
+
+    #[test]
+    fn workspaces_open_elsewhere_are_not_rewritten_or_deleted() {
+        let root = TempRoot::new();
+        let app = Workspaces::load(&root.0).unwrap();
+        app.open().unwrap();
+        let cli = Workspaces::load(&root.0).unwrap();
+
+        let refused = cli.encrypt("correct horse").unwrap_err();
+        assert!(refused.contains("open in another process"), "{}", refused);
+        assert!(!cli.active().unwrap().encrypted);
+        assert!(!Workspaces::load(&root.0).unwrap().active().unwrap().encrypted);
+
+        let work = cli.create("Work", None).unwrap();
+        app.switch(&work.id).unwrap();
+        cli.switch(DEFAULT_WORKSPACE_ID).unwrap();
+        // Until the app follows the switch, it still has the work database open
+        assert!(cli.delete(&work.id).unwrap_err().contains("open in another process"));
+        app.reload().unwrap();
+        cli.delete(&work.id).unwrap();
+        assert!(!app.path(&work).exists());
+        assert!(cli.encrypt("correct horse").unwrap_err().contains("open in another process"));
+    }
+
+    #[test]
+    fn encrypted_workspaces_open_only_with_their_passphrase() {
+        let root = TempRoot::new();
+        let app = Workspaces::load(&root.0).unwrap();
+        app.open().unwrap();
+        let changes = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counted = changes.clone();
+        app.subscribe(move |_| {
+            counted.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        })
+        .unwrap();
+        let add_project = |name: &str| {
+            app.database()
+                .unwrap()
+                .create_project(CreateProject {
+                    name: name.to_string(),
+                    description: None,
+                    color: "#3b82f6".to_string(),
+                    auto_archive: false,
+                })
+                .unwrap();
+        };
+        add_project("Launch");
+
+        let workspace = app.encrypt("correct horse").unwrap();
+        assert!(workspace.encrypted);
+        let path = app.path(&workspace);
+        assert!(Database::open(&path).err().is_some_and(|e| database::is_wrong_passphrase(&e)));
+
+        app.lock().unwrap();
+        assert!(app.lock_status().unwrap().locked);
+        assert!(app.database().is_err());
+        assert_eq!(app.unlock("wrong horse").unwrap_err(), "Wrong passphrase");
+        app.unlock("correct horse").unwrap();
+        // Listeners outlive locking, and the data survived encryption
+        add_project("Beta");
+        assert_eq!(changes.load(std::sync::atomic::Ordering::SeqCst), 2);
+        assert_eq!(app.database().unwrap().get_projects(false).unwrap().len(), 2);
+
+        assert_eq!(app.change_passphrase("wrong horse", "battery staple").unwrap_err(), "Wrong passphrase");
+        app.change_passphrase("correct horse", "battery staple").unwrap();
+        app.set_auto_lock(Some(1)).unwrap();
+        assert!(!app.lock_if_idle(Instant::now()).unwrap());
+        assert!(app.lock_if_idle(Instant::now() + Duration::from_secs(61)).unwrap());
+        assert!(app.lock_status().unwrap().locked);
+        assert_eq!(app.unlock("correct horse").unwrap_err(), "Wrong passphrase");
+        app.unlock("battery staple").unwrap();
+
+        assert_eq!(app.decrypt("correct horse").unwrap_err(), "Wrong passphrase");
+        let workspace = app.decrypt("battery staple").unwrap();
+        assert!(!workspace.encrypted && workspace.auto_lock_minutes.is_none());
+        assert_eq!(app.database().unwrap().get_projects(false).unwrap().len(), 2);
+        drop(app);
+        assert_eq!(Database::open(&path).unwrap().get_projects(false).unwrap().len(), 2);
+    }
+}
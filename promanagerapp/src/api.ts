@@ -1,4 +1,5 @@
 import { invoke } from '@tauri-apps/api/tauri';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
 import { 
   Project, 
   Task, 
@@ -6,21 +7,156 @@ import {
   UpdateProject, 
   CreateTask, 
   UpdateTask, 
-  TaskStats 
+  TaskStats,
+  DashboardStats,
+  ApiServerInfo,
+  ChangeEvent,
+  ActivityPage,
+  StatusTransition,
+  FlowMetrics,
+  BurndownPoint,
+  ChartUnit,
+  CumulativeFlowPoint,
+  Forecast,
+  Milestone,
+  CreateMilestone,
+  UpdateMilestone,
+  MilestoneProgress,
+  Sprint,
+  CreateSprint,
+  UpdateSprint,
+  SprintReport,
+  ProjectTemplate,
+  SaveProjectTemplate,
+  UpdateProjectTemplate,
+  InstantiateTemplate,
+  BatchOperation,
+  BatchResult,
+  Reminder,
+  CreateReminder,
+  ReminderNotification,
+  Comment,
+  CreateComment,
+  UpdateComment,
+  Person,
+  CreatePerson,
+  UpdatePerson,
+  Workload,
+  ChecklistItem,
+  CreateChecklistItem,
+  Attachment,
+  DigestPeriod,
+  DigestFormat,
+  DigestSchedule,
+  Workspace,
+  LockStatus
 } from './types';
 
+// Workspace API
+export const workspaceApi = {
+  getAll: async (): Promise<Workspace[]> => {
+    return await invoke('get_workspaces');
+  },
+
+  getActive: async (): Promise<Workspace> => {
+    return await invoke('get_active_workspace');
+  },
+
+  // Creates an empty workspace without switching to it, encrypted when a
+  // passphrase is given
+  create: async (name: string, passphrase?: string): Promise<Workspace> => {
+    return await invoke('create_workspace', { name, passphrase });
+  },
+
+  // Every window gets a workspace-switched event, see listen
+  switch: async (id: string): Promise<Workspace> => {
+    return await invoke('switch_workspace', { id });
+  },
+
+  rename: async (id: string, name: string): Promise<Workspace> => {
+    return await invoke('rename_workspace', { id, name });
+  },
+
+  // The active workspace cannot be deleted
+  delete: async (id: string): Promise<void> => {
+    return await invoke('delete_workspace', { id });
+  },
+
+  // Fired after a switch; everything shown should be reloaded
+  listen: (handler: (workspace: Workspace) => void): Promise<UnlistenFn> => {
+    return listen<Workspace>('workspace-switched', (event) => handler(event.payload));
+  },
+};
+
+// Encryption API, always about the active workspace. While it is locked every
+// other command fails with "The workspace is locked".
+export const encryptionApi = {
+  getStatus: async (): Promise<LockStatus> => {
+    return await invoke('get_lock_status');
+  },
+
+  // Fails with "Wrong passphrase"
+  unlock: async (passphrase: string): Promise<LockStatus> => {
+    return await invoke('unlock_workspace', { passphrase });
+  },
+
+  lock: async (): Promise<LockStatus> => {
+    return await invoke('lock_workspace');
+  },
+
+  // Passphrases need at least 8 characters. Only the database is encrypted;
+  // attachments stay readable on disk
+  encrypt: async (passphrase: string): Promise<Workspace> => {
+    return await invoke('encrypt_workspace', { passphrase });
+  },
+
+  decrypt: async (passphrase: string): Promise<Workspace> => {
+    return await invoke('decrypt_workspace', { passphrase });
+  },
+
+  changePassphrase: async (currentPassphrase: string, newPassphrase: string): Promise<void> => {
+    return await invoke('change_passphrase', { currentPassphrase, newPassphrase });
+  },
+
+  // null never locks automatically
+  setAutoLock: async (minutes: number | null): Promise<Workspace> => {
+    return await invoke('set_auto_lock', { minutes });
+  },
+
+  // Fired when the workspace locks or unlocks, including after being idle
+  listen: (handler: (status: LockStatus) => void): Promise<UnlistenFn> => {
+    return listen<LockStatus>('workspace-lock', (event) => handler(event.payload));
+  },
+};
+
 // Project API
 export const projectApi = {
   create: async (projectData: CreateProject): Promise<Project> => {
     return await invoke('create_project', { projectData });
   },
 
-  getAll: async (): Promise<Project[]> => {
-    return await invoke('get_projects');
+  // Archived projects are left out unless includeArchived is set
+  getAll: async (includeArchived = false): Promise<Project[]> => {
+    return await invoke('get_projects', { includeArchived });
+  },
+
+  // Pass base, the copy that was edited, to merge with changes made since
+  // instead of failing with a conflict
+  update: async (projectData: UpdateProject, base?: Project): Promise<Project> => {
+    return await invoke('update_project', { projectData, base });
+  },
+
+  archive: async (id: string): Promise<Project> => {
+    return await invoke('archive_project', { id });
+  },
+
+  unarchive: async (id: string): Promise<Project> => {
+    return await invoke('unarchive_project', { id });
   },
 
-  update: async (projectData: UpdateProject): Promise<Project> => {
-    return await invoke('update_project', { projectData });
+  // Moves everything from source into target and deletes source
+  merge: async (sourceId: string, targetId: string): Promise<Project> => {
+    return await invoke('merge_projects', { sourceId, targetId });
   },
 
   delete: async (id: string): Promise<void> => {
@@ -28,22 +164,113 @@ export const projectApi = {
   },
 };
 
+// Milestone API
+export const milestoneApi = {
+  create: async (milestoneData: CreateMilestone): Promise<Milestone> => {
+    return await invoke('create_milestone', { milestoneData });
+  },
+
+  // Omit projectId for the roadmap across all projects
+  getAll: async (projectId?: string): Promise<MilestoneProgress[]> => {
+    return await invoke('get_milestones', { projectId });
+  },
+
+  get: async (id: string): Promise<MilestoneProgress> => {
+    return await invoke('get_milestone', { id });
+  },
+
+  update: async (milestoneData: UpdateMilestone): Promise<Milestone> => {
+    return await invoke('update_milestone', { milestoneData });
+  },
+
+  delete: async (id: string): Promise<void> => {
+    return await invoke('delete_milestone', { id });
+  },
+};
+
+// Template API
+export const templateApi = {
+  saveFromProject: async (templateData: SaveProjectTemplate): Promise<ProjectTemplate> => {
+    return await invoke('save_project_as_template', { templateData });
+  },
+
+  getAll: async (): Promise<ProjectTemplate[]> => {
+    return await invoke('get_templates');
+  },
+
+  update: async (templateData: UpdateProjectTemplate): Promise<ProjectTemplate> => {
+    return await invoke('update_template', { templateData });
+  },
+
+  delete: async (id: string): Promise<void> => {
+    return await invoke('delete_template', { id });
+  },
+
+  // Creates a new project with dates shifted to request.start_date
+  instantiate: async (request: InstantiateTemplate): Promise<Project> => {
+    return await invoke('instantiate_template', { request });
+  },
+};
+
+// Sprint API
+export const sprintApi = {
+  create: async (sprintData: CreateSprint): Promise<Sprint> => {
+    return await invoke('create_sprint', { sprintData });
+  },
+
+  getAll: async (): Promise<Sprint[]> => {
+    return await invoke('get_sprints');
+  },
+
+  update: async (sprintData: UpdateSprint): Promise<Sprint> => {
+    return await invoke('update_sprint', { sprintData });
+  },
+
+  delete: async (id: string): Promise<void> => {
+    return await invoke('delete_sprint', { id });
+  },
+
+  addTasks: async (sprintId: string, taskIds: string[]): Promise<SprintReport> => {
+    return await invoke('add_tasks_to_sprint', { sprintId, taskIds });
+  },
+
+  removeTask: async (sprintId: string, taskId: string): Promise<void> => {
+    return await invoke('remove_task_from_sprint', { sprintId, taskId });
+  },
+
+  // Unfinished tasks move to nextSprintId, or to the next open sprint by start date
+  close: async (id: string, nextSprintId?: string): Promise<SprintReport> => {
+    return await invoke('close_sprint', { id, nextSprintId });
+  },
+
+  getReport: async (id: string): Promise<SprintReport> => {
+    return await invoke('get_sprint_report', { id });
+  },
+};
+
 // Task API
 export const taskApi = {
   create: async (taskData: CreateTask): Promise<Task> => {
     return await invoke('create_task', { taskData });
   },
 
-  getAll: async (): Promise<Task[]> => {
-    return await invoke('get_tasks');
+  // Tasks in archived projects are left out unless includeArchived is set
+  getAll: async (includeArchived = false): Promise<Task[]> => {
+    return await invoke('get_tasks', { includeArchived });
   },
 
   getByProject: async (projectId: string): Promise<Task[]> => {
     return await invoke('get_tasks_by_project', { projectId });
   },
 
-  update: async (taskData: UpdateTask): Promise<Task> => {
-    return await invoke('update_task', { taskData });
+  filter: async (query: string): Promise<Task[]> => {
+    return await invoke('filter_tasks', { query });
+  },
+
+  // Pass base, the copy that was edited, to merge with changes made since
+  // instead of failing with a conflict
+  update: async (taskData: UpdateTask, base?: Task): Promise<Task> => {
+    return await invoke('update_task', { taskData, base });
   },
 
   delete: async (id: string): Promise<void> => {
@@ -58,6 +285,20 @@ export const taskApi = {
     return await invoke('get_upcoming_tasks');
   },
 
+  // The checklist and assignees are copied unless turned off
+  duplicate: async (
+    id: string,
+    title?: string,
+    options: { checklist?: boolean; assignees?: boolean } = {}
+  ): Promise<Task> => {
+    return await invoke('duplicate_task', { id, title, ...options });
+  },
+
+  // Omit projectId to take the tasks out of their projects
+  move: async (taskIds: string[], projectId?: string): Promise<Task[]> => {
+    return await invoke('move_tasks', { taskIds, projectId });
+  },
+
   markComplete: async (id: string, completed: boolean): Promise<Task> => {
     return await invoke('mark_task_complete', { id, completed });
   },
@@ -65,4 +306,274 @@ export const taskApi = {
   getStats: async (): Promise<TaskStats> => {
     return await invoke('get_task_stats');
   },
+
+  getDashboardStats: async (days?: number): Promise<DashboardStats> => {
+    return await invoke('get_dashboard_stats', { days });
+  },
+};
+
+// Reminder API
+export const reminderApi = {
+  create: async (reminderData: CreateReminder): Promise<Reminder> => {
+    return await invoke('create_reminder', { reminderData });
+  },
+
+  // Omit taskId for the reminders of every task
+  getAll: async (taskId?: string): Promise<Reminder[]> => {
+    return await invoke('get_reminders', { taskId });
+  },
+
+  // Fires the reminder again in the given number of minutes
+  snooze: async (id: string, minutes: number): Promise<Reminder> => {
+    return await invoke('snooze_reminder', { id, minutes });
+  },
+
+  delete: async (id: string): Promise<void> => {
+    return await invoke('delete_reminder', { id });
+  },
+
+  listen: (handler: (notification: ReminderNotification) => void): Promise<UnlistenFn> => {
+    return listen<ReminderNotification>('reminder', (event) => handler(event.payload));
+  },
+};
+
+// Comment API
+export const commentApi = {
+  add: async (commentData: CreateComment): Promise<Comment> => {
+    return await invoke('add_comment', { commentData });
+  },
+
+  // Oldest first
+  getForTask: async (taskId: string): Promise<Comment[]> => {
+    return await invoke('get_comments', { taskId });
+  },
+
+  edit: async (commentData: UpdateComment): Promise<Comment> => {
+    return await invoke('edit_comment', { commentData });
+  },
+
+  delete: async (id: string): Promise<void> => {
+    return await invoke('delete_comment', { id });
+  },
+};
+
+// People API
+export const peopleApi = {
+  create: async (personData: CreatePerson): Promise<Person> => {
+    return await invoke('create_person', { personData });
+  },
+
+  getAll: async (): Promise<Person[]> => {
+    return await invoke('get_people');
+  },
+
+  update: async (personData: UpdatePerson): Promise<Person> => {
+    return await invoke('update_person', { personData });
+  },
+
+  // Also unassigns them from their tasks
+  delete: async (id: string): Promise<void> => {
+    return await invoke('delete_person', { id });
+  },
+
+  // The person `assignee:me` in task filters refers to
+  getCurrentUser: async (): Promise<Person | null> => {
+    return await invoke('get_current_user');
+  },
+
+  setCurrentUser: async (personId: string | null): Promise<Person | null> => {
+    return await invoke('set_current_user', { personId });
+  },
+
+  // Replaces the assignees of every task; pass no one to unassign them
+  assign: async (taskIds: string[], personIds: string[]): Promise<Task[]> => {
+    return await invoke('assign_tasks', { taskIds, personIds });
+  },
+
+  // Passes all open tasks of fromId to toId, or just takes fromId off them
+  handOver: async (fromId: string, toId?: string): Promise<Task[]> => {
+    return await invoke('hand_over_tasks', { fromId, toId });
+  },
+
+  getWorkload: async (): Promise<Workload[]> => {
+    return await invoke('get_workload');
+  },
+};
+
+// Checklist API
+export const checklistApi = {
+  add: async (itemData: CreateChecklistItem): Promise<ChecklistItem> => {
+    return await invoke('add_checklist_item', { itemData });
+  },
+
+  getForTask: async (taskId: string): Promise<ChecklistItem[]> => {
+    return await invoke('get_checklist', { taskId });
+  },
+
+  toggle: async (id: string): Promise<ChecklistItem> => {
+    return await invoke('toggle_checklist_item', { id });
+  },
+
+  // itemIds must list every item of the task's checklist in the new order
+  reorder: async (taskId: string, itemIds: string[]): Promise<ChecklistItem[]> => {
+    return await invoke('reorder_checklist', { taskId, itemIds });
+  },
+
+  remove: async (id: string): Promise<void> => {
+    return await invoke('remove_checklist_item', { id });
+  },
+
+  // Replaces the item with a task in the same project and milestone
+  convertToTask: async (id: string): Promise<Task> => {
+    return await invoke('convert_checklist_item', { id });
+  },
+};
+
+// Attachment API
+export const attachmentApi = {
+  // path is a file on disk, e.g. from the dialog API; it is copied into the app
+  attach: async (taskId: string, path: string): Promise<Attachment> => {
+    return await invoke('attach_file', { taskId, path });
+  },
+
+  getForTask: async (taskId: string): Promise<Attachment[]> => {
+    return await invoke('get_attachments', { taskId });
+  },
+
+  // Opens the file with the default app for its type
+  open: async (id: string): Promise<void> => {
+    return await invoke('open_attachment', { id });
+  },
+
+  export: async (id: string, destination: string): Promise<void> => {
+    return await invoke('export_attachment', { id, destination });
+  },
+
+  delete: async (id: string): Promise<void> => {
+    return await invoke('delete_attachment', { id });
+  },
+
+  // Removes stored files no attachment refers to; returns how many
+  collectGarbage: async (): Promise<number> => {
+    return await invoke('collect_attachment_garbage');
+  },
+};
+
+// Digest API
+export const digestApi = {
+  generate: async (period: DigestPeriod = 'day', format: DigestFormat = 'markdown'): Promise<string> => {
+    return await invoke('generate_digest', { period, format });
+  },
+
+  getSchedule: async (): Promise<DigestSchedule | null> => {
+    return await invoke('get_digest_schedule');
+  },
+
+  // Pass null to stop writing digests automatically
+  setSchedule: async (schedule: DigestSchedule | null): Promise<void> => {
+    return await invoke('set_digest_schedule', { schedule });
+  },
+};
+
+// Batches run in one transaction: either every operation applies or none does
+export const batchApi = {
+  run: async (operations: BatchOperation[]): Promise<BatchResult[]> => {
+    return await invoke('batch', { operations });
+  },
+};
+
+// Import / export API
+export const interopApi = {
+  importTodoTxt: async (content: string): Promise<Task[]> => {
+    return await invoke('import_todotxt', { content });
+  },
+
+  exportTodoTxt: async (): Promise<string> => {
+    return await invoke('export_todotxt');
+  },
+
+  importMarkdown: async (content: string): Promise<Task[]> => {
+    return await invoke('import_markdown', { content });
+  },
+
+  exportMarkdown: async (): Promise<string> => {
+    return await invoke('export_markdown');
+  },
+};
+
+// REST API server
+export const apiServerApi = {
+  start: async (port?: number): Promise<ApiServerInfo> => {
+    return await invoke('start_api_server', { port });
+  },
+
+  stop: async (): Promise<void> => {
+    return await invoke('stop_api_server');
+  },
+
+  getInfo: async (): Promise<ApiServerInfo | null> => {
+    return await invoke('get_api_server_info');
+  },
+};
+
+// Change events published by the backend after every mutation
+export const changeApi = {
+  listen: (handler: (event: ChangeEvent) => void): Promise<UnlistenFn> => {
+    return listen<ChangeEvent>('db-change', (event) => handler(event.payload));
+  },
+
+  // Fired when another process (e.g. the CLI) wrote to the database
+  listenExternal: (handler: () => void): Promise<UnlistenFn> => {
+    return listen('db-external-change', () => handler());
+  },
+};
+
+// Activity log API
+export const activityApi = {
+  getForTask: async (taskId: string, page = 1, perPage = 50): Promise<ActivityPage> => {
+    return await invoke('get_task_activity', { taskId, page, perPage });
+  },
+
+  getFeed: async (page = 1, perPage = 50): Promise<ActivityPage> => {
+    return await invoke('get_activity_feed', { page, perPage });
+  },
+};
+
+// Analytics API
+export const analyticsApi = {
+  getTransitions: async (taskId: string): Promise<StatusTransition[]> => {
+    return await invoke('get_task_transitions', { taskId });
+  },
+
+  // from/to are ISO date strings; omit them for all time
+  getFlowMetrics: async (projectId?: string, from?: string, to?: string): Promise<FlowMetrics> => {
+    return await invoke('get_flow_metrics', { projectId, from, to });
+  },
+
+  // from/to are YYYY-MM-DD dates, both inclusive
+  getBurndown: async (
+    projectId: string,
+    from: string,
+    to: string,
+    unit: ChartUnit = 'tasks'
+  ): Promise<BurndownPoint[]> => {
+    return await invoke('get_burndown', { projectId, from, to, unit });
+  },
+
+  getCumulativeFlow: async (
+    projectId: string,
+    from: string,
+    to: string,
+    unit: ChartUnit = 'tasks'
+  ): Promise<CumulativeFlowPoint[]> => {
+    return await invoke('get_cumulative_flow', { projectId, from, to, unit });
+  },
+
+  // Pass a seed to get the same forecast every time
+  forecast: async (
+    projectId: string,
+    options: { trials?: number; historyDays?: number; seed?: number } = {}
+  ): Promise<Forecast> => {
+    return await invoke('forecast_project', { projectId, ...options });
+  },
 };
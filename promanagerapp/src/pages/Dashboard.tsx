@@ -1,10 +1,30 @@
-import React, { useState, useEffect } from 'react';
+import React, { useState, useEffect, useRef } from 'react';
 import { Plus, Calendar, CheckCircle, Clock, AlertTriangle } from 'lucide-react';
-import { Task, TaskStats, Project, CreateTask } from '../types';
-import { taskApi, projectApi } from '../api';
+import { Task, TaskStats, Project, CreateTask, ChangeEvent } from '../types';
+import { taskApi, projectApi, changeApi } from '../api';
 import { formatDate, isOverdue } from '../utils';
 import { TaskModal } from '../components/TaskModal';
 
+const DAY_MS = 24 * 60 * 60 * 1000;
+
+// Which list a task is shown in, by the same UTC days the backend uses:
+// due today, or due from tomorrow through the week after
+const listFor = (task: Task): 'today' | 'upcoming' | null => {
+  if (!task.due_date) return null;
+  const day = Math.floor(Date.parse(task.due_date) / DAY_MS);
+  const today = Math.floor(Date.now() / DAY_MS);
+  if (day === today) return 'today';
+  if (day > today && day <= today + 8) return 'upcoming';
+  return null;
+};
+
+// Puts `task` into a list ordered by due date, or just takes it out when null
+const placeTask = (tasks: Task[], id: string, task: Task | null): Task[] => {
+  const rest = tasks.filter((t) => t.id !== id);
+  if (!task) return rest;
+  return [...rest, task].sort((a, b) => Date.parse(a.due_date!) - Date.parse(b.due_date!));
+};
+
 export const Dashboard: React.FC = () => {
   const [stats, setStats] = useState<TaskStats | null>(null);
   const [todayTasks, setTodayTasks] = useState<Task[]>([]);
@@ -12,14 +32,25 @@ export const Dashboard: React.FC = () => {
   const [projects, setProjects] = useState<Project[]>([]);
   const [loading, setLoading] = useState(true);
   const [isTaskModalOpen, setIsTaskModalOpen] = useState(false);
+  // The listed projects, for the change listener: tasks of other (archived)
+  // projects stay off the dashboard
+  const projectsRef = useRef<Project[]>([]);
 
   useEffect(() => {
     loadDashboardData();
+
+    const unlisten = changeApi.listen(applyChange);
+    const unlistenExternal = changeApi.listenExternal(() => loadDashboardData(false));
+
+    return () => {
+      unlisten.then((fn) => fn());
+      unlistenExternal.then((fn) => fn());
+    };
   }, []);
 
-  const loadDashboardData = async () => {
+  const loadDashboardData = async (showLoading = true) => {
     try {
-      setLoading(true);
+      if (showLoading) setLoading(true);
       const [statsData, todayData, upcomingData, projectsData] = await Promise.all([
         taskApi.getStats(),
         taskApi.getToday(),
@@ -31,6 +62,7 @@ export const Dashboard: React.FC = () => {
       setTodayTasks(todayData);
       setUpcomingTasks(upcomingData);
       setProjects(projectsData);
+      projectsRef.current = projectsData;
     } catch (error) {
       console.error('Error loading dashboard data:', error);
     } finally {
@@ -38,10 +70,49 @@ export const Dashboard: React.FC = () => {
     }
   };
 
+  // Applies a change to what is on screen. Only the stats are fetched again,
+  // as they are counted server-side.
+  const applyChange = (event: ChangeEvent) => {
+    if (event.entity === 'task') {
+      const task = event.kind === 'deleted' ? null : (event.value as Task);
+      const list =
+        task && (!task.project_id || projectsRef.current.some((p) => p.id === task.project_id))
+          ? listFor(task)
+          : null;
+      setTodayTasks((current) => placeTask(current, event.id, list === 'today' ? task : null));
+      setUpcomingTasks((current) => placeTask(current, event.id, list === 'upcoming' ? task : null));
+      refreshStats();
+    } else if (event.entity === 'project') {
+      const project = event.kind === 'deleted' ? null : (event.value as Project);
+      const shown = project !== null && project.status !== 'archived';
+      const listed = projectsRef.current.some((p) => p.id === event.id);
+      if (shown !== listed && event.kind !== 'created') {
+        // Archiving, unarchiving or deleting a project takes its tasks along
+        loadDashboardData(false);
+        return;
+      }
+      let projects = projectsRef.current.filter((p) => p.id !== event.id);
+      if (project && shown) {
+        projects = listed
+          ? projectsRef.current.map((p) => (p.id === event.id ? project : p))
+          : [project, ...projects];
+      }
+      projectsRef.current = projects;
+      setProjects(projects);
+    }
+  };
+
+  const refreshStats = async () => {
+    try {
+      setStats(await taskApi.getStats());
+    } catch (error) {
+      console.error('Error loading stats:', error);
+    }
+  };
+
   const handleToggleTaskComplete = async (taskId: string, completed: boolean) => {
     try {
       await taskApi.markComplete(taskId, completed);
-      await loadDashboardData(); // Refresh data
     } catch (error) {
       console.error('Error updating task:', error);
     }
@@ -50,7 +121,6 @@ export const Dashboard: React.FC = () => {
   const handleCreateTask = async (taskData: CreateTask) => {
     try {
       await taskApi.create(taskData);
-      await loadDashboardData(); // Refresh data
     } catch (error) {
       console.error('Error creating task:', error);
       throw error;
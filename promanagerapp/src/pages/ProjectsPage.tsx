@@ -1,7 +1,7 @@
 import React, { useState, useEffect } from 'react';
 import { Plus, FolderOpen, Edit, Trash2 } from 'lucide-react';
 import { Project } from '../types';
-import { projectApi } from '../api';
+import { projectApi, changeApi } from '../api';
 import { Link } from 'react-router-dom';
 
 export const ProjectsPage: React.FC = () => {
@@ -10,6 +10,23 @@ export const ProjectsPage: React.FC = () => {
 
   useEffect(() => {
     loadProjects();
+
+    const unlisten = changeApi.listen((event) => {
+      if (event.entity !== 'project') return;
+      setProjects((current) => {
+        const rest = current.filter((p) => p.id !== event.id);
+        const project = event.value as Project;
+        if (event.kind === 'deleted' || project.status === 'archived') return rest;
+        if (event.kind === 'created' || rest.length === current.length) return [project, ...rest];
+        return current.map((p) => (p.id === event.id ? project : p));
+      });
+    });
+    const unlistenExternal = changeApi.listenExternal(() => loadProjects());
+
+    return () => {
+      unlisten.then((fn) => fn());
+      unlistenExternal.then((fn) => fn());
+    };
   }, []);
 
   const loadProjects = async () => {
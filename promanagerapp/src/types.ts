@@ -5,8 +5,13 @@ export interface Project {
   color: string;
   created_at: string; // ISO date string
   updated_at: string; // ISO date string
+  status: ProjectStatus;
+  auto_archive: boolean; // archive once every task is completed
+  revision: number; // increases with every change
 }
 
+export type ProjectStatus = 'active' | 'on_hold' | 'completed' | 'archived';
+
 export interface Task {
   id: string;
   title: string;
@@ -19,6 +24,13 @@ export interface Task {
   completed_at?: string; // ISO date string
   created_at: string; // ISO date string
   updated_at: string; // ISO date string
+  milestone_id?: string;
+  estimate_hours?: number;
+  assignee_ids: string[]; // see peopleApi.assign
+  revision: number; // increases with every change
+  comment_count: number;
+  checklist_total: number;
+  checklist_checked: number;
 }
 
 export type TaskStatus = 'todo' | 'in_progress' | 'done';
@@ -28,13 +40,18 @@ export interface CreateProject {
   name: string;
   description?: string;
   color: string;
+  auto_archive?: boolean;
 }
 
+// In update payloads a missing field is left unchanged and `null` clears it
 export interface UpdateProject {
   id: string;
   name?: string;
-  description?: string;
+  description?: string | null;
   color?: string;
+  status?: ProjectStatus;
+  auto_archive?: boolean;
+  expected_revision?: number; // fail with a conflict if the project has changed since
 }
 
 export interface CreateTask {
@@ -44,17 +61,315 @@ export interface CreateTask {
   status: TaskStatus;
   priority: TaskPriority;
   due_date?: string; // ISO date string
+  estimate_hours?: number;
+  milestone_id?: string; // also moves the task into the milestone's project
 }
 
 export interface UpdateTask {
   id: string;
   title?: string;
-  description?: string;
-  project_id?: string;
+  description?: string | null;
+  project_id?: string | null;
   status?: TaskStatus;
   priority?: TaskPriority;
-  due_date?: string; // ISO date string
+  due_date?: string | null; // ISO date string
+  estimate_hours?: number | null;
   completed?: boolean;
+  milestone_id?: string | null;
+  assignee_ids?: string[]; // replaces the assignees, [] unassigns everyone
+  expected_revision?: number; // fail with a conflict if the task has changed since
+}
+
+// Rejection of taskApi.update and projectApi.update
+export type UpdateError =
+  | {
+      kind: 'conflict';
+      entity: ChangeEntity;
+      expected_revision: number;
+      current: Task | Project;
+      fields: string[]; // changed on both sides, when merging
+    }
+  | { kind: 'error'; message: string };
+
+export type BatchOperation =
+  | { op: 'create_project'; project: CreateProject }
+  | { op: 'update_project'; project: UpdateProject }
+  | { op: 'delete_project'; id: string }
+  | { op: 'create_task'; task: CreateTask }
+  | { op: 'update_task'; task: UpdateTask }
+  | { op: 'complete_task'; id: string; completed?: boolean }
+  | { op: 'delete_task'; id: string };
+
+export type BatchResult =
+  | { type: 'project'; value: Project }
+  | { type: 'task'; value: Task }
+  | { type: 'deleted'; value: string };
+
+export interface Milestone {
+  id: string;
+  project_id: string;
+  name: string;
+  description?: string;
+  target_date: string; // ISO date string
+  created_at: string; // ISO date string
+  updated_at: string; // ISO date string
+}
+
+export interface CreateMilestone {
+  project_id: string;
+  name: string;
+  description?: string;
+  target_date: string; // ISO date string
+}
+
+export interface UpdateMilestone {
+  id: string;
+  name?: string;
+  description?: string | null;
+  target_date?: string; // ISO date string
+}
+
+export type MilestoneHealth = 'on_track' | 'at_risk' | 'overdue' | 'completed';
+
+export interface MilestoneProgress extends Milestone {
+  total_tasks: number;
+  completed_tasks: number;
+  progress: number;
+  days_left: number; // negative once the target date has passed
+  health: MilestoneHealth;
+}
+
+export interface TemplateMilestone {
+  name: string;
+  description?: string;
+  target_offset_minutes: number; // from the start of the start day
+}
+
+export interface TemplateTask {
+  title: string;
+  description?: string;
+  priority: TaskPriority;
+  due_offset_minutes?: number; // from the start of the start day
+  estimate_hours?: number;
+  milestone?: number; // index into milestones
+}
+
+export interface ProjectTemplate {
+  id: string;
+  name: string;
+  description?: string;
+  project_name: string;
+  project_description?: string;
+  color: string;
+  milestones: TemplateMilestone[];
+  tasks: TemplateTask[];
+  variables: string[]; // placeholders that need values when instantiating
+  created_at: string; // ISO date string
+  updated_at: string; // ISO date string
+}
+
+export interface SaveProjectTemplate {
+  project_id: string;
+  name: string;
+  description?: string;
+}
+
+export interface UpdateProjectTemplate {
+  id: string;
+  name?: string;
+  description?: string | null;
+}
+
+export interface InstantiateTemplate {
+  template_id: string;
+  start_date: string; // YYYY-MM-DD
+  project_name?: string;
+  variables?: Record<string, string>;
+}
+
+export type SprintStatus = 'planned' | 'active' | 'closed';
+export type SprintOutcome = 'completed' | 'carried_over' | 'incomplete';
+
+export interface Sprint {
+  id: string;
+  name: string;
+  goal?: string;
+  start_date: string; // ISO date string
+  end_date: string; // ISO date string
+  capacity?: number; // tasks the team expects to finish
+  status: SprintStatus;
+  closed_at?: string; // ISO date string
+  created_at: string; // ISO date string
+  updated_at: string; // ISO date string
+}
+
+export interface CreateSprint {
+  name: string;
+  goal?: string;
+  start_date: string; // ISO date string
+  end_date: string; // ISO date string
+  capacity?: number;
+}
+
+export interface UpdateSprint {
+  id: string;
+  name?: string;
+  goal?: string | null;
+  start_date?: string; // ISO date string
+  end_date?: string; // ISO date string
+  capacity?: number | null;
+}
+
+export interface SprintTask extends Task {
+  added_at: string; // ISO date string
+  carried_over_from?: string;
+  committed: boolean;
+  completed: boolean;
+  outcome?: SprintOutcome; // set once the sprint is closed
+}
+
+export interface SprintReport {
+  sprint: Sprint;
+  committed_tasks: number;
+  added_tasks: number;
+  completed_tasks: number;
+  carried_over_tasks: number;
+  completion_rate: number;
+  carried_over_to?: string;
+  tasks: SprintTask[];
+}
+
+// Either at a fixed time or a number of minutes before the task is due
+export interface Reminder {
+  id: string;
+  task_id: string;
+  remind_at?: string; // ISO date string
+  minutes_before_due?: number;
+  snoozed_until?: string; // ISO date string
+  fired_at?: string; // ISO date string
+  created_at: string; // ISO date string
+  next_at?: string; // ISO date string; unset once it won't fire again
+}
+
+export interface CreateReminder {
+  task_id: string;
+  remind_at?: string; // ISO date string
+  minutes_before_due?: number;
+}
+
+// body is Markdown
+export interface Comment {
+  id: string;
+  task_id: string;
+  author: string;
+  body: string;
+  created_at: string; // ISO date string
+  edited_at?: string; // ISO date string; unset until the body is changed
+}
+
+export interface CreateComment {
+  task_id: string;
+  author: string;
+  body: string;
+}
+
+export interface UpdateComment {
+  id: string;
+  body: string;
+}
+
+// Each workspace has a database of its own
+export interface Workspace {
+  id: string;
+  name: string; // unique, ignoring case
+  path: string; // database file, relative to the workspace registry
+  created_at: string; // ISO date string
+  encrypted: boolean;
+  auto_lock_minutes?: number; // idle minutes before locking, unset never locks
+}
+
+export interface LockStatus {
+  workspace: Workspace; // the active one
+  locked: boolean;
+  first_run: boolean; // no database existed yet, a good time to offer encryption
+}
+
+export interface Person {
+  id: string;
+  name: string; // unique, ignoring case
+  email?: string;
+  created_at: string; // ISO date string
+}
+
+export interface CreatePerson {
+  name: string;
+  email?: string;
+}
+
+export interface UpdatePerson {
+  id: string;
+  name?: string;
+  email?: string | null;
+}
+
+// A task with several assignees counts in full for each of them
+export interface Workload {
+  person: Person;
+  open_tasks: number;
+  estimate_hours: number;
+  unestimated_tasks: number; // left out of estimate_hours
+  weeks: WeekLoad[];
+}
+
+export interface WeekLoad {
+  week_start?: string; // YYYY-MM-DD, a Monday; unset for tasks without a due date
+  open_tasks: number;
+  estimate_hours: number;
+}
+
+export interface ChecklistItem {
+  id: string;
+  task_id: string;
+  text: string;
+  checked: boolean;
+  position: number; // lowest first
+  created_at: string; // ISO date string
+}
+
+export interface CreateChecklistItem {
+  task_id: string;
+  text: string;
+}
+
+// A file attached to a task; files with the same hash are stored once
+export interface Attachment {
+  id: string;
+  task_id: string;
+  name: string;
+  mime_type: string;
+  size: number; // bytes
+  hash: string; // SHA-256 of the content
+  created_at: string; // ISO date string
+}
+
+// Sent when a reminder fires
+export interface ReminderNotification {
+  reminder_id: string;
+  task_id: string;
+  title: string;
+  body: string;
+}
+
+export type DigestPeriod = 'day' | 'week';
+export type DigestFormat = 'markdown' | 'html';
+
+// Written into folder every day, or every Monday for weekly digests, once
+// hour (UTC) has passed
+export interface DigestSchedule {
+  folder: string;
+  period: DigestPeriod;
+  format: DigestFormat;
+  hour?: number;
 }
 
 export interface TaskStats {
@@ -65,7 +380,114 @@ export interface TaskStats {
   overdue_tasks: number;
 }
 
+export interface ProjectProgress {
+  project_id: string;
+  name: string;
+  color: string;
+  total_tasks: number;
+  completed_tasks: number;
+  progress: number;
+}
+
+export interface DailyCount {
+  date: string;
+  count: number;
+}
+
+export interface DashboardStats {
+  totals: TaskStats;
+  by_status: Record<TaskStatus, number>;
+  by_priority: Record<TaskPriority, number>;
+  projects: ProjectProgress[];
+  completions_per_day: DailyCount[];
+  overdue_trend: DailyCount[];
+  completion_streak: number;
+}
+
 export interface ProjectWithTaskCount extends Project {
   task_count?: number;
   completed_task_count?: number;
 }
+
+export interface ApiServerInfo {
+  url: string;
+  port: number;
+  token: string;
+}
+
+export type ChangeEntity = 'project' | 'task' | 'milestone' | 'sprint' | 'template' | 'reminder' | 'comment' | 'attachment' | 'checklist_item' | 'person';
+export type ChangeKind = 'created' | 'updated' | 'deleted';
+
+export interface ChangeEvent {
+  entity: ChangeEntity;
+  id: string;
+  kind: ChangeKind;
+  value?: Project | Task; // absent for deletions
+}
+
+export type ChangeSource = 'gui' | 'cli' | 'api' | 'import';
+
+export interface Activity {
+  id: number;
+  entity: ChangeEntity;
+  entity_id: string;
+  action: ChangeKind;
+  field?: string; // absent for creations and deletions
+  old_value?: string;
+  new_value?: string;
+  source: ChangeSource;
+  created_at: string; // ISO date string
+}
+
+export interface ActivityPage {
+  items: Activity[];
+  total: number;
+  page: number;
+  per_page: number;
+}
+
+export interface StatusTransition {
+  id: number;
+  task_id: string;
+  from_status?: TaskStatus; // absent for the initial status
+  to_status: TaskStatus;
+  transitioned_at: string; // ISO date string
+}
+
+export interface DurationStats {
+  count: number;
+  mean_hours: number;
+  median_hours: number;
+  p85_hours: number;
+  max_hours: number;
+}
+
+export interface FlowMetrics {
+  lead_time?: DurationStats;
+  cycle_time?: DurationStats;
+  time_in_status: { status: TaskStatus; stats: DurationStats }[];
+}
+
+// What the burndown and cumulative flow charts add up
+export type ChartUnit = 'tasks' | 'estimate_hours';
+
+export interface BurndownPoint {
+  date: string;
+  remaining: number;
+  ideal: number;
+}
+
+export interface CumulativeFlowPoint {
+  date: string;
+  todo: number;
+  in_progress: number;
+  done: number;
+}
+
+export interface Forecast {
+  remaining_tasks: number;
+  trials: number;
+  history_days: number;
+  seed: number;
+  percentiles: { percentile: number; days: number; date: string }[];
+}